@@ -0,0 +1,41 @@
+#!/bin/sh
+# Copy the JSON schemas of Pulser's abstract representation, verbatim, from a Pulser release,
+# and make `src/backend/pulser/schema` validate sequences against them.
+#
+# Usage: scripts/vendor-pulser-schemas.sh <pulser version>, e.g. 0.19.0
+
+set -eu
+
+if [ $# -ne 1 ]; then
+    echo "usage: $0 <pulser version>" >&2
+    exit 1
+fi
+version=$1
+url="https://raw.githubusercontent.com/pasqal-io/Pulser/v$version/pulser-core/pulser/json/abstract_repr/schemas"
+dest="$(dirname "$0")/../src/backend/pulser/schema"
+schemas="sequence device layout register"
+
+for schema in $schemas; do
+    curl --fail --silent --show-error --location "$url/$schema-schema.json" \
+        --output "$dest/$schema-schema.json"
+done
+echo "$version" > "$dest/PULSER_VERSION"
+# The stand-in for the schemas, see `src/backend/pulser/schema/mod.rs`.
+rm -f "$dest/abstract-repr.json"
+
+{
+    echo "// Generated by \`scripts/vendor-pulser-schemas.sh\`, do not edit."
+    echo
+    echo "/// The Pulser release the schemas were copied from, if any."
+    echo "pub const PULSER_VERSION: Option<&str> = Some(\"$version\");"
+    echo
+    echo "const DOCUMENTS: &[(&str, &str)] = &["
+    for schema in $schemas; do
+        echo "    (\"$schema-schema.json\", include_str!(\"$schema-schema.json\")),"
+    done
+    echo "];"
+    echo
+    echo "const ROOT: &str = \"sequence-schema.json\";"
+} > "$dest/documents.rs"
+
+echo "Vendored the schemas of Pulser $version, run \`cargo test\` to check our sequences against them."
@@ -0,0 +1,610 @@
+//! Checks on the result of a compilation.
+//!
+//! The layout only approximates the QUBO weights with Van der Waals interactions: a
+//! [`LayoutReport`] measures how far each interaction is from its weight, and how close
+//! the atoms are to the limits of the device. Because of these approximations, the state
+//! the atoms should end up in may not be an optimal solution to the QUBO. For small
+//! registers, [`GroundStates`] checks by enumerating all configurations.
+
+use std::{fmt::Display, sync::Arc};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    device::{c6::C6Coeff, Device},
+    pulser::{register::Register, sequence::Sequence},
+    qubo::{self, Constraints, Normalization},
+};
+
+/// Relative tolerance when comparing Rydberg energies.
+const ENERGY_TOLERANCE: f64 = 1e-9;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(
+        "cannot analyze {0} atoms, at most {} are supported",
+        qubo::MAX_NODES_BRUTE_FORCE
+    )]
+    TooManyAtoms(usize),
+
+    #[error("the register has {atoms} atoms but the QUBO has {variables} variables")]
+    Mismatch { atoms: usize, variables: usize },
+
+    #[error("cannot determine the final detuning of the sequence")]
+    FinalDetuning,
+
+    #[error("cannot evaluate the QUBO")]
+    Qubo(#[from] qubo::Error),
+}
+
+/// The number of couplings listed in [`LayoutReport::worst_pairs`].
+const WORST_PAIRS: usize = 3;
+
+/// How an interaction between two atoms compares to the QUBO weight it stands for.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Coupling {
+    pub first: Arc<str>,
+    pub second: Arc<str>,
+
+    /// The QUBO weight, in rad/µs.
+    pub expected: f64,
+
+    /// The interaction between the atoms, in rad/µs.
+    pub actual: f64,
+
+    /// `|actual - expected|`, relative to `|expected|`. For couplings whose weight is 0,
+    /// relative to the smallest non-zero weight instead.
+    pub relative_error: f64,
+}
+
+/// How well a register lays out a QUBO, on a device.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LayoutReport {
+    /// The largest relative error of a coupling.
+    pub max_relative_error: f64,
+
+    /// The mean relative error of the couplings.
+    pub mean_relative_error: f64,
+
+    /// The couplings with the largest relative error, worst first.
+    pub worst_pairs: Vec<Coupling>,
+
+    /// The distance from the atom farthest from the center to the edge of the device,
+    /// in µm, negative if the atom is beyond the edge, or `None` if the device is unbounded.
+    pub overflow_margin_um: Option<f64>,
+
+    /// The smallest distance between two atoms, in µm, or `None` with a single atom.
+    pub min_spacing_um: Option<f64>,
+
+    /// The smallest distance between two atoms allowed by the device, in µm.
+    pub device_min_spacing_um: f64,
+}
+
+impl LayoutReport {
+    /// Measure how well `register` lays out `constraints` on `device`.
+    ///
+    /// Atom `i` of `register` stands for variable `i` of `constraints`.
+    pub fn new(
+        constraints: &Constraints,
+        register: &Register,
+        device: &Device,
+    ) -> Result<Self, Error> {
+        let n = register.len();
+        if n != constraints.num_nodes() {
+            return Err(Error::Mismatch {
+                atoms: n,
+                variables: constraints.num_nodes(),
+            });
+        }
+        let pairs = (0..n).tuple_combinations::<(usize, usize)>().collect_vec();
+        let weights = pairs
+            .iter()
+            .map(|&(i, j)| constraints.at(i, j))
+            .collect::<Result<Vec<_>, _>>()?;
+        let smallest = weights
+            .iter()
+            .map(|w| w.abs())
+            .filter(|w| *w > 0.)
+            .reduce(f64::min)
+            .unwrap_or(1.);
+        let mut couplings = pairs
+            .iter()
+            .zip(weights)
+            .map(|(&(i, j), expected)| {
+                let (first, first_name) = &register.coordinates[i];
+                let (second, second_name) = &register.coordinates[j];
+                let actual = device.interaction(*first, *second);
+                let scale = if expected == 0. {
+                    smallest
+                } else {
+                    expected.abs()
+                };
+                Coupling {
+                    first: first_name.clone(),
+                    second: second_name.clone(),
+                    expected,
+                    actual,
+                    relative_error: (actual - expected).abs() / scale,
+                }
+            })
+            .collect_vec();
+        couplings.sort_by(|a, b| b.relative_error.total_cmp(&a.relative_error));
+        let max_relative_error = couplings.first().map_or(0., |c| c.relative_error);
+        let mean_relative_error = if couplings.is_empty() {
+            0.
+        } else {
+            couplings.iter().map(|c| c.relative_error).sum::<f64>() / couplings.len() as f64
+        };
+        couplings.truncate(WORST_PAIRS);
+
+        let max_sq_distance = register
+            .coordinates
+            .iter()
+            .map(|(c, _)| c.x.into_inner().powi(2) + c.y.into_inner().powi(2))
+            .fold(0., f64::max);
+        let overflow_margin_um = Some(device.max_sq_distance_to_center())
+            .filter(|max| max.is_finite())
+            .map(|max| max.sqrt() - max_sq_distance.sqrt());
+        let min_spacing_um = pairs
+            .iter()
+            .map(|&(i, j)| {
+                let (first, _) = &register.coordinates[i];
+                let (second, _) = &register.coordinates[j];
+                first.sqdist(second).into_inner().sqrt()
+            })
+            .reduce(f64::min);
+        Ok(Self {
+            max_relative_error,
+            mean_relative_error,
+            worst_pairs: couplings,
+            overflow_margin_um,
+            min_spacing_um,
+            device_min_spacing_um: device.min_atom_distance(),
+        })
+    }
+
+    /// Why the layout is not acceptable, if it isn't.
+    pub fn rejection(&self, acceptance: &Acceptance) -> Option<String> {
+        if let Some(max) = acceptance.max_relative_error {
+            if self.max_relative_error > max {
+                return Some(format!(
+                    "max relative error {:.1}% above {:.1}%",
+                    self.max_relative_error * 100.,
+                    max * 100.
+                ));
+            }
+        }
+        if let Some(max) = acceptance.max_mean_relative_error {
+            if self.mean_relative_error > max {
+                return Some(format!(
+                    "mean relative error {:.1}% above {:.1}%",
+                    self.mean_relative_error * 100.,
+                    max * 100.
+                ));
+            }
+        }
+        if let (Some(min), Some(margin)) =
+            (acceptance.min_overflow_margin_um, self.overflow_margin_um)
+        {
+            if margin < min {
+                return Some(format!("overflow margin {margin:.2} µm below {min} µm"));
+            }
+        }
+        if let (Some(min), Some(spacing)) = (acceptance.min_spacing_um, self.min_spacing_um) {
+            if spacing < min {
+                return Some(format!("min spacing {spacing:.2} µm below {min} µm"));
+            }
+        }
+        None
+    }
+}
+
+/// One line per metric.
+impl Display for LayoutReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "couplings off by {:.1}% at most, {:.1}% on average",
+            self.max_relative_error * 100.,
+            self.mean_relative_error * 100.
+        )?;
+        if !self.worst_pairs.is_empty() {
+            let pairs = self
+                .worst_pairs
+                .iter()
+                .map(|c| {
+                    format!(
+                        "{}-{} {:.3} for {:.3} rad/µs ({:.1}%)",
+                        c.first,
+                        c.second,
+                        c.actual,
+                        c.expected,
+                        c.relative_error * 100.
+                    )
+                })
+                .join(", ");
+            writeln!(f, "worst couplings: {pairs}")?;
+        }
+        match self.overflow_margin_um {
+            Some(margin) => write!(f, "overflow margin {margin:.2} µm")?,
+            None => write!(f, "no overflow, the device is unbounded")?,
+        }
+        if let Some(spacing) = self.min_spacing_um {
+            write!(
+                f,
+                ", min spacing {spacing:.2} µm (device minimum {} µm)",
+                self.device_min_spacing_um
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Thresholds on a [`LayoutReport`] for a layout to be accepted, besides its quality.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Acceptance {
+    /// The largest acceptable relative error of a coupling, e.g. `0.1` for 10%.
+    #[serde(default)]
+    pub max_relative_error: Option<f64>,
+
+    /// The largest acceptable mean relative error of the couplings.
+    #[serde(default)]
+    pub max_mean_relative_error: Option<f64>,
+
+    /// The smallest acceptable distance to the edge of the device, in µm.
+    #[serde(default)]
+    pub min_overflow_margin_um: Option<f64>,
+
+    /// The smallest acceptable distance between two atoms, in µm.
+    #[serde(default)]
+    pub min_spacing_um: Option<f64>,
+}
+
+/// The ground states of the Rydberg Hamiltonian at the end of the sequence, compared to
+/// the optimal solutions of the QUBO.
+///
+/// Once the amplitude is back to zero, the Hamiltonian is classical: the energy of a
+/// configuration `z` is `Σ_{i<j} C6 / r_ij^6 z_i z_j - δ Σ_i z_i`, with `δ` the final
+/// detuning. QUBO values are reported in the same convention, counting each pair once, see
+/// [`Constraints::pair_energy`], so that they may be compared to the Rydberg energy.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct GroundStates {
+    /// The final detuning, in rad/µs.
+    pub detuning: f64,
+
+    /// The configurations of minimal Rydberg energy, as bitstrings in the order of the
+    /// QUBO variables.
+    pub rydberg: Vec<String>,
+
+    /// The minimal Rydberg energy, in rad/µs.
+    pub rydberg_energy: f64,
+
+    /// The optimal solutions of the QUBO.
+    pub qubo: Vec<String>,
+
+    /// The value of the QUBO at its optimal solutions, counting each pair once.
+    pub qubo_energy: f64,
+
+    /// The largest value of the QUBO among the Rydberg ground states, counting each pair
+    /// once.
+    pub worst_energy: f64,
+}
+
+impl GroundStates {
+    /// Compare the ground states of the Rydberg energy, for atoms in `register` detuned by
+    /// `detuning` rad/µs, to the optimal solutions of `constraints`.
+    ///
+    /// Atom `i` of `register` stands for variable `i` of `constraints`.
+    pub fn new(
+        constraints: &Constraints,
+        register: &Register,
+        c6: C6Coeff,
+        detuning: f64,
+    ) -> Result<Self, Error> {
+        let n = register.len();
+        if n != constraints.num_nodes() {
+            return Err(Error::Mismatch {
+                atoms: n,
+                variables: constraints.num_nodes(),
+            });
+        }
+        if n > qubo::MAX_NODES_BRUTE_FORCE {
+            return Err(Error::TooManyAtoms(n));
+        }
+        let c6 = c6.value_rad_per_us_times_um_6();
+        let interactions = register
+            .coordinates
+            .iter()
+            .enumerate()
+            .map(|(i, (first, _))| {
+                register
+                    .coordinates
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (second, _))| {
+                        if i == j {
+                            0.
+                        } else {
+                            c6 / first.sqdist(second).cube().into_inner()
+                        }
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        // Enumerate configurations in Gray code order, so that each one differs from the
+        // previous one by a single atom and its energy is updated in O(n).
+        let scale = interactions
+            .iter()
+            .flatten()
+            .fold(detuning.abs(), |max, u| max.max(*u));
+        let tolerance = ENERGY_TOLERANCE * scale.max(1.);
+        let mut excited = vec![false; n];
+        // For each atom, the interactions with the excited atoms.
+        let mut fields = vec![0.; n];
+        let mut energy = 0.;
+        let mut best = 0.;
+        let mut ground_states = vec![0u64];
+        for step in 1u64..(1 << n) {
+            let k = step.trailing_zeros() as usize;
+            let delta = fields[k] - detuning;
+            let sign = if excited[k] { -1. } else { 1. };
+            energy += sign * delta;
+            excited[k] = !excited[k];
+            for (field, interaction) in fields.iter_mut().zip(&interactions[k]) {
+                *field += sign * interaction;
+            }
+            let config = step ^ (step >> 1);
+            if energy < best - tolerance {
+                best = energy;
+                ground_states.clear();
+            }
+            if energy <= best + tolerance {
+                ground_states.push(config);
+            }
+        }
+        let rydberg = ground_states
+            .into_iter()
+            .map(|config| {
+                (0..n)
+                    .map(|i| if config & (1 << i) != 0 { '1' } else { '0' })
+                    .collect::<String>()
+            })
+            .sorted()
+            .collect_vec();
+
+        let qubo = constraints.ground_states()?;
+        let qubo_energy = constraints.pair_energy(&qubo[0])?;
+        let worst_energy = rydberg
+            .iter()
+            .map(|bitstring| constraints.pair_energy(bitstring))
+            .fold_ok(f64::NEG_INFINITY, f64::max)?;
+        Ok(Self {
+            detuning,
+            rydberg,
+            rydberg_energy: best,
+            qubo,
+            qubo_energy,
+            worst_energy,
+        })
+    }
+
+    /// Analyze `sequence`, compiled from `constraints`.
+    pub fn of(constraints: &Constraints, sequence: &Sequence) -> Result<Self, Error> {
+        let detuning = final_detuning(sequence).ok_or(Error::FinalDetuning)?;
+        Self::new(
+            constraints,
+            sequence.register(),
+            sequence.device().interaction_coeff(),
+            detuning,
+        )
+    }
+
+    /// Whether every ground state of the Rydberg Hamiltonian is an optimal solution.
+    pub fn is_preserved(&self) -> bool {
+        self.rydberg.iter().all(|state| self.qubo.contains(state))
+    }
+
+    /// The optimal solutions which are not ground states of the Rydberg Hamiltonian, and
+    /// which the sequence is therefore unlikely to find.
+    pub fn unreachable(&self) -> impl Iterator<Item = &String> {
+        self.qubo
+            .iter()
+            .filter(|state| !self.rydberg.contains(state))
+    }
+}
+
+/// A summary of [`GroundStates`], with QUBO values in the units of the problem.
+pub struct Summary<'a> {
+    ground_states: &'a GroundStates,
+    normalization: Option<&'a Normalization>,
+}
+
+impl GroundStates {
+    /// Summarize, converting QUBO values back to problem units if the QUBO was normalized.
+    pub fn summary<'a>(&'a self, normalization: Option<&'a Normalization>) -> Summary<'a> {
+        Summary {
+            ground_states: self,
+            normalization,
+        }
+    }
+}
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let analysis = self.ground_states;
+        let list = |states: &[String]| states.iter().join(", ");
+        let value = |energy: f64| match self.normalization {
+            Some(normalization) => normalization.to_problem_units(energy),
+            None => energy,
+        };
+        if analysis.is_preserved() {
+            write!(
+                f,
+                "the ground states of the Rydberg Hamiltonian ({}) are optimal solutions",
+                list(&analysis.rydberg)
+            )?;
+            match analysis.unreachable().count() {
+                0 => Ok(()),
+                1 => write!(f, ", another QUBO optimum is not a Rydberg ground state"),
+                unreachable => write!(
+                    f,
+                    ", {unreachable} other QUBO optima are not Rydberg ground states"
+                ),
+            }
+        } else {
+            write!(
+                f,
+                "compilation changed the answer: the ground states of the Rydberg Hamiltonian ({}) have a QUBO value up to {:.4}, the optimal solutions ({}) have {:.4}",
+                list(&analysis.rydberg),
+                value(analysis.worst_energy),
+                list(&analysis.qubo),
+                value(analysis.qubo_energy)
+            )
+        }
+    }
+}
+
+/// The detuning at the end of the last pulse, in rad/µs, with variables at their default
+/// value.
+pub fn final_detuning(sequence: &Sequence) -> Option<f64> {
+    let sequence = sequence.bind(&Default::default()).ok()?;
+    let pulse = sequence.pulses().last()?;
+    let duration = pulse.detuning().duration().as_value()?;
+    pulse.detuning().value_at(duration)
+}
+
+#[test]
+fn test_ground_states() {
+    use crate::{backend::device::Device, types::units::Coordinates};
+
+    let names = vec!["a".into(), "b".into(), "c".into()];
+    // a and b should not both be chosen, nor b and c. Choosing a and c is optimal.
+    let constraints =
+        Constraints::from_const([[-1., 4., 0.], [4., -1., 4.], [0., 4., -1.]], names.clone());
+    let c6 = Device::analog().interaction_coeff();
+    let register = |positions: &[(f64, f64)]| Register {
+        coordinates: positions
+            .iter()
+            .zip(&names)
+            .map(|(&(x, y), name)| (Coordinates::new(x, y), name.clone()))
+            .collect(),
+    };
+
+    // A chain, with blockaded neighbours and a weak interaction between a and c.
+    let chain = register(&[(0., 0.), (6., 0.), (12., 0.)]);
+    let analysis = GroundStates::new(&constraints, &chain, c6, 1.).unwrap();
+    assert_eq!(analysis.rydberg, vec!["101"]);
+    assert_eq!(analysis.qubo, vec!["101"]);
+    assert!(analysis.is_preserved());
+    assert_eq!(analysis.unreachable().count(), 0);
+
+    // A triangle: a and c are blockaded too, so only one atom is excited.
+    let triangle = register(&[(0., 0.), (6., 0.), (3., 5.)]);
+    let analysis = GroundStates::new(&constraints, &triangle, c6, 1.).unwrap();
+    assert_eq!(analysis.rydberg, vec!["001", "010", "100"]);
+    assert!(!analysis.is_preserved());
+    assert_eq!(analysis.worst_energy, -1.);
+    let summary = analysis.summary(None).to_string();
+    assert!(summary.contains("up to -1.0000"), "{summary}");
+
+    // With a normalized QUBO, values are reported in problem units.
+    let normalization = Normalization {
+        factor: 2.,
+        reasoning: vec![],
+    };
+    let summary = analysis.summary(Some(&normalization)).to_string();
+    assert!(summary.contains("up to -0.5000"), "{summary}");
+    assert!(summary.contains("have -1.0000"), "{summary}");
+
+    // When the interactions and the detuning match the QUBO exactly, the optima agree and
+    // so do the energies, in spite of the interactions between the two excited atoms.
+    let device = Device::analog();
+    let exact = register(&[(0., 0.), (5., 0.), (0., 10.)]);
+    let weights = (0..3)
+        .map(|i| {
+            (0..3)
+                .map(|j| {
+                    if i == j {
+                        -1.
+                    } else {
+                        device.interaction(exact.coordinates[i].0, exact.coordinates[j].0)
+                    }
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+    let constraints = Constraints::try_new(3, weights.concat(), names.clone()).unwrap();
+    let analysis = GroundStates::new(&constraints, &exact, c6, 1.).unwrap();
+    assert_eq!(analysis.rydberg, vec!["011"]);
+    assert_eq!(analysis.qubo, vec!["011"]);
+    assert!((analysis.rydberg_energy - analysis.qubo_energy).abs() < 1e-9);
+    assert!(analysis.qubo_energy < constraints.energy("011").unwrap());
+
+    // QUBO optima missing from the Rydberg ground states.
+    let partial = GroundStates {
+        detuning: 1.,
+        rydberg: vec!["01".into()],
+        rydberg_energy: -1.,
+        qubo: vec!["01".into(), "10".into()],
+        qubo_energy: -1.,
+        worst_energy: -1.,
+    };
+    assert_eq!(
+        partial.summary(None).to_string(),
+        "the ground states of the Rydberg Hamiltonian (01) are optimal solutions, another QUBO optimum is not a Rydberg ground state"
+    );
+
+    assert!(matches!(
+        GroundStates::new(&constraints, &register(&[(0., 0.), (6., 0.)]), c6, 1.),
+        Err(Error::Mismatch { .. })
+    ));
+}
+
+#[test]
+fn test_layout_report() {
+    use crate::types::units::Coordinates;
+
+    let device = Device::analog();
+    let names: Vec<Arc<str>> = vec!["a".into(), "b".into(), "c".into()];
+    let register = Register {
+        coordinates: [(0., 0.), (6., 0.), (12., 0.)]
+            .iter()
+            .zip(&names)
+            .map(|(&(x, y), name)| (Coordinates::new(x, y), name.clone()))
+            .collect(),
+    };
+    let ab = device.interaction(register.coordinates[0].0, register.coordinates[1].0);
+    let constraints = Constraints::from_const(
+        [[-1., ab, 0.], [ab, -1., 2. * ab], [0., 2. * ab, -1.]],
+        names,
+    );
+    let report = LayoutReport::new(&constraints, &register, &device).unwrap();
+    // a-b is exact, b-c is half of its weight, a-c is spurious.
+    assert_eq!(report.worst_pairs.len(), 3);
+    assert_eq!(&*report.worst_pairs[0].first, "b");
+    assert!((report.worst_pairs[0].relative_error - 0.5).abs() < 1e-9);
+    assert!((report.max_relative_error - 0.5).abs() < 1e-9);
+    assert!(report.worst_pairs[2].relative_error < 1e-9);
+    assert!((report.min_spacing_um.unwrap() - 6.).abs() < 1e-9);
+    assert!((report.overflow_margin_um.unwrap() - 23.).abs() < 1e-9);
+
+    assert!(report.rejection(&Acceptance::default()).is_none());
+    let acceptance = Acceptance {
+        max_relative_error: Some(0.4),
+        ..Default::default()
+    };
+    assert!(report
+        .rejection(&acceptance)
+        .unwrap()
+        .contains("max relative error"));
+    let acceptance = Acceptance {
+        min_spacing_um: Some(7.),
+        ..Default::default()
+    };
+    assert!(report
+        .rejection(&acceptance)
+        .unwrap()
+        .contains("min spacing"));
+}
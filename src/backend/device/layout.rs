@@ -1,10 +1,273 @@
-use std::sync::Arc;
+//! Layouts of traps, in which atoms may be placed.
 
+use std::{fmt::Display, sync::Arc};
+
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+/// The precision of coordinates, in decimal places, as in Pulser.
+const COORD_PRECISION: i32 = 6;
+
 #[derive(Clone)]
 pub struct Layout {
     pub coordinates: Arc<[[f64; 2]]>,
+
+    /// How the layout was generated.
+    pub kind: Kind,
+}
+
+/// How a layout was generated.
+///
+/// This determines the slug used by Pulser to describe the layout.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Kind {
+    /// A triangular lattice with `n_traps` traps, filled ring by ring from the center.
+    Triangular { n_traps: usize, spacing: f64 },
+
+    /// A square lattice.
+    Square {
+        rows: usize,
+        columns: usize,
+        spacing: f64,
+    },
+
+    /// A rectangular lattice.
+    Rectangular {
+        rows: usize,
+        columns: usize,
+        col_spacing: f64,
+        row_spacing: f64,
+    },
+
+    /// A layout with arbitrary coordinates, described by its slug, if any.
+    Custom { slug: Option<Arc<str>> },
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Floats are formatted with `{:?}` to match Python, e.g. `5.0` rather than `5`.
+        match *self {
+            Kind::Triangular { n_traps, spacing } => {
+                write!(f, "TriangularLatticeLayout({n_traps}, {spacing:?}µm)")
+            }
+            Kind::Square {
+                rows,
+                columns,
+                spacing,
+            } => write!(f, "SquareLatticeLayout({rows}x{columns}, {spacing:?}µm)"),
+            Kind::Rectangular {
+                rows,
+                columns,
+                col_spacing,
+                row_spacing,
+            } => write!(
+                f,
+                "RectangularLatticeLayout({rows}x{columns}, {col_spacing:?}x{row_spacing:?}µm)"
+            ),
+            Kind::Custom {
+                slug: Some(ref slug),
+            } => slug.fmt(f),
+            Kind::Custom { slug: None } => write!(f, "RegisterLayout"),
+        }
+    }
+}
+
+impl Kind {
+    /// Recognize the slugs generated by Pulser for lattice layouts.
+    pub fn parse(slug: &str) -> Self {
+        let parse = || -> Option<Kind> {
+            let (name, args) = slug.strip_suffix("µm)")?.split_once('(')?;
+            let (shape, spacing) = args.split_once(", ")?;
+            match name {
+                "TriangularLatticeLayout" => Some(Kind::Triangular {
+                    n_traps: shape.parse().ok()?,
+                    spacing: spacing.parse().ok()?,
+                }),
+                "SquareLatticeLayout" => {
+                    let (rows, columns) = shape.split_once('x')?;
+                    Some(Kind::Square {
+                        rows: rows.parse().ok()?,
+                        columns: columns.parse().ok()?,
+                        spacing: spacing.parse().ok()?,
+                    })
+                }
+                "RectangularLatticeLayout" => {
+                    let (rows, columns) = shape.split_once('x')?;
+                    let (col_spacing, row_spacing) = spacing.split_once('x')?;
+                    Some(Kind::Rectangular {
+                        rows: rows.parse().ok()?,
+                        columns: columns.parse().ok()?,
+                        col_spacing: col_spacing.parse().ok()?,
+                        row_spacing: row_spacing.parse().ok()?,
+                    })
+                }
+                _ => None,
+            }
+        };
+        parse().unwrap_or_else(|| Kind::Custom {
+            slug: Some(slug.into()),
+        })
+    }
+}
+
+impl Layout {
+    /// A layout with arbitrary coordinates.
+    pub fn custom(coordinates: Vec<[f64; 2]>) -> Self {
+        Self {
+            coordinates: sorted(coordinates),
+            kind: Kind::Custom { slug: None },
+        }
+    }
+
+    /// A triangular lattice of `n_traps` traps separated by `spacing` µm, centered on a trap.
+    ///
+    /// Traps are added by concentric hexagonal rings, as by Pulser's `triangular_hex`, so
+    /// that trap ids match Pulser's. If the last ring is incomplete, each side of the hexagon
+    /// receives a contiguous run of traps, see [`hex_ring`].
+    pub fn triangular(n_traps: usize, spacing: f64) -> Self {
+        let mut coordinates = Vec::with_capacity(n_traps);
+        let mut ring = 0;
+        while coordinates.len() < n_traps {
+            let remaining = n_traps - coordinates.len();
+            coordinates.extend(
+                hex_ring(ring, remaining)
+                    .into_iter()
+                    .map(|[x, y]| [spacing * x, spacing * y]),
+            );
+            ring += 1;
+        }
+        Self {
+            coordinates: sorted(coordinates),
+            kind: Kind::Triangular { n_traps, spacing },
+        }
+    }
+
+    /// A square lattice of `rows` x `columns` traps separated by `spacing` µm, centered on 0.
+    pub fn square(rows: usize, columns: usize, spacing: f64) -> Self {
+        Self {
+            coordinates: rectangular_lattice(rows, columns, spacing, spacing),
+            kind: Kind::Square {
+                rows,
+                columns,
+                spacing,
+            },
+        }
+    }
+
+    /// A rectangular lattice of `rows` x `columns` traps, centered on 0.
+    pub fn rectangular(rows: usize, columns: usize, col_spacing: f64, row_spacing: f64) -> Self {
+        Self {
+            coordinates: rectangular_lattice(rows, columns, col_spacing, row_spacing),
+            kind: Kind::Rectangular {
+                rows,
+                columns,
+                col_spacing,
+                row_spacing,
+            },
+        }
+    }
+
+    /// The description of the layout used by Pulser.
+    pub fn slug(&self) -> String {
+        self.kind.to_string()
+    }
+
+    pub fn len(&self) -> usize {
+        self.coordinates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.coordinates.is_empty()
+    }
+}
+
+/// Up to `count` traps of ring `k` of a hexagon, for a spacing of 1.
+///
+/// As in Pulser's `triangular_hex`, each side starts from a vertex, the first at `(-k, 0)`,
+/// and holds traps `1..=k` along that side. If the ring is incomplete, each side is filled
+/// from its start with `count / 6` traps, and the `count % 6` remaining traps add one more
+/// trap to the sides ranked first in `[0, 3, 1, 4, 2, 5]`, i.e. sides 0, 2 and 4 first, so
+/// that the ring keeps as much symmetry as possible.
+fn hex_ring(k: usize, count: usize) -> Vec<[f64; 2]> {
+    const SIDES_RANK: [usize; 6] = [0, 3, 1, 4, 2, 5];
+    let crest = f64::sqrt(3.) / 2.;
+    let vertices = [
+        [-1., 0.],
+        [-0.5, crest],
+        [0.5, crest],
+        [1., 0.],
+        [0.5, -crest],
+        [-0.5, -crest],
+    ];
+    let steps = [
+        [0.5, crest],
+        [1., 0.],
+        [0.5, -crest],
+        [-0.5, -crest],
+        [-1., 0.],
+        [-0.5, crest],
+    ];
+    if k == 0 {
+        return vec![[0., 0.]];
+    }
+    let per_side = |side: usize| {
+        if count >= 6 * k {
+            k
+        } else {
+            count / 6 + usize::from(count % 6 > SIDES_RANK[side])
+        }
+    };
+    let k_f = k as f64;
+    (0..6)
+        .flat_map(|side| {
+            (1..=per_side(side)).map(move |atom| {
+                let atom = atom as f64;
+                [
+                    vertices[side][0] * k_f + atom * steps[side][0],
+                    vertices[side][1] * k_f + atom * steps[side][1],
+                ]
+            })
+        })
+        .collect()
+}
+
+fn rectangular_lattice(
+    rows: usize,
+    columns: usize,
+    col_spacing: f64,
+    row_spacing: f64,
+) -> Arc<[[f64; 2]]> {
+    let center_x = (columns as f64 - 1.) * col_spacing / 2.;
+    let center_y = (rows as f64 - 1.) * row_spacing / 2.;
+    let coordinates = (0..rows)
+        .cartesian_product(0..columns)
+        .map(|(row, col)| {
+            [
+                col as f64 * col_spacing - center_x,
+                row as f64 * row_spacing - center_y,
+            ]
+        })
+        .collect();
+    sorted(coordinates)
+}
+
+/// Round coordinates and sort them, as Pulser does.
+fn sorted(coordinates: Vec<[f64; 2]>) -> Arc<[[f64; 2]]> {
+    let scale = 10f64.powi(COORD_PRECISION);
+    let round = |v: f64| {
+        let rounded = (v * scale).round() / scale;
+        // Avoid `-0.0`.
+        if rounded == 0. {
+            0.
+        } else {
+            rounded
+        }
+    };
+    coordinates
+        .into_iter()
+        .map(|[x, y]| [round(x), round(y)])
+        .sorted_by(|a, b| a.partial_cmp(b).unwrap())
+        .collect()
 }
 
 impl Serialize for Layout {
@@ -14,7 +277,7 @@ impl Serialize for Layout {
     {
         let schema = Schema {
             coordinates: self.coordinates.clone(),
-            slug: "TriangularLatticeLayout(61, 5.0µm)".into(),
+            slug: self.slug().into(),
         };
         schema.serialize(serializer)
     }
@@ -28,6 +291,7 @@ impl<'de> Deserialize<'de> for Layout {
         let schema = Schema::deserialize(deserializer)?;
         Ok(Self {
             coordinates: schema.coordinates,
+            kind: Kind::parse(&schema.slug),
         })
     }
 }
@@ -35,5 +299,153 @@ impl<'de> Deserialize<'de> for Layout {
 #[derive(Deserialize, Serialize)]
 struct Schema {
     coordinates: Arc<[[f64; 2]]>,
-    slug: String,
+    slug: Arc<str>,
+}
+
+#[test]
+fn test_layouts_vs_pulser() {
+    // Pulser's `TriangularLatticeLayout(61, 5)`.
+    let expected: Vec<[f64; 2]> = vec![
+        [-20.0, 0.0],
+        [-17.5, -4.330127],
+        [-17.5, 4.330127],
+        [-15.0, -8.660254],
+        [-15.0, 0.0],
+        [-15.0, 8.660254],
+        [-12.5, -12.990381],
+        [-12.5, -4.330127],
+        [-12.5, 4.330127],
+        [-12.5, 12.990381],
+        [-10.0, -17.320508],
+        [-10.0, -8.660254],
+        [-10.0, 0.0],
+        [-10.0, 8.660254],
+        [-10.0, 17.320508],
+        [-7.5, -12.990381],
+        [-7.5, -4.330127],
+        [-7.5, 4.330127],
+        [-7.5, 12.990381],
+        [-5.0, -17.320508],
+        [-5.0, -8.660254],
+        [-5.0, 0.0],
+        [-5.0, 8.660254],
+        [-5.0, 17.320508],
+        [-2.5, -12.990381],
+        [-2.5, -4.330127],
+        [-2.5, 4.330127],
+        [-2.5, 12.990381],
+        [0.0, -17.320508],
+        [0.0, -8.660254],
+        [0.0, 0.0],
+        [0.0, 8.660254],
+        [0.0, 17.320508],
+        [2.5, -12.990381],
+        [2.5, -4.330127],
+        [2.5, 4.330127],
+        [2.5, 12.990381],
+        [5.0, -17.320508],
+        [5.0, -8.660254],
+        [5.0, 0.0],
+        [5.0, 8.660254],
+        [5.0, 17.320508],
+        [7.5, -12.990381],
+        [7.5, -4.330127],
+        [7.5, 4.330127],
+        [7.5, 12.990381],
+        [10.0, -17.320508],
+        [10.0, -8.660254],
+        [10.0, 0.0],
+        [10.0, 8.660254],
+        [10.0, 17.320508],
+        [12.5, -12.990381],
+        [12.5, -4.330127],
+        [12.5, 4.330127],
+        [12.5, 12.990381],
+        [15.0, -8.660254],
+        [15.0, 0.0],
+        [15.0, 8.660254],
+        [17.5, -4.330127],
+        [17.5, 4.330127],
+        [20.0, 0.0],
+    ];
+    let layout = Layout::triangular(61, 5.0);
+    assert_eq!(layout.slug(), "TriangularLatticeLayout(61, 5.0µm)");
+    assert_eq!(layout.coordinates.as_ref(), expected.as_slice());
+
+    // `TriangularLatticeLayout(10, 5)`: the second ring is partial, and the ids of the
+    // traps depend on which of its traps are kept. Pulser could not be run to generate
+    // these: they come from `triangular_hex` (`pulser/register/_patterns.py`), transliterated
+    // to Python and run separately from this implementation. Sides 0, 2 and 4 get one trap.
+    let expected: Vec<[f64; 2]> = vec![
+        [-7.5, 4.330127],
+        [-5.0, 0.0],
+        [-2.5, -4.330127],
+        [-2.5, 4.330127],
+        [0.0, -8.660254],
+        [0.0, 0.0],
+        [2.5, -4.330127],
+        [2.5, 4.330127],
+        [5.0, 0.0],
+        [7.5, 4.330127],
+    ];
+    let layout = Layout::triangular(10, 5.0);
+    assert_eq!(layout.coordinates.as_ref(), expected.as_slice());
+
+    // `TriangularLatticeLayout(29, 5)`, from the same transliteration: the third ring holds
+    // 10 traps, two on sides 0, 1, 2 and 4, one on sides 3 and 5, contiguous on each side.
+    let expected: Vec<[f64; 2]> = vec![
+        [-12.5, 4.330127],
+        [-10.0, -8.660254],
+        [-10.0, 0.0],
+        [-10.0, 8.660254],
+        [-7.5, -4.330127],
+        [-7.5, 4.330127],
+        [-5.0, -8.660254],
+        [-5.0, 0.0],
+        [-5.0, 8.660254],
+        [-2.5, -12.990381],
+        [-2.5, -4.330127],
+        [-2.5, 4.330127],
+        [-2.5, 12.990381],
+        [0.0, -8.660254],
+        [0.0, 0.0],
+        [0.0, 8.660254],
+        [2.5, -12.990381],
+        [2.5, -4.330127],
+        [2.5, 4.330127],
+        [2.5, 12.990381],
+        [5.0, -8.660254],
+        [5.0, 0.0],
+        [5.0, 8.660254],
+        [7.5, -4.330127],
+        [7.5, 4.330127],
+        [10.0, 0.0],
+        [10.0, 8.660254],
+        [12.5, -4.330127],
+        [12.5, 4.330127],
+    ];
+    let layout = Layout::triangular(29, 5.0);
+    assert_eq!(layout.coordinates.as_ref(), expected.as_slice());
+
+    // A partial ring is spread across the six sides.
+    let layout = Layout::triangular(13, 5.0);
+    let ring = layout
+        .coordinates
+        .iter()
+        .filter(|[x, y]| x * x + y * y > 5.0 * 5.0 + 1e-6)
+        .count();
+    assert_eq!(ring, 6);
+
+    let layout = Layout::rectangular(2, 3, 4.0, 5.0);
+    assert_eq!(layout.slug(), "RectangularLatticeLayout(2x3, 4.0x5.0µm)");
+    assert_eq!(layout.coordinates[0], [-4.0, -2.5]);
+    assert_eq!(layout.coordinates[5], [4.0, 2.5]);
+
+    for layout in [
+        Layout::triangular(61, 5.0),
+        Layout::square(4, 4, 4.5),
+        Layout::rectangular(2, 3, 4.0, 5.0),
+    ] {
+        assert_eq!(Kind::parse(&layout.slug()), layout.kind);
+    }
 }
@@ -8,38 +8,99 @@ use serde::{Deserialize, Serialize};
 
 use layout::Layout;
 
-use crate::backend::pulser::device::{ChannelId, PhysicalChannel, RydbergBeam, RydbergEom};
+use crate::{
+    backend::pulser::device::{Addressing, Basis, PhysicalChannel, RydbergBeam, RydbergEom},
+    types::units::{Coordinates, Micrometers},
+};
 
+#[derive(Clone)]
 pub struct Device {
+    version: String,
     interaction_coeff: c6::C6Coeff,
     dimensions: u32,
     rydberg_level: u32,
-    max_atom_num: u32,
+    max_atom_num: Option<u32>,
 
     /// Max distance to the center of the board, in um.
-    max_radial_distance_um: u32,
+    max_radial_distance_um: Option<u32>,
     max_sq_distance_to_center_um_sq: f64,
     min_atom_distance: f64,
-    max_sequence_duration: u32,
+    max_sequence_duration: Option<u32>,
     is_virtual: bool,
     max_layout_filling: f64,
     name: String,
     channels: Vec<PhysicalChannel>,
     pre_calibrated_layouts: Vec<Layout>,
+    interaction_coeff_xy: Option<f64>,
+    reusable_channels: bool,
+    supports_slm_mask: bool,
 }
 impl Device {
     pub fn interaction_coeff(&self) -> c6::C6Coeff {
         self.interaction_coeff
     }
+
+    /// The distance below which two atoms driven at `rabi_frequency` (in rad/µs) may not
+    /// both be excited, in µm.
+    pub fn rydberg_blockade_radius(&self, rabi_frequency: f64) -> f64 {
+        (self.interaction_coeff.value_rad_per_us_times_um_6() / rabi_frequency).powf(1. / 6.)
+    }
+
+    /// The Van der Waals interaction between two atoms, in rad/µs.
+    pub fn interaction(
+        &self,
+        first: Coordinates<Micrometers>,
+        second: Coordinates<Micrometers>,
+    ) -> f64 {
+        self.interaction_coeff.value_rad_per_us_times_um_6()
+            / first.sqdist(&second).cube().into_inner()
+    }
+
+    /// The square of the max distance to the center of the board, in um^2.
+    ///
+    /// Infinite if the device does not restrict the position of atoms.
     pub fn max_sq_distance_to_center(&self) -> f64 {
         self.max_sq_distance_to_center_um_sq
     }
+    pub fn max_radial_distance(&self) -> Option<u32> {
+        self.max_radial_distance_um
+    }
     pub fn min_atom_distance(&self) -> f64 {
         self.min_atom_distance
     }
     pub fn rydberg_level(&self) -> u32 {
         self.rydberg_level
     }
+    pub fn max_atom_num(&self) -> Option<u32> {
+        self.max_atom_num
+    }
+    pub fn max_sequence_duration(&self) -> Option<u32> {
+        self.max_sequence_duration
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn channels(&self) -> &[PhysicalChannel] {
+        &self.channels
+    }
+    pub fn pre_calibrated_layouts(&self) -> &[Layout] {
+        &self.pre_calibrated_layouts
+    }
+
+    /// Replace the layouts calibrated for this device.
+    pub fn with_pre_calibrated_layouts(mut self, layouts: Vec<Layout>) -> Self {
+        self.pre_calibrated_layouts = layouts;
+        self
+    }
+
+    /// The first global channel addressing the ground-rydberg basis, if any.
+    ///
+    /// This is the channel we use for analog algorithms.
+    pub fn global_rydberg_channel(&self) -> Option<&PhysicalChannel> {
+        self.channels.iter().find(|channel| {
+            channel.addressing == Addressing::Global && channel.basis == Basis::GroundRydberg
+        })
+    }
 }
 
 impl Serialize for Device {
@@ -48,7 +109,7 @@ impl Serialize for Device {
         S: serde::Serializer,
     {
         let schema = Schema {
-            version: "1".into(),
+            version: self.version.clone(),
             dimensions: self.dimensions,
             max_atom_num: self.max_atom_num,
             rydberg_level: self.rydberg_level,
@@ -60,9 +121,9 @@ impl Serialize for Device {
             name: self.name.clone(),
             channels: self.channels.clone(),
             pre_calibrated_layouts: self.pre_calibrated_layouts.clone(),
-            interaction_coeff_xy: None,
-            reusable_channels: false,
-            supports_slm_mask: false,
+            interaction_coeff_xy: self.interaction_coeff_xy,
+            reusable_channels: self.reusable_channels,
+            supports_slm_mask: self.supports_slm_mask,
         };
         schema.serialize(serializer)
     }
@@ -74,7 +135,19 @@ impl<'de> Deserialize<'de> for Device {
         D: serde::Deserializer<'de>,
     {
         let schema = Schema::deserialize(deserializer)?;
+        for channel in &schema.channels {
+            channel.check().map_err(serde::de::Error::custom)?;
+        }
+        let interaction_coeff = C6Coeff::new(schema.rydberg_level).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "unsupported rydberg level {}, expected a value in [{}, {}]",
+                schema.rydberg_level,
+                C6Coeff::MIN,
+                C6Coeff::MAX
+            ))
+        })?;
         Ok(Self {
+            version: schema.version,
             dimensions: schema.dimensions,
             max_atom_num: schema.max_atom_num,
             rydberg_level: schema.rydberg_level,
@@ -84,23 +157,33 @@ impl<'de> Deserialize<'de> for Device {
             is_virtual: schema.is_virtual,
             max_layout_filling: schema.max_layout_filling,
             name: schema.name,
-            channels: schema.channels.clone(),
-            pre_calibrated_layouts: schema.pre_calibrated_layouts.clone(),
-            interaction_coeff: C6Coeff::new(schema.rydberg_level).unwrap(),
-            max_sq_distance_to_center_um_sq: u64::pow(schema.max_radial_distance as u64, 2) as f64,
+            channels: schema.channels,
+            pre_calibrated_layouts: schema.pre_calibrated_layouts,
+            interaction_coeff,
+            max_sq_distance_to_center_um_sq: sq_distance(schema.max_radial_distance),
+            interaction_coeff_xy: schema.interaction_coeff_xy,
+            reusable_channels: schema.reusable_channels,
+            supports_slm_mask: schema.supports_slm_mask,
         })
     }
 }
 
+fn sq_distance(max_radial_distance_um: Option<u32>) -> f64 {
+    match max_radial_distance_um {
+        None => f64::INFINITY,
+        Some(distance) => (distance as f64).powi(2),
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 struct Schema {
     version: String,
     dimensions: u32,
     rydberg_level: u32,
-    max_atom_num: u32,
-    max_radial_distance: u32,
+    max_atom_num: Option<u32>,
+    max_radial_distance: Option<u32>,
     min_atom_distance: f64,
-    max_sequence_duration: u32,
+    max_sequence_duration: Option<u32>,
     is_virtual: bool,
     max_layout_filling: f64,
     name: String,
@@ -111,90 +194,44 @@ struct Schema {
     supports_slm_mask: bool,
 }
 
+impl Device {
+    /// The names of the built-in devices, as in Pulser.
+    pub const PRESETS: [&'static str; 3] = ["AnalogDevice", "DigitalAnalogDevice", "MockDevice"];
+
+    /// Return one of the built-in devices.
+    ///
+    /// Names are case-insensitive and the `Device` suffix is optional, so
+    /// `AnalogDevice` may also be written `analog`.
+    pub fn preset(name: &str) -> Option<Self> {
+        let name = name.to_lowercase().replace(['-', '_'], "");
+        match name.strip_suffix("device").unwrap_or(&name) {
+            "analog" => Some(Self::analog()),
+            "digitalanalog" => Some(Self::digital_analog()),
+            "mock" => Some(Self::mock()),
+            _ => None,
+        }
+    }
+
+    /// Load a device from its JSON specification, in Pulser's abstract representation.
+    pub fn from_json<R: std::io::Read>(reader: R) -> Result<Self, serde_json::Error> {
+        serde_json::from_reader(reader)
+    }
+}
+
 impl Device {
     pub fn analog() -> Self {
         // All this is ported from Pulser's `AnalogDevice` hard-coded implementation.
         let rydberg_level = 60;
         let interaction_coeff = c6::C6Coeff::new(rydberg_level).unwrap();
 
-        let pre_calibrated_layouts = vec![Layout {
-            coordinates: vec![
-                [-20.0, 0.0],
-                [-17.5, -4.330127],
-                [-17.5, 4.330127],
-                [-15.0, -8.660254],
-                [-15.0, 0.0],
-                [-15.0, 8.660254],
-                [-12.5, -12.990381],
-                [-12.5, -4.330127],
-                [-12.5, 4.330127],
-                [-12.5, 12.990381],
-                [-10.0, -17.320508],
-                [-10.0, -8.660254],
-                [-10.0, 0.0],
-                [-10.0, 8.660254],
-                [-10.0, 17.320508],
-                [-7.5, -12.990381],
-                [-7.5, -4.330127],
-                [-7.5, 4.330127],
-                [-7.5, 12.990381],
-                [-5.0, -17.320508],
-                [-5.0, -8.660254],
-                [-5.0, 0.0],
-                [-5.0, 8.660254],
-                [-5.0, 17.320508],
-                [-2.5, -12.990381],
-                [-2.5, -4.330127],
-                [-2.5, 4.330127],
-                [-2.5, 12.990381],
-                [0.0, -17.320508],
-                [0.0, -8.660254],
-                [0.0, 0.0],
-                [0.0, 8.660254],
-                [0.0, 17.320508],
-                [2.5, -12.990381],
-                [2.5, -4.330127],
-                [2.5, 4.330127],
-                [2.5, 12.990381],
-                [5.0, -17.320508],
-                [5.0, -8.660254],
-                [5.0, 0.0],
-                [5.0, 8.660254],
-                [5.0, 17.320508],
-                [7.5, -12.990381],
-                [7.5, -4.330127],
-                [7.5, 4.330127],
-                [7.5, 12.990381],
-                [10.0, -17.320508],
-                [10.0, -8.660254],
-                [10.0, 0.0],
-                [10.0, 8.660254],
-                [10.0, 17.320508],
-                [12.5, -12.990381],
-                [12.5, -4.330127],
-                [12.5, 4.330127],
-                [12.5, 12.990381],
-                [15.0, -8.660254],
-                [15.0, 0.0],
-                [15.0, 8.660254],
-                [17.5, -4.330127],
-                [17.5, 4.330127],
-                [20.0, 0.0],
-            ]
-            .into(),
-        }];
-        let channels = vec![PhysicalChannel::Variant0 {
-            addressing: "Global".to_string(),
-            basis: "ground-rydberg".to_string(),
+        let pre_calibrated_layouts = vec![Layout::triangular(61, 5.0)];
+        let channels = vec![PhysicalChannel {
             clock_period: 4.0,
-            id: ChannelId("ising".to_string()),
-            max_targets: (),
-            fixed_retarget_t: (),
-            max_abs_detuning: 40.0 * std::f64::consts::PI,
-            max_amp: 4.0 * std::f64::consts::PI,
+            max_abs_detuning: Some(40.0 * std::f64::consts::PI),
+            max_amp: Some(4.0 * std::f64::consts::PI),
             min_duration: 16.0,
             mod_bandwidth: Some(8.0),
-            max_duration: 100000000.0,
+            max_duration: Some(100000000.0),
             eom_config: Some(RydbergEom {
                 limiting_beam: RydbergBeam::Red,
                 max_limiting_amp: 60.0 * std::f64::consts::PI,
@@ -204,26 +241,145 @@ impl Device {
                 mod_bandwidth: 40.0,
                 multiple_beam_control: Some(true),
             }),
-            min_avg_amp: None,
-            min_retarget_interval: (),
+            ..PhysicalChannel::rydberg("ising", Addressing::Global)
         }];
 
-        let max_radial_distance_um = 35;
-        let max_sq_distance_to_center_um_sq = (max_radial_distance_um as f64).powi(2);
+        let max_radial_distance_um = Some(35);
         Self {
+            version: "1".into(),
             interaction_coeff,
             dimensions: 2,
             rydberg_level,
-            max_atom_num: 25,
+            max_atom_num: Some(25),
             max_radial_distance_um,
             min_atom_distance: 5.,
-            max_sequence_duration: 4_000,
-            max_sq_distance_to_center_um_sq,
+            max_sequence_duration: Some(4_000),
+            max_sq_distance_to_center_um_sq: sq_distance(max_radial_distance_um),
             is_virtual: false,
             max_layout_filling: 0.5,
             name: "AnalogDevice".into(),
             channels,
             pre_calibrated_layouts,
+            interaction_coeff_xy: None,
+            reusable_channels: false,
+            supports_slm_mask: false,
         }
     }
+
+    pub fn digital_analog() -> Self {
+        // Ported from Pulser's `DigitalAnalogDevice`.
+        use std::f64::consts::PI;
+        let rydberg_level = 70;
+        let interaction_coeff = c6::C6Coeff::new(rydberg_level).unwrap();
+        let eom_config = RydbergEom {
+            limiting_beam: RydbergBeam::Red,
+            max_limiting_amp: 40.0 * 2.0 * PI,
+            controlled_beams: vec![RydbergBeam::Blue],
+            custom_buffer_time: None,
+            intermediate_detuning: 700.0 * 2.0 * PI,
+            mod_bandwidth: 24.0,
+            multiple_beam_control: None,
+        };
+        let local = |channel: PhysicalChannel| PhysicalChannel {
+            max_abs_detuning: Some(2.0 * PI * 20.0),
+            max_amp: Some(2.0 * PI * 10.0),
+            min_retarget_interval: Some(220.0),
+            fixed_retarget_t: Some(0.0),
+            max_targets: Some(1),
+            clock_period: 4.0,
+            min_duration: 16.0,
+            max_duration: Some(2f64.powi(26)),
+            mod_bandwidth: Some(4.0),
+            ..channel
+        };
+        let channels = vec![
+            PhysicalChannel {
+                max_abs_detuning: Some(2.0 * PI * 20.0),
+                max_amp: Some(2.0 * PI * 2.5),
+                clock_period: 4.0,
+                min_duration: 16.0,
+                max_duration: Some(2f64.powi(26)),
+                mod_bandwidth: Some(8.0),
+                eom_config: Some(eom_config),
+                ..PhysicalChannel::rydberg("rydberg_global", Addressing::Global)
+            },
+            // As in Pulser, only the global channel has an EOM mode.
+            local(PhysicalChannel::rydberg("rydberg_local", Addressing::Local)),
+            local(PhysicalChannel::raman("raman_local", Addressing::Local)),
+        ];
+        let max_radial_distance_um = Some(50);
+        Self {
+            version: "1".into(),
+            interaction_coeff,
+            dimensions: 2,
+            rydberg_level,
+            max_atom_num: Some(100),
+            max_radial_distance_um,
+            min_atom_distance: 4.,
+            max_sequence_duration: None,
+            max_sq_distance_to_center_um_sq: sq_distance(max_radial_distance_um),
+            is_virtual: false,
+            max_layout_filling: 0.5,
+            name: "DigitalAnalogDevice".into(),
+            channels,
+            pre_calibrated_layouts: vec![],
+            interaction_coeff_xy: None,
+            reusable_channels: false,
+            supports_slm_mask: true,
+        }
+    }
+
+    pub fn mock() -> Self {
+        // Ported from Pulser's `MockDevice`, a virtual device without physical limits.
+        let rydberg_level = 70;
+        let interaction_coeff = c6::C6Coeff::new(rydberg_level).unwrap();
+        let channels = vec![
+            PhysicalChannel::rydberg("rydberg_global", Addressing::Global),
+            PhysicalChannel::rydberg("rydberg_local", Addressing::Local),
+            PhysicalChannel::raman("raman_global", Addressing::Global),
+            PhysicalChannel::raman("raman_local", Addressing::Local),
+            PhysicalChannel::microwave("mw_global"),
+        ];
+        Self {
+            version: "1".into(),
+            interaction_coeff,
+            dimensions: 3,
+            rydberg_level,
+            max_atom_num: None,
+            max_radial_distance_um: None,
+            min_atom_distance: 0.,
+            max_sequence_duration: None,
+            max_sq_distance_to_center_um_sq: sq_distance(None),
+            is_virtual: true,
+            max_layout_filling: 0.5,
+            name: "MockDevice".into(),
+            channels,
+            pre_calibrated_layouts: vec![],
+            interaction_coeff_xy: Some(3700.0),
+            reusable_channels: true,
+            supports_slm_mask: true,
+        }
+    }
+}
+
+#[test]
+fn test_presets_roundtrip() {
+    for name in Device::PRESETS {
+        let device = Device::preset(name).unwrap();
+        assert_eq!(device.name(), name);
+        let json = serde_json::to_string(&device).unwrap();
+        let device = Device::from_json(json.as_bytes()).unwrap();
+        assert_eq!(serde_json::to_string(&device).unwrap(), json);
+    }
+    let eom_channels = Device::digital_analog()
+        .channels()
+        .iter()
+        .filter(|chan| chan.eom_config.is_some())
+        .map(|chan| chan.id.0.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(eom_channels, vec!["rydberg_global"]);
+
+    assert!(Device::preset("analog").is_some());
+    assert!(Device::preset("digital-analog").is_some());
+    assert!(Device::preset("no such device").is_none());
 }
@@ -1,18 +1,313 @@
+//! The compiled artifact, i.e. the contents of `.qlaf` files.
+//!
+//! Besides the sequence itself, the artifact records everything needed to understand
+//! and reproduce the compilation: the problem, the QUBO, the register, the options and
+//! the version of the compiler.
+//!
+//! Files written by older versions of qlafoutea are migrated transparently when loaded.
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    analysis::GroundStates,
+    pulser::{register::Register, schema, sequence::Sequence},
+    qaa, qaoa,
+    qubo::{self, Constraints},
+};
+use crate::{frontend::Input, types::Quality};
+
+/// The version of the format written by this compiler.
+///
+/// - 1: the problem and the sequence, as a string (unversioned).
+/// - 2: provenance metadata, the sequence as structured JSON.
+pub const VERSION: u64 = 2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("invalid code")]
+    Json(#[from] serde_json::Error),
 
-use super::pulser::sequence;
+    #[error("unsupported format version {0}, this compiler reads versions up to {VERSION}")]
+    UnsupportedVersion(u64),
+
+    #[error("invalid sequence")]
+    Sequence(#[from] schema::Error),
+
+    #[error("cannot compile the problem to QUBO")]
+    Problem(#[from] crate::frontend::Error),
+}
 
-#[derive(Deserialize, Serialize)]
 pub struct Code {
-    pub problem: crate::frontend::Input,
-    pub sequence: String,
+    /// The version of the format, see [`VERSION`].
+    pub version: u64,
+
+    /// The compiler which produced this file, e.g. `qlafoutea 0.2.0`, or for files migrated
+    /// from version 1, which did not record it, e.g. `unknown (migrated from v1 by qlafoutea
+    /// 0.2.0)`.
+    pub compiler: String,
+
+    /// The name of the device the sequence was compiled for.
+    pub device: String,
+
+    pub problem: Input,
+
+    /// The QUBO laid out on the register, i.e. after normalization if any.
+    pub qubo: Constraints,
+
+    /// The scale factor applied to the QUBO of the problem, if it was normalized.
+    ///
+    /// Energies of `qubo` are converted to problem units with
+    /// [`qubo::Normalization::to_problem_units`].
+    pub normalization: Option<qubo::Normalization>,
+
+    pub register: Register,
+
+    /// How the QUBO was laid out, or `None` for files migrated from version 1.
+    pub layout: Option<Layout>,
+
+    /// With QAA, the options passed to the compiler, or `None` for files migrated from
+    /// version 1.
+    pub qaa_options: Option<qaa::Options>,
+
+    /// With QAA, the parameters of the sweep and why they were chosen.
+    pub qaa: Option<qaa::Report>,
+
+    /// With QAOA, the options passed to the compiler.
+    pub qaoa_options: Option<qaoa::Options>,
+
+    /// How the ground states of the Rydberg Hamiltonian compare to the optimal solutions,
+    /// if the register is small enough to check.
+    pub ground_states: Option<GroundStates>,
+
+    /// The sequence, in Pulser's abstract representation.
+    pub sequence: Value,
 }
+
+/// How the QUBO was laid out on the device.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Layout {
+    pub quality: Quality,
+    pub seed: u64,
+    pub options: qubo::Options,
+}
+
 impl Code {
     pub fn try_new(
-        problem: crate::frontend::Input,
-        sequence: sequence::Sequence,
-    ) -> Result<Self, anyhow::Error> {
-        let sequence = serde_json::to_string_pretty(&sequence)?;
-        Ok(Self { problem, sequence })
+        problem: Input,
+        qubo: Constraints,
+        sequence: &Sequence,
+        layout: Layout,
+    ) -> Result<Self, Error> {
+        Ok(Self {
+            version: VERSION,
+            compiler: compiler(),
+            device: sequence.device().name().to_string(),
+            problem,
+            qubo,
+            normalization: None,
+            register: sequence.register().clone(),
+            layout: Some(layout),
+            qaa_options: None,
+            qaa: None,
+            qaoa_options: None,
+            ground_states: None,
+            sequence: sequence.to_validated_json()?,
+        })
+    }
+
+    /// Parse the sequence.
+    pub fn sequence(&self) -> Result<Sequence, Error> {
+        Ok(Sequence::deserialize(&self.sequence)?)
+    }
+
+    /// The version of the format of a code file, without parsing the rest of the file.
+    pub fn version_of(value: &Value) -> u64 {
+        value.get("version").and_then(Value::as_u64).unwrap_or(1)
+    }
+
+    /// Load a code file in any supported version, migrating it to the current version.
+    pub fn from_value(value: Value) -> Result<Self, Error> {
+        match Self::version_of(&value) {
+            1 => Self::migrate_v1(serde_json::from_value(value)?),
+            VERSION => {
+                let schema: Schema = serde_json::from_value(value)?;
+                Ok(Self {
+                    version: schema.version,
+                    compiler: schema.compiler,
+                    device: schema.device,
+                    problem: schema.problem,
+                    qubo: schema.qubo,
+                    normalization: schema.normalization,
+                    register: schema.register,
+                    layout: schema.layout,
+                    qaa_options: schema.qaa_options,
+                    qaa: schema.qaa,
+                    qaoa_options: schema.qaoa_options,
+                    ground_states: schema.ground_states,
+                    sequence: schema.sequence,
+                })
+            }
+            version => Err(Error::UnsupportedVersion(version)),
+        }
+    }
+
+    fn migrate_v1(legacy: SchemaV1) -> Result<Self, Error> {
+        let sequence: Value = serde_json::from_str(&legacy.sequence)?;
+        let parsed = Sequence::deserialize(&sequence)?;
+        let qubo = legacy.problem.to_constraints()?;
+        Ok(Self {
+            version: VERSION,
+            compiler: format!("unknown (migrated from v1 by {})", compiler()),
+            device: parsed.device().name().to_string(),
+            problem: legacy.problem,
+            qubo,
+            normalization: None,
+            register: parsed.register().clone(),
+            layout: None,
+            qaa_options: None,
+            qaa: legacy.qaa,
+            qaoa_options: None,
+            ground_states: None,
+            sequence,
+        })
+    }
+}
+
+fn compiler() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+impl Serialize for Code {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let schema = SchemaRef {
+            version: self.version,
+            compiler: &self.compiler,
+            device: &self.device,
+            problem: &self.problem,
+            qubo: &self.qubo,
+            normalization: &self.normalization,
+            register: &self.register,
+            layout: &self.layout,
+            qaa_options: &self.qaa_options,
+            qaa: &self.qaa,
+            qaoa_options: &self.qaoa_options,
+            ground_states: &self.ground_states,
+            sequence: &self.sequence,
+        };
+        schema.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Code {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Self::from_value(value).map_err(|err| {
+            // serde only keeps the message, so make it include the causes.
+            let mut message = err.to_string();
+            let mut source = std::error::Error::source(&err);
+            while let Some(cause) = source {
+                message = format!("{message}: {cause}");
+                source = cause.source();
+            }
+            serde::de::Error::custom(message)
+        })
     }
 }
+
+#[derive(Deserialize)]
+struct Schema {
+    version: u64,
+    compiler: String,
+    device: String,
+    problem: Input,
+    qubo: Constraints,
+    #[serde(default)]
+    normalization: Option<qubo::Normalization>,
+    register: Register,
+    layout: Option<Layout>,
+    #[serde(default)]
+    qaa_options: Option<qaa::Options>,
+    #[serde(default)]
+    qaa: Option<qaa::Report>,
+    #[serde(default)]
+    qaoa_options: Option<qaoa::Options>,
+    #[serde(default)]
+    ground_states: Option<GroundStates>,
+    sequence: Value,
+}
+
+#[derive(Serialize)]
+struct SchemaRef<'a> {
+    version: u64,
+    compiler: &'a str,
+    device: &'a str,
+    problem: &'a Input,
+    qubo: &'a Constraints,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalization: &'a Option<qubo::Normalization>,
+    register: &'a Register,
+    layout: &'a Option<Layout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qaa_options: &'a Option<qaa::Options>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qaa: &'a Option<qaa::Report>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    qaoa_options: &'a Option<qaoa::Options>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ground_states: &'a Option<GroundStates>,
+    sequence: &'a Value,
+}
+
+/// Version 1, unversioned.
+#[derive(Deserialize)]
+struct SchemaV1 {
+    problem: Input,
+    sequence: String,
+    #[serde(default)]
+    qaa: Option<qaa::Report>,
+}
+
+#[test]
+fn test_migrate_v1() {
+    use crate::backend::pulser::sequence::fixture;
+
+    let qubo = Constraints::from_const([[-1., 2.], [2., -1.]], vec!["a".into(), "b".into()]);
+    let sequence = fixture::sweep(fixture::atoms(&[("a", 0., 0.), ("b", 5., 0.)]));
+    let legacy = serde_json::json!({
+        "problem": Input::new(qubo.clone()),
+        "sequence": serde_json::to_string_pretty(&sequence).unwrap(),
+    });
+    assert_eq!(Code::version_of(&legacy), 1);
+
+    let code = Code::from_value(legacy).unwrap();
+    assert_eq!(code.version, VERSION);
+    assert_eq!(
+        code.compiler,
+        format!("unknown (migrated from v1 by {})", compiler())
+    );
+    assert_eq!(code.device, "AnalogDevice");
+    assert_eq!(code.qubo, qubo);
+    assert_eq!(code.register.len(), 2);
+    assert!(code.layout.is_none());
+    code.sequence().unwrap();
+
+    // Migrated files round-trip in the current version.
+    let value = serde_json::to_value(&code).unwrap();
+    assert_eq!(Code::version_of(&value), VERSION);
+    let reloaded: Code = serde_json::from_value(value.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&reloaded).unwrap(), value);
+
+    let mut future = value;
+    future["version"] = (VERSION + 1).into();
+    assert!(matches!(
+        Code::from_value(future),
+        Err(Error::UnsupportedVersion(_))
+    ));
+}
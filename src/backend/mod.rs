@@ -1,6 +1,8 @@
 //! Compiler back-end.
+pub mod analysis;
 pub mod device;
 pub mod format;
 pub mod pulser;
 pub mod qaa;
+pub mod qaoa;
 pub mod qubo;
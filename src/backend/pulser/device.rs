@@ -532,33 +532,124 @@ pub struct ChannelId(pub String);
 #[doc = "}"]
 #[doc = r" ```"]
 #[doc = r" </details>"]
+#[doc = r""]
+#[doc = r" The six branches of the schema differ only in the values of `addressing` and"]
+#[doc = r" `basis` and in which fields may be null, so we represent them with a single"]
+#[doc = r" struct and check the combination with [`PhysicalChannel::check`]."]
 #[derive(Clone, Debug, Deserialize, Serialize)]
-#[serde(untagged, deny_unknown_fields)]
-pub enum PhysicalChannel {
-    Variant0 {
-        addressing: String,
-        #[doc = "The addressed basis name."]
-        basis: String,
-        clock_period: f64,
-        #[doc = "Configuration of an associated EOM."]
-        eom_config: Option<RydbergEom>,
-        #[doc = "Time taken to change the target (in ns)."]
-        fixed_retarget_t: (),
-        #[doc = "The identifier of the channel within its device."]
-        id: ChannelId,
-        max_abs_detuning: f64,
-        max_amp: f64,
-        max_duration: f64,
-        #[doc = "How many atoms can be locally addressed at once by the same beam."]
-        max_targets: (),
-        #[serde(default, skip_serializing_if = "Option::is_none")]
-        min_avg_amp: Option<f64>,
-        min_duration: f64,
-        #[doc = "Minimum time required between the ends of two target instructions (in ns)."]
-        min_retarget_interval: (),
-        #[doc = "The modulation bandwidth at -3dB (50% reduction), in MHz."]
-        mod_bandwidth: Option<f64>,
-    },
+#[serde(deny_unknown_fields)]
+pub struct PhysicalChannel {
+    pub addressing: Addressing,
+    #[doc = "The addressed basis name."]
+    pub basis: Basis,
+    pub clock_period: f64,
+    #[doc = "Configuration of an associated EOM."]
+    pub eom_config: Option<RydbergEom>,
+    #[doc = "Time taken to change the target (in ns)."]
+    pub fixed_retarget_t: Option<f64>,
+    #[doc = "The identifier of the channel within its device."]
+    pub id: ChannelId,
+    #[doc = "Maximum possible detuning (in rad/µs), in absolute value. Null for virtual devices."]
+    pub max_abs_detuning: Option<f64>,
+    #[doc = "Maximum pulse amplitude (in rad/µs). Null for virtual devices."]
+    pub max_amp: Option<f64>,
+    #[doc = "The longest duration an instruction can take. Null for virtual devices."]
+    pub max_duration: Option<f64>,
+    #[doc = "How many atoms can be locally addressed at once by the same beam."]
+    pub max_targets: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_avg_amp: Option<f64>,
+    pub min_duration: f64,
+    #[doc = "Minimum time required between the ends of two target instructions (in ns)."]
+    pub min_retarget_interval: Option<f64>,
+    #[doc = "The modulation bandwidth at -3dB (50% reduction), in MHz."]
+    pub mod_bandwidth: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Addressing {
+    Global,
+    Local,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum Basis {
+    #[serde(rename = "ground-rydberg")]
+    GroundRydberg,
+    #[serde(rename = "digital")]
+    Digital,
+    #[serde(rename = "XY")]
+    Xy,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ChannelError {
+    #[error("channel {0}: only ground-rydberg channels may have an EOM")]
+    UnexpectedEom(String),
+
+    #[error("channel {0}: global channels cannot be retargeted")]
+    UnexpectedRetarget(String),
+
+    #[error("channel {0}: local channels need a retargeting configuration")]
+    MissingRetarget(String),
+}
+
+impl PhysicalChannel {
+    /// A channel with the defaults used by Pulser for all the optional fields.
+    fn new(id: &str, addressing: Addressing, basis: Basis) -> Self {
+        let local = addressing == Addressing::Local;
+        Self {
+            addressing,
+            basis,
+            clock_period: 1.0,
+            eom_config: None,
+            fixed_retarget_t: local.then_some(0.),
+            id: ChannelId(id.to_string()),
+            max_abs_detuning: None,
+            max_amp: None,
+            max_duration: None,
+            max_targets: None,
+            min_avg_amp: None,
+            min_duration: 1.0,
+            min_retarget_interval: local.then_some(0.),
+            mod_bandwidth: None,
+        }
+    }
+
+    /// A Rydberg channel, addressing the ground-rydberg basis.
+    pub fn rydberg(id: &str, addressing: Addressing) -> Self {
+        Self::new(id, addressing, Basis::GroundRydberg)
+    }
+
+    /// A Raman channel, addressing the digital basis.
+    pub fn raman(id: &str, addressing: Addressing) -> Self {
+        Self::new(id, addressing, Basis::Digital)
+    }
+
+    /// A Microwave channel, addressing the XY basis.
+    pub fn microwave(id: &str) -> Self {
+        Self::new(id, Addressing::Global, Basis::Xy)
+    }
+
+    /// Check that the fields correspond to one of the branches of the schema.
+    pub fn check(&self) -> Result<(), ChannelError> {
+        if self.eom_config.is_some() && self.basis != Basis::GroundRydberg {
+            return Err(ChannelError::UnexpectedEom(self.id.0.clone()));
+        }
+        let retarget = [self.fixed_retarget_t, self.min_retarget_interval];
+        match self.addressing {
+            Addressing::Global if retarget.iter().any(Option::is_some) => {
+                Err(ChannelError::UnexpectedRetarget(self.id.0.clone()))
+            }
+            Addressing::Global if self.max_targets.is_some() => {
+                Err(ChannelError::UnexpectedRetarget(self.id.0.clone()))
+            }
+            Addressing::Local if retarget.iter().any(Option::is_none) => {
+                Err(ChannelError::MissingRetarget(self.id.0.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
 }
 
 #[doc = "RydbergBeam"]
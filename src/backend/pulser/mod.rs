@@ -1,5 +1,8 @@
 pub mod device;
+pub mod operation;
 pub mod pulse;
 pub mod register;
+pub mod schema;
 pub mod sequence;
+pub mod variable;
 pub mod waveform;
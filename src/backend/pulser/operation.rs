@@ -0,0 +1,155 @@
+use std::rc::Rc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::backend::pulser::{
+    pulse::Pulse,
+    variable::{self, Env, Parameter},
+};
+
+/// An operation of a sequence.
+///
+/// We only interpret pulses. Any other operation of Pulser's abstract representation
+/// (e.g. `delay`, `target`, `align`, `phase_shift`) is kept as-is, so that sequences
+/// written by Pulser survive a round-trip through qlafoutea.
+pub enum Operation {
+    Pulse(Rc<Pulse>),
+    Other(Value),
+}
+impl Operation {
+    pub fn as_pulse(&self) -> Option<&Pulse> {
+        match *self {
+            Self::Pulse(ref pulse) => Some(pulse),
+            Self::Other(_) => None,
+        }
+    }
+
+    /// The `op` field of the operation, e.g. `pulse` or `delay`.
+    pub fn name(&self) -> &str {
+        match *self {
+            Self::Pulse(_) => "pulse",
+            Self::Other(ref value) => value.get("op").and_then(Value::as_str).unwrap_or("?"),
+        }
+    }
+
+    /// Replace all variables with their value.
+    pub fn bind(&self, env: &Env) -> Result<Self, variable::Error> {
+        match *self {
+            Self::Pulse(ref pulse) => Ok(Self::Pulse(Rc::new(pulse.bind(env)?))),
+            Self::Other(ref value) => Ok(Self::Other(bind_value(value, env)?)),
+        }
+    }
+}
+
+/// Replace the references to variables found anywhere in `value`.
+fn bind_value(value: &Value, env: &Env) -> Result<Value, variable::Error> {
+    match *value {
+        Value::Object(ref fields) => {
+            if let Ok(Parameter::Variable(name)) = Parameter::deserialize(value) {
+                return Parameter::Variable(name).resolve(env).map(Value::from);
+            }
+            Ok(Value::Object(
+                fields
+                    .iter()
+                    .map(|(name, field)| Ok((name.clone(), bind_value(field, env)?)))
+                    .collect::<Result<_, _>>()?,
+            ))
+        }
+        Value::Array(ref items) => Ok(Value::Array(
+            items
+                .iter()
+                .map(|item| bind_value(item, env))
+                .collect::<Result<_, _>>()?,
+        )),
+        _ => Ok(value.clone()),
+    }
+}
+
+impl From<Pulse> for Operation {
+    fn from(pulse: Pulse) -> Self {
+        Self::Pulse(Rc::new(pulse))
+    }
+}
+
+impl Serialize for Operation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            Self::Pulse(ref pulse) => pulse.serialize(serializer),
+            Self::Other(ref value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Operation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        match value.get("op").and_then(Value::as_str) {
+            Some("pulse") => Ok(Self::Pulse(Rc::new(
+                Pulse::deserialize(value).map_err(serde::de::Error::custom)?,
+            ))),
+            Some(_) => Ok(Self::Other(value)),
+            None => Err(serde::de::Error::missing_field("op")),
+        }
+    }
+}
+
+#[test]
+fn test_other_operations() {
+    use crate::backend::pulser::{
+        schema,
+        sequence::{fixture, Sequence},
+        variable::Variable,
+    };
+
+    let sequence =
+        fixture::sweep(fixture::register(2)).with_variables(vec![Variable::int("wait", 100.)]);
+    let mut json = serde_json::to_value(&sequence).unwrap();
+    let others = serde_json::json!([
+        {"op": "target", "target_ids": ["q0"], "channel": "rydberg_global"},
+        {"op": "delay", "time": {"expression": "index", "lhs": {"variable": "wait"}, "rhs": 0}, "channel": "rydberg_global"},
+        {"op": "align", "channels": ["rydberg_global"]},
+        {"op": "phase_shift", "phi": 1.5, "targets": ["q0", "q1"], "basis": "ground-rydberg"},
+    ]);
+    json["operations"]
+        .as_array_mut()
+        .unwrap()
+        .extend(others.as_array().unwrap().iter().cloned());
+    schema::validate(&json).unwrap();
+
+    // Round-trip, without losing any operation.
+    let sequence: Sequence = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(sequence.operations().len(), 5);
+    assert_eq!(sequence.pulses().count(), 1);
+    assert_eq!(
+        sequence
+            .operations()
+            .iter()
+            .map(|op| op.name())
+            .collect::<Vec<_>>(),
+        vec!["pulse", "target", "delay", "align", "phase_shift"]
+    );
+    assert_eq!(serde_json::to_value(&sequence).unwrap(), json);
+
+    // Variables are bound in operations that we do not interpret, too.
+    let bound = sequence
+        .bind(&[("wait".to_string(), 200.)].into_iter().collect())
+        .unwrap();
+    let bound = serde_json::to_value(&bound).unwrap();
+    assert_eq!(bound["operations"][2]["time"], serde_json::json!(200.));
+    assert_eq!(bound["operations"][3], others[2]);
+
+    // Malformed pulses are still rejected.
+    let mut malformed = json;
+    malformed["operations"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("amplitude");
+    assert!(serde_json::from_value::<Sequence>(malformed).is_err());
+}
@@ -2,12 +2,30 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 
-use crate::backend::pulser::waveform::Waveform;
+use crate::backend::pulser::{
+    variable::{self, Env, Parameter},
+    waveform::Waveform,
+};
+
+/// How a pulse is scheduled with respect to pulses on other channels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Protocol {
+    #[default]
+    #[serde(rename = "min-delay")]
+    MinDelay,
+    #[serde(rename = "wait-for-all")]
+    WaitForAll,
+    #[serde(rename = "no-delay")]
+    NoDelay,
+}
 
 pub struct Pulse {
     channel: Rc<str>,
     amplitude: Waveform,
     detuning: Waveform,
+    phase: Parameter,
+    post_phase_shift: Parameter,
+    protocol: Protocol,
 }
 impl Pulse {
     pub fn new(channel: Rc<str>, amplitude: Waveform, detuning: Waveform) -> Self {
@@ -15,8 +33,35 @@ impl Pulse {
             channel,
             amplitude,
             detuning,
+            phase: Parameter::Value(0.),
+            post_phase_shift: Parameter::Value(0.),
+            protocol: Protocol::default(),
         }
     }
+    pub fn channel(&self) -> &str {
+        &self.channel
+    }
+    pub fn amplitude(&self) -> &Waveform {
+        &self.amplitude
+    }
+    pub fn detuning(&self) -> &Waveform {
+        &self.detuning
+    }
+    pub fn phase(&self) -> &Parameter {
+        &self.phase
+    }
+
+    /// Replace all variables with their value.
+    pub fn bind(&self, env: &Env) -> Result<Self, variable::Error> {
+        Ok(Self {
+            channel: self.channel.clone(),
+            amplitude: self.amplitude.bind(env)?,
+            detuning: self.detuning.bind(env)?,
+            phase: Parameter::Value(self.phase.resolve(env)?),
+            post_phase_shift: Parameter::Value(self.post_phase_shift.resolve(env)?),
+            protocol: self.protocol,
+        })
+    }
 }
 
 impl Serialize for Pulse {
@@ -29,9 +74,9 @@ impl Serialize for Pulse {
             detuning: self.detuning.clone(),
             channel: self.channel.clone(),
             op: "pulse".to_string(),
-            phase: 0.,
-            post_phase_shift: 0.,
-            protocol: "min-delay".to_string(),
+            phase: self.phase.clone(),
+            post_phase_shift: self.post_phase_shift.clone(),
+            protocol: self.protocol,
         };
         schema.serialize(serializer)
     }
@@ -43,10 +88,19 @@ impl<'de> Deserialize<'de> for Pulse {
         D: serde::Deserializer<'de>,
     {
         let schema = Schema::deserialize(deserializer)?;
+        if schema.op != "pulse" {
+            return Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Str(&schema.op),
+                &"operation `pulse`",
+            ));
+        }
         Ok(Self {
             amplitude: schema.amplitude,
             detuning: schema.detuning,
             channel: schema.channel,
+            phase: schema.phase,
+            post_phase_shift: schema.post_phase_shift,
+            protocol: schema.protocol,
         })
     }
 }
@@ -57,7 +111,7 @@ struct Schema {
     detuning: Waveform,
     channel: Rc<str>,
     op: String,
-    phase: f64,
-    post_phase_shift: f64,
-    protocol: String,
+    phase: Parameter,
+    post_phase_shift: Parameter,
+    protocol: Protocol,
 }
@@ -0,0 +1,10 @@
+// Generated by `scripts/vendor-pulser-schemas.sh`, do not edit.
+//
+// No Pulser release has been vendored yet, `abstract-repr.json` stands in for its schemas.
+
+/// The Pulser release the schemas were copied from, if any.
+pub const PULSER_VERSION: Option<&str> = None;
+
+const DOCUMENTS: &[(&str, &str)] = &[("abstract-repr.json", include_str!("abstract-repr.json"))];
+
+const ROOT: &str = "abstract-repr.json";
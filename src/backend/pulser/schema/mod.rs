@@ -0,0 +1,438 @@
+//! Validation of sequences against Pulser's abstract representation.
+//!
+//! Pulser rejects any file that does not match its JSON schema, which is split in several
+//! documents (`sequence-schema.json`, `device-schema.json`, `layout-schema.json` and
+//! `register-schema.json` in `pulser-core/pulser/json/abstract_repr/schemas`). We check
+//! everything we emit against a copy of these schemas, so that drift between our serializers
+//! and Pulser is caught at compile time rather than by the Python side.
+//!
+//! `scripts/vendor-pulser-schemas.sh <version>` copies the schemas of a Pulser release into
+//! this directory, verbatim, and records the release in `PULSER_VERSION`. Until they are
+//! vendored, `abstract-repr.json` stands in for them: it only describes the parts of the
+//! format that qlafoutea emits, and accepts any other operation.
+//!
+//! The validator supports the subset of JSON Schema (draft 7) used by these schemas:
+//! `type`, `const`, `enum`, `required`, `properties`, `additionalProperties`, `items`
+//! (including tuples), `minItems`, `maxItems`, `minimum`, `maximum`, `anyOf`, `oneOf`,
+//! `allOf`, `not` and `$ref`s, within a document or to another document.
+
+use std::{collections::HashMap, fmt::Display, sync::OnceLock};
+
+use itertools::Itertools;
+use serde_json::Value;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("failed to serialize sequence")]
+    Serialization(#[source] serde_json::Error),
+
+    #[error("sequence does not match Pulser's abstract representation:\n{}", .0.iter().join("\n"))]
+    Invalid(Vec<Violation>),
+}
+
+/// A single mismatch between a document and the schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// The position in the document, as a JSON pointer.
+    pub path: String,
+    pub message: String,
+}
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "- {}: {}", path, self.message)
+    }
+}
+
+// `DOCUMENTS`, the vendored schema documents by file name, `ROOT`, the document describing
+// a whole sequence, and `PULSER_VERSION`, as written by `scripts/vendor-pulser-schemas.sh`.
+include!("documents.rs");
+
+fn documents() -> &'static HashMap<&'static str, Value> {
+    static DOCUMENTS_JSON: OnceLock<HashMap<&'static str, Value>> = OnceLock::new();
+    DOCUMENTS_JSON.get_or_init(|| {
+        DOCUMENTS
+            .iter()
+            .map(|(name, source)| {
+                let value = serde_json::from_str(source)
+                    .unwrap_or_else(|err| panic!("Invalid vendored schema {name}: {err}"));
+                (*name, value)
+            })
+            .collect()
+    })
+}
+
+/// The vendored schema of a sequence.
+pub fn abstract_repr() -> &'static Value {
+    &documents()[ROOT]
+}
+
+/// Check a sequence, in its JSON form, against the vendored schema.
+pub fn validate(value: &Value) -> Result<(), Error> {
+    let mut violations = vec![];
+    Validator {
+        documents: documents(),
+    }
+    .check(ROOT, abstract_repr(), value, "", &mut violations);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Invalid(violations))
+    }
+}
+
+struct Validator<'a> {
+    documents: &'a HashMap<&'static str, Value>,
+}
+
+impl<'a> Validator<'a> {
+    /// Resolve `reference`, found in `document`, as the document it points to and the
+    /// schema within that document.
+    fn resolve<'r>(&self, document: &'r str, reference: &'r str) -> Option<(&'r str, &'a Value)> {
+        let (name, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+        let name = if name.is_empty() { document } else { name };
+        let (name, root) = self.documents.get_key_value(name)?;
+        Some((name, root.pointer(pointer)?))
+    }
+
+    /// Whether `value` matches `schema`, and if not, why.
+    fn violations(
+        &self,
+        document: &str,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+    ) -> Vec<Violation> {
+        let mut out = vec![];
+        self.check(document, schema, value, path, &mut out);
+        out
+    }
+
+    fn check(
+        &self,
+        document: &str,
+        schema: &Value,
+        value: &Value,
+        path: &str,
+        out: &mut Vec<Violation>,
+    ) {
+        let fail = |out: &mut Vec<Violation>, message: String| {
+            out.push(Violation {
+                path: path.to_string(),
+                message,
+            })
+        };
+        let Some(schema) = schema.as_object() else {
+            // `true` accepts everything, `false` nothing.
+            if schema == &Value::Bool(false) {
+                fail(out, "no value is allowed here".to_string());
+            }
+            return;
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(document, reference) {
+                None => fail(out, format!("unknown reference {reference}")),
+                Some((document, target)) => self.check(document, target, value, path, out),
+            }
+            return;
+        }
+
+        if let Some(expected) = schema.get("type") {
+            let accepted = match expected {
+                Value::String(kind) => vec![kind.as_str()],
+                Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).collect(),
+                _ => vec![],
+            };
+            if !accepted.iter().any(|kind| has_type(value, kind)) {
+                fail(
+                    out,
+                    format!(
+                        "expected {}, got {}",
+                        accepted.join(" or "),
+                        type_name(value)
+                    ),
+                );
+                return;
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                fail(out, format!("expected {expected}, got {value}"));
+            }
+        }
+        if let Some(Value::Array(expected)) = schema.get("enum") {
+            if !expected.contains(value) {
+                fail(
+                    out,
+                    format!(
+                        "expected one of {}, got {value}",
+                        expected.iter().join(", ")
+                    ),
+                );
+            }
+        }
+        if let Some(number) = value.as_f64() {
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < min {
+                    fail(out, format!("{number} is less than the minimum {min}"));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > max {
+                    fail(out, format!("{number} is greater than the maximum {max}"));
+                }
+            }
+        }
+        if let Some(Value::Array(branches)) = schema.get("anyOf") {
+            let attempts = branches
+                .iter()
+                .map(|branch| self.violations(document, branch, value, path))
+                .collect_vec();
+            if attempts.iter().all(|violations| !violations.is_empty()) {
+                // Report the closest branch, if it points inside `value`, e.g. at the
+                // field of a pulse which does not match.
+                match attempts.into_iter().min_by_key(Vec::len) {
+                    Some(closest) if closest.iter().all(|v| v.path != path) => out.extend(closest),
+                    _ => fail(
+                        out,
+                        format!(
+                            "{} does not match any of the expected shapes",
+                            type_name(value)
+                        ),
+                    ),
+                }
+            }
+        }
+        if let Some(Value::Array(branches)) = schema.get("oneOf") {
+            let matches = branches
+                .iter()
+                .filter(|branch| self.violations(document, branch, value, path).is_empty())
+                .count();
+            if matches != 1 {
+                fail(
+                    out,
+                    format!(
+                        "{} matches {matches} of the expected shapes, instead of exactly one",
+                        type_name(value)
+                    ),
+                );
+            }
+        }
+        if let Some(Value::Array(branches)) = schema.get("allOf") {
+            for branch in branches {
+                self.check(document, branch, value, path, out);
+            }
+        }
+        if let Some(forbidden) = schema.get("not") {
+            if self.violations(document, forbidden, value, path).is_empty() {
+                fail(out, format!("{value} is not allowed here"));
+            }
+        }
+
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::Array(required)) = schema.get("required") {
+                    for name in required.iter().filter_map(Value::as_str) {
+                        if !fields.contains_key(name) {
+                            fail(out, format!("missing field `{name}`"));
+                        }
+                    }
+                }
+                let properties = schema.get("properties").and_then(Value::as_object);
+                for (name, field) in fields {
+                    let field_path = format!("{path}/{name}");
+                    match properties.and_then(|p| p.get(name)) {
+                        Some(sub_schema) => {
+                            self.check(document, sub_schema, field, &field_path, out)
+                        }
+                        None => match schema.get("additionalProperties") {
+                            Some(Value::Bool(false)) => {
+                                fail(out, format!("unexpected field `{name}`"))
+                            }
+                            Some(sub_schema) => {
+                                self.check(document, sub_schema, field, &field_path, out)
+                            }
+                            None => {}
+                        },
+                    }
+                }
+            }
+            Value::Array(items) => {
+                if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) < min {
+                        fail(
+                            out,
+                            format!("expected at least {min} items, got {}", items.len()),
+                        );
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                    if (items.len() as u64) > max {
+                        fail(
+                            out,
+                            format!("expected at most {max} items, got {}", items.len()),
+                        );
+                    }
+                }
+                match schema.get("items") {
+                    // A tuple, one schema per position.
+                    Some(Value::Array(sub_schemas)) => {
+                        for (i, (sub_schema, item)) in sub_schemas.iter().zip(items).enumerate() {
+                            self.check(document, sub_schema, item, &format!("{path}/{i}"), out);
+                        }
+                    }
+                    Some(sub_schema) => {
+                        for (i, item) in items.iter().enumerate() {
+                            self.check(document, sub_schema, item, &format!("{path}/{i}"), out);
+                        }
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[test]
+fn test_validate_sequence() {
+    use crate::backend::pulser::sequence::{fixture, Sequence};
+
+    let sequence = fixture::sweep(fixture::atoms(&[("a", 0., 0.), ("b", 10., 0.)]));
+    let json = sequence.to_validated_json().unwrap();
+
+    // Round-trip, without losing any field.
+    let sequence: Sequence = serde_json::from_value(json.clone()).unwrap();
+    assert_eq!(serde_json::to_value(&sequence).unwrap(), json);
+
+    // Drift is detected.
+    let mut drifted = json.clone();
+    drifted["operations"][0]["protocol"] = "as-soon-as-possible".into();
+    drifted["device"]["channels"][0]["max_amp"] = "fast".into();
+    drifted.as_object_mut().unwrap().remove("measurement");
+    let Err(Error::Invalid(violations)) = validate(&drifted) else {
+        panic!("expected violations")
+    };
+    let paths = violations.iter().map(|v| v.path.as_str()).collect_vec();
+    assert_eq!(
+        paths,
+        vec!["", "/device/channels/0/max_amp", "/operations/0/protocol"]
+    );
+
+    // Operations which we do not emit are valid.
+    let mut other = json.clone();
+    other["operations"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({"op": "delay", "time": 100, "channel": "ising"}));
+    validate(&other).unwrap();
+}
+
+#[test]
+fn test_validate_across_documents() {
+    let documents = [
+        (
+            "sequence-schema.json",
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "device": {"$ref": "device-schema.json#/definitions/Device"},
+                    "atoms": {"items": [{"type": "string"}, {"type": "number"}]},
+                },
+            }),
+        ),
+        (
+            "device-schema.json",
+            serde_json::json!({
+                "definitions": {
+                    "Device": {
+                        "oneOf": [{"$ref": "#/definitions/Name"}, {"type": "object"}],
+                    },
+                    "Name": {"type": "string", "allOf": [{"not": {"const": ""}}]},
+                },
+            }),
+        ),
+    ]
+    .into_iter()
+    .collect::<HashMap<_, _>>();
+    let validator = Validator {
+        documents: &documents,
+    };
+    let check = |value: serde_json::Value| {
+        validator
+            .violations(
+                "sequence-schema.json",
+                &documents["sequence-schema.json"],
+                &value,
+                "",
+            )
+            .into_iter()
+            .map(|v| v.path)
+            .collect_vec()
+    };
+    assert!(check(serde_json::json!({"device": "AnalogDevice", "atoms": ["q0", 1]})).is_empty());
+    assert_eq!(
+        check(serde_json::json!({"device": "", "atoms": ["q0", "far"]})),
+        vec!["/atoms/1", "/device"]
+    );
+}
+
+#[test]
+fn test_vendored_references() {
+    // Every `$ref` of the vendored documents points to an existing schema.
+    fn references<'v>(value: &'v Value, out: &mut Vec<&'v str>) {
+        match value {
+            Value::Object(fields) => {
+                if let Some(Value::String(reference)) = fields.get("$ref") {
+                    out.push(reference);
+                }
+                fields.values().for_each(|field| references(field, out));
+            }
+            Value::Array(items) => items.iter().for_each(|item| references(item, out)),
+            _ => {}
+        }
+    }
+    let validator = Validator {
+        documents: documents(),
+    };
+    assert!(documents().contains_key(ROOT));
+    for (name, document) in documents() {
+        let mut found = vec![];
+        references(document, &mut found);
+        for reference in found {
+            assert!(
+                validator.resolve(name, reference).is_some(),
+                "{name}: unknown reference {reference}"
+            );
+        }
+    }
+}
@@ -1,39 +1,133 @@
-use std::{collections::HashMap, rc::Rc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    rc::Rc,
+};
 
 use serde::{Deserialize, Serialize};
 
 use crate::backend::{
     device::Device,
-    pulser::{device::ChannelId, pulse::Pulse, register::Register},
+    pulser::{
+        device::ChannelId,
+        operation::Operation,
+        pulse::Pulse,
+        register::Register,
+        schema,
+        variable::{self, Env, Variable},
+    },
 };
 
 pub struct Sequence {
     register: Rc<Register>,
     device: Rc<Device>,
-    pulse: Rc<Pulse>,
-    channels: Vec<Rc<str>>,
+    operations: Vec<Rc<Operation>>,
+
+    /// The channels declared by the sequence, as (name, id of the channel on the device).
+    channels: Vec<(Rc<str>, ChannelId)>,
+    variables: Vec<Variable>,
+    name: String,
+    version: String,
+
+    /// The basis in which the sequence is measured, if any.
+    measurement: Option<String>,
+
+    /// Optional fields of the abstract representation that we do not interpret
+    /// (e.g. `layout`, `slm_mask_targets`), kept as-is.
+    extra: BTreeMap<String, serde_json::Value>,
 }
 
 impl Sequence {
     pub fn new(device: Device, register: Register, pulse: Pulse, channels: &[Rc<str>]) -> Self {
+        Self::from_pulses(device, register, vec![pulse], channels)
+    }
+
+    /// A sequence playing `pulses` one after the other.
+    pub fn from_pulses(
+        device: Device,
+        register: Register,
+        pulses: Vec<Pulse>,
+        channels: &[Rc<str>],
+    ) -> Self {
+        assert!(!pulses.is_empty());
         Self {
             register: Rc::new(register),
             device: Rc::new(device),
-            pulse: Rc::new(pulse),
-            channels: channels.to_vec(),
+            operations: pulses
+                .into_iter()
+                .map(|pulse| Rc::new(pulse.into()))
+                .collect(),
+            channels: channels
+                .iter()
+                .map(|chan| (chan.clone(), ChannelId(chan.to_string())))
+                .collect(),
+            variables: vec![],
+            name: "qlafoutea compilation target".to_string(),
+            version: "1".to_string(),
+            measurement: None,
+            extra: BTreeMap::new(),
         }
     }
+
+    /// Declare variables, which may be referenced from the waveforms of the pulse.
+    pub fn with_variables(mut self, variables: Vec<Variable>) -> Self {
+        self.variables = variables;
+        self
+    }
     pub fn register(&self) -> &Register {
         &self.register
     }
     pub fn device(&self) -> &Device {
         &self.device
     }
-    pub fn pulse(&self) -> &Pulse {
-        &self.pulse
+    pub fn operations(&self) -> &[Rc<Operation>] {
+        &self.operations
+    }
+
+    /// The pulses of the sequence, skipping any other operation.
+    pub fn pulses(&self) -> impl Iterator<Item = &Pulse> {
+        self.operations.iter().filter_map(|op| op.as_pulse())
+    }
+    pub fn channels(&self) -> impl Iterator<Item = &Rc<str>> {
+        self.channels.iter().map(|(name, _)| name)
+    }
+
+    /// The id, on the device, of the channel declared as `name`.
+    pub fn channel_id(&self, name: &str) -> Option<&ChannelId> {
+        self.channels
+            .iter()
+            .find(|(chan, _)| chan.as_ref() == name)
+            .map(|(_, id)| id)
+    }
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    /// Produce a sequence without variables, in which every variable is replaced
+    /// with its value from `overrides` or, if unspecified, its default value.
+    pub fn bind(&self, overrides: &HashMap<String, f64>) -> Result<Self, variable::Error> {
+        let env = Env::new(&self.variables, overrides)?;
+        Ok(Self {
+            register: self.register.clone(),
+            device: self.device.clone(),
+            operations: self
+                .operations
+                .iter()
+                .map(|op| op.bind(&env).map(Rc::new))
+                .collect::<Result<_, _>>()?,
+            channels: self.channels.clone(),
+            variables: vec![],
+            name: self.name.clone(),
+            version: self.version.clone(),
+            measurement: self.measurement.clone(),
+            extra: self.extra.clone(),
+        })
     }
-    pub fn channels(&self) -> &[Rc<str>] {
-        &self.channels
+
+    /// Serialize to JSON, checking the result against Pulser's abstract representation schema.
+    pub fn to_validated_json(&self) -> Result<serde_json::Value, schema::Error> {
+        let value = serde_json::to_value(self).map_err(schema::Error::Serialization)?;
+        schema::validate(&value)?;
+        Ok(value)
     }
 }
 
@@ -43,26 +137,45 @@ impl<'de> Deserialize<'de> for Sequence {
         D: serde::Deserializer<'de>,
     {
         let schema = Schema::deserialize(deserializer)?;
-        match schema.operations.len() {
-            0 => {
-                return Err(serde::de::Error::invalid_value(
-                    serde::de::Unexpected::Other("empty operations"),
-                    &"exactly one operation",
-                ))
-            }
-            n if n > 1 => {
-                return Err(serde::de::Error::invalid_value(
-                    serde::de::Unexpected::Other(&format!("{} operations", n)),
-                    &"exactly one operation",
-                ))
-            }
-            _ => {}
+        if schema.operations.is_empty() {
+            return Err(serde::de::Error::invalid_value(
+                serde::de::Unexpected::Other("empty operations"),
+                &"at least one operation",
+            ));
         }
         Ok(Self {
             register: schema.register,
             device: schema.device,
-            pulse: schema.operations[0].clone(),
-            channels: schema.channels.keys().map(|k| k.clone().into()).collect(),
+            operations: schema.operations,
+            channels: schema
+                .channels
+                .into_iter()
+                .map(|(name, id)| (name.into(), id))
+                .collect(),
+            variables: schema
+                .variables
+                .into_iter()
+                .map(|(name, var)| {
+                    let default = match var.value.as_slice() {
+                        [value] => *value,
+                        _ => {
+                            return Err(serde::de::Error::invalid_length(
+                                var.value.len(),
+                                &"a variable of size 1",
+                            ))
+                        }
+                    };
+                    Ok(Variable {
+                        name: name.into(),
+                        kind: var.kind,
+                        default,
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            name: schema.name,
+            version: schema.version,
+            measurement: schema.measurement,
+            extra: schema.extra,
         })
     }
 }
@@ -73,18 +186,31 @@ impl Serialize for Sequence {
         S: serde::Serializer,
     {
         let schema = Schema {
-            version: "1".to_string(),
-            name: "qlafoutea compilation target".to_string(),
+            version: self.version.clone(),
+            name: self.name.clone(),
             register: self.register.clone(),
             device: self.device.clone(),
-            variables: HashMap::new(),
-            operations: vec![self.pulse.clone()],
-            measurement: None,
+            variables: self
+                .variables
+                .iter()
+                .map(|var| {
+                    (
+                        var.name.to_string(),
+                        variable::Schema {
+                            kind: var.kind,
+                            value: vec![var.default],
+                        },
+                    )
+                })
+                .collect(),
+            operations: self.operations.clone(),
+            measurement: self.measurement.clone(),
             channels: self
                 .channels
                 .iter()
-                .map(|chan| (chan.to_string(), ChannelId(chan.to_string())))
+                .map(|(name, id)| (name.to_string(), id.clone()))
                 .collect(),
+            extra: self.extra.clone(),
         };
         schema.serialize(serializer)
     }
@@ -93,11 +219,62 @@ impl Serialize for Sequence {
 #[derive(Deserialize, Serialize)]
 struct Schema {
     version: String,
-    variables: HashMap<String, ()>, // always empty for the time being.
+    variables: BTreeMap<String, variable::Schema>,
     register: Rc<Register>,
     device: Rc<Device>,
     name: String,
-    operations: Vec<Rc<Pulse>>,
-    channels: HashMap<String, ChannelId>,
-    measurement: Option<()>, // always None for the time being.
+    operations: Vec<Rc<Operation>>,
+    channels: BTreeMap<String, ChannelId>,
+    measurement: Option<String>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, serde_json::Value>,
+}
+
+/// Registers and sequences shared by tests.
+#[cfg(test)]
+pub mod fixture {
+    use super::*;
+    use crate::{backend::pulser::waveform::Waveform, types::units::Coordinates};
+
+    /// Atoms with the given names and positions, in µm.
+    pub fn atoms(atoms: &[(&str, f64, f64)]) -> Register {
+        Register {
+            coordinates: atoms
+                .iter()
+                .map(|&(name, x, y)| (Coordinates::new(x, y), name.into()))
+                .collect(),
+        }
+    }
+
+    /// `len` atoms, named `q0`, `q1`, ..., 5 µm apart along the x axis.
+    pub fn register(len: usize) -> Register {
+        Register {
+            coordinates: (0..len)
+                .map(|i| (Coordinates::new(5. * i as f64, 0.), format!("q{i}").into()))
+                .collect(),
+        }
+    }
+
+    /// A single pulse on the global Rydberg channel of `AnalogDevice`.
+    pub fn sequence(register: Register, amplitude: Waveform, detuning: Waveform) -> Sequence {
+        let device = Device::analog();
+        let channel: Rc<str> = device
+            .global_rydberg_channel()
+            .unwrap()
+            .id
+            .0
+            .as_str()
+            .into();
+        let pulse = Pulse::new(channel.clone(), amplitude, detuning);
+        Sequence::new(device, register, pulse, std::slice::from_ref(&channel))
+    }
+
+    /// A 1 µs adiabatic sweep.
+    pub fn sweep(register: Register) -> Sequence {
+        sequence(
+            register,
+            Waveform::interpolated(1_000., &[0., 1., 0.]),
+            Waveform::interpolated(1_000., &[-1., 0., 1.]),
+        )
+    }
 }
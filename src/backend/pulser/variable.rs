@@ -0,0 +1,202 @@
+//! Sequence variables.
+//!
+//! A sequence may declare variables (e.g. durations, amplitudes, detuning endpoints),
+//! which are referenced from waveforms. This lets us compile a register once and
+//! re-run the resulting sequence with distinct schedule parameters.
+
+use std::{collections::HashMap, fmt::Display, rc::Rc};
+
+use serde::{Deserialize, Serialize};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("unknown variable {0}")]
+    UnknownVariable(String),
+
+    #[error("variable {name} expects an integer, got {value}")]
+    NotAnInteger { name: String, value: f64 },
+
+    #[error("unsupported expression {0}")]
+    UnsupportedExpression(String),
+}
+
+/// The type of a variable, as understood by Pulser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Kind {
+    #[serde(rename = "float")]
+    Float,
+    #[serde(rename = "int")]
+    Int,
+}
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Float => write!(f, "float"),
+            Self::Int => write!(f, "int"),
+        }
+    }
+}
+
+/// A variable declared by a sequence.
+#[derive(Clone, Debug)]
+pub struct Variable {
+    pub name: Rc<str>,
+    pub kind: Kind,
+
+    /// The value used unless the caller overrides it at run time.
+    pub default: f64,
+}
+impl Variable {
+    pub fn float(name: &str, default: f64) -> Self {
+        Self {
+            name: name.into(),
+            kind: Kind::Float,
+            default,
+        }
+    }
+    pub fn int(name: &str, default: f64) -> Self {
+        Self {
+            name: name.into(),
+            kind: Kind::Int,
+            default,
+        }
+    }
+}
+
+/// The values of variables, once resolved.
+pub struct Env(HashMap<Rc<str>, f64>);
+impl Env {
+    /// Resolve the values of `variables`, using `overrides` if specified, their
+    /// default value otherwise.
+    pub fn new(variables: &[Variable], overrides: &HashMap<String, f64>) -> Result<Self, Error> {
+        for name in overrides.keys() {
+            if !variables.iter().any(|var| var.name.as_ref() == name) {
+                return Err(Error::UnknownVariable(name.clone()));
+            }
+        }
+        let mut values = HashMap::new();
+        for var in variables {
+            let value = overrides
+                .get(var.name.as_ref())
+                .cloned()
+                .unwrap_or(var.default);
+            if var.kind == Kind::Int && value.fract() != 0. {
+                return Err(Error::NotAnInteger {
+                    name: var.name.to_string(),
+                    value,
+                });
+            }
+            values.insert(var.name.clone(), value);
+        }
+        Ok(Self(values))
+    }
+}
+
+/// A number in a sequence, either a constant or a reference to a variable.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Parameter {
+    Value(f64),
+    Variable(Rc<str>),
+}
+impl Parameter {
+    pub fn resolve(&self, env: &Env) -> Result<f64, Error> {
+        match *self {
+            Self::Value(value) => Ok(value),
+            Self::Variable(ref name) => env
+                .0
+                .get(name)
+                .cloned()
+                .ok_or_else(|| Error::UnknownVariable(name.to_string())),
+        }
+    }
+
+    /// Return the value, if this parameter does not depend on a variable.
+    pub fn as_value(&self) -> Option<f64> {
+        match *self {
+            Self::Value(value) => Some(value),
+            Self::Variable(_) => None,
+        }
+    }
+}
+impl Display for Parameter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Value(value) => value.fmt(f),
+            Self::Variable(ref name) => write!(f, "${name}"),
+        }
+    }
+}
+impl From<f64> for Parameter {
+    fn from(value: f64) -> Self {
+        Self::Value(value)
+    }
+}
+
+impl Serialize for Parameter {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            Self::Value(value) => value.serialize(serializer),
+            // Pulser variables are arrays, so scalars are represented as item 0.
+            Self::Variable(ref name) => ParameterSchema::Index {
+                expression: "index".to_string(),
+                lhs: VariableRef {
+                    variable: name.to_string(),
+                },
+                rhs: 0,
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Parameter {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match ParameterSchema::deserialize(deserializer)? {
+            ParameterSchema::Value(value) => Ok(Self::Value(value)),
+            ParameterSchema::Variable(var) => Ok(Self::Variable(var.variable.into())),
+            ParameterSchema::Index {
+                expression,
+                lhs,
+                rhs,
+            } => {
+                if expression != "index" || rhs != 0 {
+                    return Err(serde::de::Error::custom(Error::UnsupportedExpression(
+                        expression,
+                    )));
+                }
+                Ok(Self::Variable(lhs.variable.into()))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum ParameterSchema {
+    Value(f64),
+    Index {
+        expression: String,
+        lhs: VariableRef,
+        rhs: usize,
+    },
+    Variable(VariableRef),
+}
+
+#[derive(Deserialize, Serialize)]
+struct VariableRef {
+    variable: String,
+}
+
+/// The representation of a variable in the `variables` field of a sequence.
+#[derive(Deserialize, Serialize)]
+pub struct Schema {
+    #[serde(rename = "type")]
+    pub kind: Kind,
+    pub value: Vec<f64>,
+}
@@ -1,28 +1,183 @@
-use std::rc::Rc;
+use std::{fmt::Display, rc::Rc};
 
 use serde::{Deserialize, Serialize};
 
+use crate::backend::pulser::variable::{self, Env, Parameter};
+
 #[derive(Clone)]
 pub enum Waveform {
     Interpolated {
-        values: Rc<[f64]>,
-        timestamps: Rc<[f64]>,
+        duration: Parameter,
+
+        /// Timestamps, normalized to [0, 1].
+        times: Rc<[f64]>,
+        values: Rc<[Parameter]>,
+    },
+    Constant {
+        duration: Parameter,
+        value: Parameter,
     },
 }
 
 impl Waveform {
     pub fn interpolated(total_duration_ns: f64, values: &[f64]) -> Self {
         assert!(total_duration_ns > 0.);
+        let values = values
+            .iter()
+            .cloned()
+            .map(Parameter::from)
+            .collect::<Vec<_>>();
+        Self::parametrized(Parameter::Value(total_duration_ns), &values)
+    }
+
+    /// An interpolated waveform, in which the duration and any value may be a variable.
+    pub fn parametrized(duration: Parameter, values: &[Parameter]) -> Self {
         assert!(values.len() >= 2);
-        let mut timestamps = Vec::with_capacity(values.len());
-        timestamps.push(0.);
-        for i in 1..values.len() {
-            timestamps.push(i as f64 * total_duration_ns / (values.len() as f64 - 1f64));
-        }
-        assert_eq!(timestamps.len(), values.len());
+        let times = (0..values.len())
+            .map(|i| i as f64 / (values.len() as f64 - 1f64))
+            .collect::<Vec<_>>();
         Waveform::Interpolated {
+            duration,
+            times: times.into(),
             values: values.to_vec().into(),
-            timestamps: timestamps.into(),
+        }
+    }
+
+    pub fn constant(duration_ns: f64, value: f64) -> Self {
+        assert!(duration_ns > 0.);
+        Waveform::Constant {
+            duration: Parameter::Value(duration_ns),
+            value: Parameter::Value(value),
+        }
+    }
+
+    pub fn duration(&self) -> &Parameter {
+        match *self {
+            Waveform::Interpolated { ref duration, .. } => duration,
+            Waveform::Constant { ref duration, .. } => duration,
+        }
+    }
+
+    /// The value at `t` ns from the start of the waveform, or `None` if the waveform
+    /// depends on a variable.
+    ///
+    /// Like Pulser, interpolated waveforms use monotone cubic (PCHIP) interpolation.
+    pub fn value_at(&self, t: f64) -> Option<f64> {
+        match *self {
+            Waveform::Constant { ref value, .. } => value.as_value(),
+            Waveform::Interpolated {
+                ref duration,
+                ref times,
+                ref values,
+            } => {
+                let duration = duration.as_value()?;
+                let xs = times.iter().map(|t| t * duration).collect::<Vec<_>>();
+                let ys = values
+                    .iter()
+                    .map(Parameter::as_value)
+                    .collect::<Option<Vec<_>>>()?;
+                Some(pchip(&xs, &ys, t))
+            }
+        }
+    }
+
+    /// Replace all variables with their value.
+    pub fn bind(&self, env: &Env) -> Result<Self, variable::Error> {
+        match *self {
+            Waveform::Interpolated {
+                ref duration,
+                ref times,
+                ref values,
+            } => {
+                let values = values
+                    .iter()
+                    .map(|v| v.resolve(env).map(Parameter::Value))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Waveform::Interpolated {
+                    duration: Parameter::Value(duration.resolve(env)?),
+                    times: times.clone(),
+                    values: values.into(),
+                })
+            }
+            Waveform::Constant {
+                ref duration,
+                ref value,
+            } => Ok(Waveform::Constant {
+                duration: Parameter::Value(duration.resolve(env)?),
+                value: Parameter::Value(value.resolve(env)?),
+            }),
+        }
+    }
+}
+
+/// Piecewise cubic Hermite interpolation of `(xs, ys)` at `x`, with the slopes chosen
+/// as in SciPy's `PchipInterpolator`, which preserves monotonicity.
+fn pchip(xs: &[f64], ys: &[f64], x: f64) -> f64 {
+    let n = xs.len();
+    if x <= xs[0] {
+        return ys[0];
+    }
+    if x >= xs[n - 1] {
+        return ys[n - 1];
+    }
+    let h = |k: usize| xs[k + 1] - xs[k];
+    let m = |k: usize| (ys[k + 1] - ys[k]) / h(k);
+    let slope = |k: usize| -> f64 {
+        if n == 2 {
+            return m(0);
+        }
+        let end = |h0: f64, h1: f64, m0: f64, m1: f64| {
+            let d = ((2. * h0 + h1) * m0 - h0 * m1) / (h0 + h1);
+            if d.signum() != m0.signum() || m0 == 0. {
+                0.
+            } else if m0.signum() != m1.signum() && d.abs() > 3. * m0.abs() {
+                3. * m0
+            } else {
+                d
+            }
+        };
+        if k == 0 {
+            return end(h(0), h(1), m(0), m(1));
+        }
+        if k == n - 1 {
+            return end(h(n - 2), h(n - 3), m(n - 2), m(n - 3));
+        }
+        let (m0, m1) = (m(k - 1), m(k));
+        if m0 == 0. || m1 == 0. || m0.signum() != m1.signum() {
+            return 0.;
+        }
+        let w1 = 2. * h(k) + h(k - 1);
+        let w2 = h(k) + 2. * h(k - 1);
+        (w1 + w2) / (w1 / m0 + w2 / m1)
+    };
+    let k = xs.partition_point(|&xk| xk <= x) - 1;
+    let t = (x - xs[k]) / h(k);
+    let (h00, h10) = ((1. + 2. * t) * (1. - t).powi(2), t * (1. - t).powi(2));
+    let (h01, h11) = (t * t * (3. - 2. * t), t * t * (t - 1.));
+    h00 * ys[k] + h10 * h(k) * slope(k) + h01 * ys[k + 1] + h11 * h(k) * slope(k + 1)
+}
+
+impl Display for Waveform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Waveform::Interpolated {
+                ref duration,
+                ref values,
+                ..
+            } => {
+                write!(f, "interpolated over {duration} ns: [")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{value:.3}")?;
+                }
+                write!(f, "]")
+            }
+            Waveform::Constant {
+                ref duration,
+                ref value,
+            } => write!(f, "constant over {duration} ns: {value:.3}"),
         }
     }
 }
@@ -32,16 +187,23 @@ impl Serialize for Waveform {
     where
         S: serde::Serializer,
     {
-        let Waveform::Interpolated {
-            ref values,
-            ref timestamps,
-        } = *self;
-        let duration = timestamps.last().cloned().unwrap();
-        let schema = Schema {
-            kind: "interpolated".to_string(),
-            duration,
-            times: timestamps.iter().map(|v| v / duration).collect(),
-            values: values.clone(),
+        let schema = match *self {
+            Waveform::Interpolated {
+                ref duration,
+                ref times,
+                ref values,
+            } => Schema::Interpolated {
+                duration: duration.clone(),
+                times: times.clone(),
+                values: values.clone(),
+            },
+            Waveform::Constant {
+                ref duration,
+                ref value,
+            } => Schema::Constant {
+                duration: duration.clone(),
+                value: value.clone(),
+            },
         };
         schema.serialize(serializer)
     }
@@ -52,18 +214,62 @@ impl<'de> Deserialize<'de> for Waveform {
     where
         D: serde::Deserializer<'de>,
     {
-        let schema = Schema::deserialize(deserializer)?;
-        Ok(Waveform::Interpolated {
-            values: schema.values,
-            timestamps: schema.times.iter().map(|v| v * schema.duration).collect(),
-        })
+        let waveform = match Schema::deserialize(deserializer)? {
+            Schema::Interpolated {
+                duration,
+                times,
+                values,
+            } => Waveform::Interpolated {
+                duration,
+                times,
+                values,
+            },
+            Schema::Constant { duration, value } => Waveform::Constant { duration, value },
+        };
+        Ok(waveform)
     }
 }
 
 #[derive(Deserialize, Serialize)]
-struct Schema {
-    kind: String,
-    duration: f64,
-    times: Rc<[f64]>,
-    values: Rc<[f64]>,
+#[serde(tag = "kind")]
+enum Schema {
+    #[serde(rename = "interpolated")]
+    Interpolated {
+        duration: Parameter,
+        times: Rc<[f64]>,
+        values: Rc<[Parameter]>,
+    },
+    #[serde(rename = "constant")]
+    Constant {
+        duration: Parameter,
+        value: Parameter,
+    },
+}
+
+#[test]
+fn test_value_at() {
+    let waveform = Waveform::interpolated(1_000., &[0., 1., 1., 0.5]);
+    for (t, expected) in [
+        (0., 0.),
+        (1_000. / 3., 1.),
+        (2_000. / 3., 1.),
+        (1_000., 0.5),
+    ] {
+        assert!((waveform.value_at(t).unwrap() - expected).abs() < 1e-12);
+    }
+    // Monotone between points, flat where the values are equal.
+    let mut previous = 0.;
+    for t in 0..=333 {
+        let value = waveform.value_at(t as f64).unwrap();
+        assert!(value >= previous && value <= 1.);
+        previous = value;
+    }
+    assert!((waveform.value_at(500.).unwrap() - 1.).abs() < 1e-12);
+
+    let waveform = Waveform::interpolated(100., &[-1., 1.]);
+    assert!((waveform.value_at(25.).unwrap() + 0.5).abs() < 1e-12);
+    assert_eq!(Waveform::constant(100., 3.).value_at(50.), Some(3.));
+    let waveform =
+        Waveform::parametrized(Parameter::Variable("d".into()), &[0f64.into(), 1f64.into()]);
+    assert_eq!(waveform.value_at(0.), None);
 }
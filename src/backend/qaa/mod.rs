@@ -1,31 +1,922 @@
-use std::rc::Rc;
+//! Quantum Adiabatic Algorithm.
+//!
+//! A single pulse slowly sweeps the detuning from negative to positive while the
+//! amplitude rises then falls back to zero. If the sweep is slow enough, the atoms
+//! end up in the ground state of the Rydberg Hamiltonian, i.e. an optimal solution
+//! to the QUBO.
+//!
+//! The shape of the sweep is a [`Schedule`], which may be tuned against emulated
+//! ground-state probabilities with [`optimize`].
 
-use crate::backend::{
-    device::Device,
-    pulser::{pulse::Pulse, register::Register, sequence::Sequence, waveform::Waveform},
-    qubo::Constraints,
+use std::{collections::HashSet, f64::consts::PI, rc::Rc};
+
+use argmin::{
+    core::{CostFunction, Executor},
+    solver::neldermead::NelderMead,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::{
+        device::Device,
+        pulser::{
+            device::PhysicalChannel,
+            pulse::Pulse,
+            register::Register,
+            sequence::Sequence,
+            variable::{Parameter, Variable},
+            waveform::Waveform,
+        },
+        qubo::{self, Constraints},
+    },
+    runtime::run::Sampler,
 };
 
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("device {0} has no global ground-rydberg channel")]
+    NoGlobalRydbergChannel(String),
+
+    #[error("invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("only the default schedule may be parametrized")]
+    CannotParametrize,
+
+    #[error("cannot determine the optimal solutions")]
+    GroundStates(#[source] qubo::Error),
+
+    #[error("failed to sample sequence")]
+    Sampling(#[source] anyhow::Error),
+
+    #[error("failed to optimize schedule")]
+    Optimization(#[source] anyhow::Error),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Options {
-    pub half_duration_ns: f64,
+    /// The duration of the pulse. If unspecified, [`DEFAULT_DURATION_NS`], or with
+    /// `derive_sweep`, one long enough for the sweep to be adiabatic, within the limits of
+    /// the device.
+    pub half_duration_ns: Option<f64>,
+
+    /// If `true`, derive the detuning range and, unless specified, the duration from the
+    /// problem and the device, instead of using [`DEFAULT_DELTA_F`] and
+    /// [`DEFAULT_DURATION_NS`].
+    #[serde(default)]
+    pub derive_sweep: bool,
+
+    /// If `true`, declare the duration, amplitude and detuning endpoints as sequence
+    /// variables, so that they may be changed at run time without recompiling.
+    pub parametrize: bool,
+
+    /// The shape of the sweep.
+    pub schedule: Schedule,
+}
+
+/// The names of the variables declared by a parametrized sequence.
+pub const VAR_DURATION: &str = "duration";
+pub const VAR_OMEGA: &str = "omega";
+pub const VAR_DELTA_0: &str = "delta_0";
+pub const VAR_DELTA_F: &str = "delta_f";
+
+/// The detuning at the end of the sweep, unless derived from the problem. The sweep starts
+/// from the opposite detuning.
+pub const DEFAULT_DELTA_F: f64 = 5.0;
+
+/// The duration of the sweep, unless specified or derived from the problem.
+pub const DEFAULT_DURATION_NS: f64 = 4_000.;
+
+/// The acceptable probability of a diabatic transition at the smallest gap, which
+/// determines the duration of the sweep.
+const DIABATIC_PROBABILITY: f64 = 0.01;
+
+/// The parameters of the sweep, as chosen from the problem and the device.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Report {
+    /// The maximal amplitude, in rad/µs.
+    pub omega: f64,
+
+    /// The initial detuning, in rad/µs.
+    pub delta_0: f64,
+
+    /// The final detuning, in rad/µs.
+    pub delta_f: f64,
+
+    pub duration_ns: f64,
+
+    /// Why these values were chosen, one line per decision.
+    pub reasoning: Vec<String>,
+}
+
+impl Report {
+    /// Derive the parameters of the sweep from the spectrum of `constraints` and the limits
+    /// of `channel`.
+    pub fn choose(
+        constraints: &Constraints,
+        device: &Device,
+        channel: &PhysicalChannel,
+        options: &Options,
+    ) -> Self {
+        let mut reasoning = vec![];
+
+        // Amplitude: the typical interaction, which lets atoms feel the blockade.
+        let mut omega = constraints.omega();
+        reasoning.push(format!(
+            "amplitude {omega:.3} rad/µs: median of the positive QUBO weights"
+        ));
+        if let Some(max_amp) = channel.max_amp {
+            if omega > max_amp {
+                omega = max_amp;
+                reasoning.push(format!(
+                    "amplitude clamped to {max_amp:.3} rad/µs, the maximum of channel {}",
+                    channel.id.0
+                ));
+            }
+        }
+
+        // Detuning: at the end of the sweep, the detuning encodes the QUBO diagonal as
+        // `-delta * n_i`, so when deriving the sweep, we sweep symmetrically up to the typical
+        // diagonal weight.
+        let mut delta_f = if !options.derive_sweep {
+            reasoning.push(format!(
+                "final detuning {DEFAULT_DELTA_F:.3} rad/µs: default"
+            ));
+            DEFAULT_DELTA_F
+        } else {
+            match constraints.diagonal_scale() {
+                Some(scale) => {
+                    reasoning.push(format!(
+                        "final detuning {scale:.3} rad/µs: mean magnitude of the negative QUBO diagonal"
+                    ));
+                    scale
+                }
+                None => {
+                    reasoning.push(format!(
+                        "final detuning {omega:.3} rad/µs: no negative QUBO diagonal, using the amplitude"
+                    ));
+                    omega
+                }
+            }
+        };
+        if let Some(max) = channel.max_abs_detuning {
+            if delta_f > max {
+                delta_f = max;
+                reasoning.push(format!(
+                    "detuning clamped to ±{max:.3} rad/µs, the maximum of channel {}",
+                    channel.id.0
+                ));
+            }
+        }
+        let delta_0 = -delta_f;
+
+        // Duration: by Landau-Zener, crossing a gap `g` at sweep rate `v` causes a diabatic
+        // transition with probability `exp(-π g² / 2v)`.
+        let duration_ns = match options.half_duration_ns {
+            Some(duration_ns) => {
+                reasoning.push(format!("duration {duration_ns} ns: specified by user"));
+                duration_ns
+            }
+            None if !options.derive_sweep => {
+                reasoning.push(format!("duration {DEFAULT_DURATION_NS} ns: default"));
+                DEFAULT_DURATION_NS
+            }
+            None => {
+                // We do not compute the gap of the Rydberg Hamiltonian along the sweep. The
+                // gap between the two lowest QUBO energies, i.e. at the end of the sweep,
+                // stands in for it, as does the amplitude, which opens the gap mid-sweep.
+                let gap = match constraints.spectral_gap() {
+                    Ok(Some(gap)) if gap < omega => {
+                        reasoning.push(format!(
+                            "gap proxy {gap:.3} rad/µs: difference between the two lowest QUBO energies"
+                        ));
+                        gap
+                    }
+                    Ok(_) => {
+                        reasoning.push(format!(
+                            "gap proxy {omega:.3} rad/µs: the amplitude, below the difference between the two lowest QUBO energies"
+                        ));
+                        omega
+                    }
+                    Err(_) => {
+                        reasoning.push(format!(
+                            "gap proxy {omega:.3} rad/µs: the amplitude, {} variables are too many to compute the QUBO energies",
+                            constraints.num_nodes()
+                        ));
+                        omega
+                    }
+                };
+                let duration_us = 2. * f64::ln(1. / DIABATIC_PROBABILITY) * (delta_f - delta_0)
+                    / (std::f64::consts::PI * gap * gap);
+                let mut duration_ns = duration_us * 1_000.;
+                reasoning.push(format!(
+                    "duration {duration_ns:.0} ns: Landau-Zener transition probability below {DIABATIC_PROBABILITY} at the gap proxy"
+                ));
+                let max = [
+                    device.max_sequence_duration().map(f64::from),
+                    channel.max_duration,
+                ]
+                .into_iter()
+                .flatten()
+                .reduce(f64::min);
+                if let Some(max) = max {
+                    if duration_ns > max {
+                        duration_ns = max;
+                        reasoning.push(format!(
+                            "duration clamped to {max} ns, the maximum of device {}; the sweep may not be adiabatic",
+                            device.name()
+                        ));
+                    }
+                }
+                if duration_ns < channel.min_duration {
+                    duration_ns = channel.min_duration;
+                    reasoning.push(format!(
+                        "duration raised to {duration_ns} ns, the minimum of channel {}",
+                        channel.id.0
+                    ));
+                }
+                // Durations must be multiples of the clock period.
+                let clock_period = channel.clock_period;
+                (duration_ns / clock_period).floor() * clock_period
+            }
+        };
+        Self {
+            omega,
+            delta_0,
+            delta_f,
+            duration_ns,
+            reasoning,
+        }
+    }
+}
+
+/// The shape of the amplitude and detuning over the sweep.
+///
+/// Amplitudes are expressed as fractions of the maximal amplitude `omega` and detunings
+/// as fractions of the final detuning `delta_f`, so the same schedule may be used for any
+/// problem. The initial detuning is `-delta_f`.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Schedule {
+    /// Sweep at constant speed, with `points` interpolation points per waveform.
+    ///
+    /// With 3 points, amplitude is `[0, omega, 0]` and detuning `[delta_0, 0, delta_f]`.
+    Linear { points: usize },
+
+    /// Sweep more slowly around the middle of the pulse, where the detuning crosses zero.
+    ///
+    /// The slowdown is always centered on the middle, it does not depend on the spectrum of
+    /// the problem. `slowdown` is in [0, 1): 0 is a linear sweep, values close to 1 spend most
+    /// of the pulse around the middle.
+    CenterSlowdown { points: usize, slowdown: f64 },
+
+    /// Sweep more slowly where the gap of the Rydberg Hamiltonian is small, with `points`
+    /// interpolation points per waveform.
+    ///
+    /// The sweep follows the local adiabatic condition, at a speed proportional to the square
+    /// of the estimated gap, see [`Levels::gap`]. Estimating the gap enumerates the
+    /// configurations of the register, so it needs at most [`qubo::MAX_NODES_BRUTE_FORCE`]
+    /// atoms. Resolved to breakpoints by [`Schedule::resolve`].
+    GapAware { points: usize },
+
+    /// User-specified breakpoints, evenly spaced over the pulse.
+    Breakpoints {
+        /// Fractions of `omega`, in [0, 1].
+        amplitude: Vec<f64>,
+
+        /// Fractions of `delta_f`, in [-1, 1].
+        detuning: Vec<f64>,
+    },
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule::Linear { points: 3 }
+    }
+}
+
+impl Schedule {
+    /// The normalized amplitude and detuning breakpoints of this schedule.
+    ///
+    /// Fails for [`Schedule::GapAware`], which depends on the register, see
+    /// [`Schedule::resolve`].
+    pub fn breakpoints(&self) -> Result<(Vec<f64>, Vec<f64>), Error> {
+        let (amplitude, detuning) = match *self {
+            Schedule::Linear { points } => Self::sweep(points, |u| u),
+            Schedule::GapAware { .. } => {
+                return Err(Error::InvalidSchedule(
+                    "the gap-aware schedule depends on the register".to_string(),
+                ))
+            }
+            Schedule::CenterSlowdown { points, slowdown } => {
+                if !(0. ..1.).contains(&slowdown) {
+                    return Err(Error::InvalidSchedule(format!(
+                        "slowdown must be in [0, 1), got {slowdown}"
+                    )));
+                }
+                // ds/du = 1 + slowdown * cos(2πu), smallest at u = 0.5.
+                Self::sweep(points, |u| u + slowdown / (2. * PI) * f64::sin(2. * PI * u))
+            }
+            Schedule::Breakpoints {
+                ref amplitude,
+                ref detuning,
+            } => (amplitude.clone(), detuning.clone()),
+        };
+        if amplitude.len() < 2 || detuning.len() < 2 {
+            return Err(Error::InvalidSchedule(
+                "each waveform needs at least 2 points".to_string(),
+            ));
+        }
+        if let Some(a) = amplitude.iter().find(|a| !(0. ..=1.).contains(*a)) {
+            return Err(Error::InvalidSchedule(format!(
+                "amplitude breakpoints must be in [0, 1], got {a}"
+            )));
+        }
+        if let Some(d) = detuning.iter().find(|d| !(-1. ..=1.).contains(*d)) {
+            return Err(Error::InvalidSchedule(format!(
+                "detuning breakpoints must be in [-1, 1], got {d}"
+            )));
+        }
+        Ok((amplitude, detuning))
+    }
+
+    /// Replace a schedule which depends on the register with its breakpoints, recording
+    /// why in `report`. Other schedules are returned as-is.
+    pub fn resolve(
+        &self,
+        report: &mut Report,
+        register: &Register,
+        device: &Device,
+    ) -> Result<Schedule, Error> {
+        let Schedule::GapAware { points } = *self else {
+            return Ok(self.clone());
+        };
+        let Some(levels) = Levels::new(register, device) else {
+            return Err(Error::InvalidSchedule(format!(
+                "the gap-aware schedule needs at most {} atoms, got {}",
+                qubo::MAX_NODES_BRUTE_FORCE,
+                register.len()
+            )));
+        };
+        // The gap at progress `s` of the standard sweep, see `Self::sweep`.
+        let gap = |s: f64| {
+            levels.gap(
+                report.delta_f * (2. * s - 1.),
+                report.omega * (1. - f64::abs(2. * s - 1.)),
+            )
+        };
+
+        // Time spent between `s` and `s + ds` is proportional to `ds / gap(s)²`.
+        let steps = 1_000;
+        let mut elapsed = vec![0.];
+        let mut min_gap = (f64::INFINITY, 0.);
+        for k in 0..steps {
+            let s = (k as f64 + 0.5) / steps as f64;
+            let g = gap(s).max(GAP_FLOOR * report.omega);
+            if g < min_gap.0 {
+                min_gap = (g, s);
+            }
+            elapsed.push(elapsed[k] + 1. / (g * g));
+        }
+        let total = elapsed[steps];
+        let progress = |u: f64| {
+            let t = u * total;
+            let k = elapsed.partition_point(|e| *e < t).clamp(1, steps);
+            let fraction = (t - elapsed[k - 1]) / (elapsed[k] - elapsed[k - 1]);
+            (k as f64 - 1. + fraction) / steps as f64
+        };
+        let (amplitude, detuning) = Self::sweep(points, progress);
+        let (gap, s) = min_gap;
+        report.reasoning.push(format!(
+            "gap-aware schedule: estimated gap of the Rydberg Hamiltonian smallest at {gap:.3} rad/µs, for detuning {:.3} rad/µs",
+            report.delta_f * (2. * s - 1.)
+        ));
+        Ok(Schedule::Breakpoints {
+            amplitude,
+            detuning,
+        })
+    }
+
+    /// Sample the standard sweep at `points` evenly spaced times, with progress `s(u)`.
+    fn sweep(points: usize, progress: impl Fn(f64) -> f64) -> (Vec<f64>, Vec<f64>) {
+        if points < 2 {
+            return (vec![], vec![]);
+        }
+        let s = (0..points)
+            .map(|k| progress(k as f64 / (points - 1) as f64))
+            .collect::<Vec<_>>();
+        let amplitude = s.iter().map(|s| 1. - f64::abs(2. * s - 1.)).collect();
+        let detuning = s.iter().map(|s| 2. * s - 1.).collect();
+        (amplitude, detuning)
+    }
+}
+
+/// With [`Schedule::GapAware`], the smallest gap, as a fraction of the amplitude, which keeps
+/// the sweep from stalling where levels cross without a drive, i.e. at the ends of the sweep.
+const GAP_FLOOR: f64 = 0.05;
+
+/// The lowest energies of the interactions within a register, by number of excited atoms.
+///
+/// At detuning `delta`, a configuration with `c` excited atoms and interaction energy `u`
+/// has energy `u - delta * c`, so these are enough to find the two lowest levels of the
+/// Rydberg Hamiltonian without a drive at any detuning.
+pub struct Levels {
+    /// For each number of excited atoms, the lowest interaction energy and the next distinct
+    /// one, in rad/µs, or infinity if there is none.
+    lowest: Vec<[f64; 2]>,
 }
 
+impl Levels {
+    /// Enumerate the configurations of `register`, or `None` if it has more than
+    /// [`qubo::MAX_NODES_BRUTE_FORCE`] atoms.
+    pub fn new(register: &Register, device: &Device) -> Option<Self> {
+        let n = register.len();
+        if n > qubo::MAX_NODES_BRUTE_FORCE {
+            return None;
+        }
+        let atoms = register
+            .coordinates
+            .iter()
+            .map(|(c, _)| *c)
+            .collect::<Vec<_>>();
+        let interactions = atoms
+            .iter()
+            .enumerate()
+            .map(|(i, first)| {
+                atoms
+                    .iter()
+                    .enumerate()
+                    .map(|(j, second)| {
+                        if i == j {
+                            0.
+                        } else {
+                            device.interaction(*first, *second)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let tolerance = 1e-9
+            * interactions
+                .iter()
+                .flatten()
+                .fold(1f64, |max, u| max.max(*u));
+
+        let mut lowest = vec![[f64::INFINITY; 2]; n + 1];
+        lowest[0][0] = 0.;
+        // Gray code order, so that each configuration differs from the previous one by a
+        // single atom, as in `analysis::GroundStates::new`.
+        let mut excited = vec![false; n];
+        let mut fields = vec![0.; n];
+        let mut energy = 0.;
+        let mut count = 0;
+        for step in 1u64..(1 << n) {
+            let k = step.trailing_zeros() as usize;
+            let sign = if excited[k] { -1. } else { 1. };
+            energy += sign * fields[k];
+            excited[k] = !excited[k];
+            count = if excited[k] { count + 1 } else { count - 1 };
+            for (field, interaction) in fields.iter_mut().zip(&interactions[k]) {
+                *field += sign * interaction;
+            }
+            let [first, second] = &mut lowest[count];
+            if energy < *first - tolerance {
+                *second = *first;
+                *first = energy;
+            } else if energy > *first + tolerance && energy < *second {
+                *second = energy;
+            }
+        }
+        Some(Self { lowest })
+    }
+
+    /// An estimate of the gap between the two lowest levels of the Rydberg Hamiltonian, at
+    /// detuning `delta` and amplitude `omega`, both in rad/µs.
+    ///
+    /// Without a drive, the gap is the difference `d` between the two lowest levels, which
+    /// closes where levels with distinct numbers of excited atoms cross. The drive couples
+    /// configurations with amplitude `omega / 2`, which opens these crossings as between two
+    /// levels, to `sqrt(d² + omega²)`. Configurations with as many excited atoms and the same
+    /// interaction energy are degenerate at any detuning, and count as a single level.
+    pub fn gap(&self, delta: f64, omega: f64) -> f64 {
+        let mut energies = self
+            .lowest
+            .iter()
+            .enumerate()
+            .flat_map(|(count, energies)| energies.map(|u| u - delta * count as f64))
+            .filter(|e| e.is_finite())
+            .collect::<Vec<_>>();
+        energies.sort_by(f64::total_cmp);
+        let d = match energies[..] {
+            [ground, excited, ..] => excited - ground,
+            _ => f64::INFINITY,
+        };
+        f64::hypot(d, omega)
+    }
+}
+
+/// Compile a QAA sequence, choosing the parameters of the sweep from the problem and
+/// the device.
 pub fn compile(
     constraints: &Constraints,
     device: Device,
     register: Register,
     options: &Options,
-) -> Sequence {
-    let omega = constraints.omega();
-    let delta_0 = -5.0; // Any negative number will do.
-    let delta_f = -delta_0; // Any positive number will do.
-    let amplitude = Waveform::interpolated(options.half_duration_ns, &[0., omega, 0.]);
-    let detuning = Waveform::interpolated(options.half_duration_ns, &[delta_0, 0f64, delta_f]);
-    let channel: Rc<str> = "ising".into();
-    Sequence::new(
+) -> Result<(Sequence, Report), Error> {
+    let Some(channel) = device.global_rydberg_channel() else {
+        return Err(Error::NoGlobalRydbergChannel(device.name().to_string()));
+    };
+    let mut report = Report::choose(constraints, &device, channel, options);
+    let options = Options {
+        schedule: options.schedule.resolve(&mut report, &register, &device)?,
+        ..options.clone()
+    };
+    let sequence = compile_with(&report, device, register, &options)?;
+    Ok((sequence, report))
+}
+
+/// Compile a QAA sequence with already chosen parameters.
+fn compile_with(
+    report: &Report,
+    device: Device,
+    register: Register,
+    options: &Options,
+) -> Result<Sequence, Error> {
+    let channel: Rc<str> = match device.global_rydberg_channel() {
+        None => return Err(Error::NoGlobalRydbergChannel(device.name().to_string())),
+        Some(channel) => channel.id.0.as_str().into(),
+    };
+    if !options.parametrize {
+        let (amplitude, detuning) = options.schedule.breakpoints()?;
+        let amplitude = amplitude
+            .iter()
+            .map(|a| a * report.omega)
+            .collect::<Vec<_>>();
+        let detuning = detuning
+            .iter()
+            .map(|d| d * report.delta_f)
+            .collect::<Vec<_>>();
+        let amplitude = Waveform::interpolated(report.duration_ns, &amplitude);
+        let detuning = Waveform::interpolated(report.duration_ns, &detuning);
+        return Ok(Sequence::new(
+            device,
+            register,
+            Pulse::new(channel.clone(), amplitude, detuning),
+            &[channel],
+        ));
+    }
+    if options.schedule != Schedule::default() {
+        return Err(Error::CannotParametrize);
+    }
+
+    let var = |name: &str| Parameter::Variable(name.into());
+    let amplitude = Waveform::parametrized(
+        var(VAR_DURATION),
+        &[0f64.into(), var(VAR_OMEGA), 0f64.into()],
+    );
+    let detuning = Waveform::parametrized(
+        var(VAR_DURATION),
+        &[var(VAR_DELTA_0), 0f64.into(), var(VAR_DELTA_F)],
+    );
+    Ok(Sequence::new(
         device,
         register,
         Pulse::new(channel.clone(), amplitude, detuning),
         &[channel],
     )
+    .with_variables(vec![
+        Variable::int(VAR_DURATION, report.duration_ns.round()),
+        Variable::float(VAR_OMEGA, report.omega),
+        Variable::float(VAR_DELTA_0, report.delta_0),
+        Variable::float(VAR_DELTA_F, report.delta_f),
+    ]))
+}
+
+pub struct OptimizeOptions {
+    /// An upper bound to the number of iterations of the classical optimizer.
+    pub max_iters: u64,
+}
+
+/// Compile a QAA sequence, tuning the interior breakpoints of `options.schedule` to
+/// maximize the probability of sampling an optimal solution with `sampler`.
+///
+/// The endpoints of both waveforms are left untouched. Returns the sequence, the parameters
+/// of the sweep, the tuned schedule and its ground-state probability.
+pub fn optimize(
+    constraints: &Constraints,
+    device: &Device,
+    register: &Register,
+    options: &Options,
+    optimize: &OptimizeOptions,
+    sampler: &Sampler,
+) -> Result<(Sequence, Report, Schedule, f64), Error> {
+    if options.parametrize {
+        return Err(Error::CannotParametrize);
+    }
+    let Some(channel) = device.global_rydberg_channel() else {
+        return Err(Error::NoGlobalRydbergChannel(device.name().to_string()));
+    };
+    let mut report = Report::choose(constraints, device, channel, options);
+    let options = &Options {
+        schedule: options.schedule.resolve(&mut report, register, device)?,
+        ..options.clone()
+    };
+    let ground_states = constraints
+        .ground_states()
+        .map_err(Error::GroundStates)?
+        .into_iter()
+        .collect::<HashSet<_>>();
+    let (amplitude, detuning) = options.schedule.breakpoints()?;
+    let cost = Cost {
+        report: &report,
+        device,
+        register,
+        sampler,
+        options,
+        ground_states: &ground_states,
+        amplitude: &amplitude,
+        detuning: &detuning,
+    };
+
+    // Nelder-Mead needs dimensions + 1 starting points: the initial schedule, plus the
+    // initial schedule with each interior breakpoint nudged in turn.
+    let start = cost.to_param(&amplitude, &detuning);
+    let schedule = if start.is_empty() {
+        // Nothing to tune.
+        options.schedule.clone()
+    } else {
+        let mut params = vec![start.clone()];
+        for i in 0..start.len() {
+            let mut point = start.clone();
+            point[i] = if point[i] > 0.5 {
+                point[i] - 0.25
+            } else {
+                point[i] + 0.25
+            };
+            params.push(point);
+        }
+        let optimized = Executor::new(cost.clone(), NelderMead::new(params))
+            .configure(|state| state.max_iters(optimize.max_iters))
+            .run()
+            .map_err(Error::Optimization)?;
+        match optimized.state.best_param {
+            Some(ref param) => cost.schedule(param),
+            None => options.schedule.clone(),
+        }
+    };
+    let sequence = compile_with(
+        &report,
+        device.clone(),
+        register.clone(),
+        &Options {
+            half_duration_ns: options.half_duration_ns,
+            derive_sweep: options.derive_sweep,
+            parametrize: false,
+            schedule: schedule.clone(),
+        },
+    )?;
+    let probability = cost.ground_state_probability(&sequence)?;
+    Ok((sequence, report, schedule, probability))
+}
+
+#[derive(Clone)]
+struct Cost<'a> {
+    report: &'a Report,
+    device: &'a Device,
+    register: &'a Register,
+    sampler: &'a Sampler<'a>,
+    options: &'a Options,
+    ground_states: &'a HashSet<String>,
+
+    /// The initial breakpoints, whose endpoints are kept.
+    amplitude: &'a [f64],
+    detuning: &'a [f64],
+}
+
+impl Cost<'_> {
+    /// The interior breakpoints, i.e. the parameters to tune.
+    fn to_param(&self, amplitude: &[f64], detuning: &[f64]) -> Vec<f64> {
+        let interior = |v: &[f64]| v[1..v.len() - 1].to_vec();
+        let mut param = interior(amplitude);
+        param.extend(interior(detuning));
+        param
+    }
+
+    fn schedule(&self, param: &[f64]) -> Schedule {
+        let (amplitude, detuning) = param.split_at(self.amplitude.len() - 2);
+        let with_endpoints = |endpoints: &[f64], interior: &[f64]| {
+            let mut v = vec![endpoints[0]];
+            v.extend(interior);
+            v.push(endpoints[endpoints.len() - 1]);
+            v
+        };
+        Schedule::Breakpoints {
+            amplitude: with_endpoints(self.amplitude, amplitude),
+            detuning: with_endpoints(self.detuning, detuning),
+        }
+    }
+
+    fn ground_state_probability(&self, sequence: &Sequence) -> Result<f64, Error> {
+        let samples = (self.sampler)(sequence).map_err(Error::Sampling)?;
+        let total = samples.iter().map(|s| s.instances).sum::<u64>();
+        if total == 0 {
+            return Ok(0.);
+        }
+        let hits = samples
+            .iter()
+            .filter(|s| self.ground_states.contains(&s.bitstring))
+            .map(|s| s.instances)
+            .sum::<u64>();
+        Ok(hits as f64 / total as f64)
+    }
+}
+
+impl CostFunction for Cost<'_> {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, anyhow::Error> {
+        let schedule = self.schedule(param);
+        // Out-of-range breakpoints are not physical, don't even try them.
+        if schedule.breakpoints().is_err() {
+            return Ok(f64::INFINITY);
+        }
+        let sequence = compile_with(
+            self.report,
+            self.device.clone(),
+            self.register.clone(),
+            &Options {
+                half_duration_ns: self.options.half_duration_ns,
+                derive_sweep: self.options.derive_sweep,
+                parametrize: false,
+                schedule,
+            },
+        )?;
+        let probability = self.ground_state_probability(&sequence)?;
+        eprintln!(
+            "...QAA breakpoints {:?} => ground-state probability {}",
+            param, probability
+        );
+        Ok(1. - probability)
+    }
+}
+
+#[test]
+fn test_qaa_schedules() {
+    use crate::{backend::pulser::sequence::fixture, runtime::run::Sample};
+
+    let (amplitude, detuning) = Schedule::default().breakpoints().unwrap();
+    assert_eq!(amplitude, vec![0., 1., 0.]);
+    assert_eq!(detuning, vec![-1., 0., 1.]);
+
+    // Slowing down around the middle keeps the endpoints but packs points near it.
+    let (linear, _) = Schedule::Linear { points: 5 }.breakpoints().unwrap();
+    let (center_slowdown, _) = Schedule::CenterSlowdown {
+        points: 5,
+        slowdown: 0.5,
+    }
+    .breakpoints()
+    .unwrap();
+    assert_eq!(center_slowdown[0], 0.);
+    assert!(center_slowdown[1] > linear[1]);
+    assert!((center_slowdown[2] - 1.).abs() < 1e-9);
+
+    // The gap-aware schedule slows down where the ground state changes: with two atoms in
+    // blockade, from no atom to one excited atom at detuning 0, then never again.
+    let device = Device::analog();
+    let register = fixture::atoms(&[("a", 0., 0.), ("b", 5., 0.)]);
+    let levels = Levels::new(&register, &device).unwrap();
+    assert!(levels.gap(0., 0.) < 1e-9);
+    assert!((levels.gap(-1., 0.) - 1.).abs() < 1e-9);
+    assert!((levels.gap(0., 2.) - 2.).abs() < 1e-9);
+    let mut report = Report {
+        omega: 1.,
+        delta_0: -5.,
+        delta_f: 5.,
+        duration_ns: 4_000.,
+        reasoning: vec![],
+    };
+    let Schedule::Breakpoints { detuning, .. } = Schedule::GapAware { points: 9 }
+        .resolve(&mut report, &register, &device)
+        .unwrap()
+    else {
+        panic!("expected breakpoints")
+    };
+    assert_eq!(detuning.len(), 9);
+    assert_eq!((detuning[0], detuning[8]), (-1., 1.));
+    let (_, linear) = Schedule::Linear { points: 9 }.breakpoints().unwrap();
+    let near_crossing = |d: &[f64]| d.iter().filter(|d| d.abs() < 0.25).count();
+    assert!(near_crossing(&detuning) > near_crossing(&linear));
+    assert!(report.reasoning[0].starts_with("gap-aware schedule"));
+    assert!(Schedule::GapAware { points: 9 }.breakpoints().is_err());
+    assert!(Schedule::GapAware { points: 9 }
+        .resolve(&mut report, &fixture::register(21), &device)
+        .is_err());
+
+    assert!(Schedule::Breakpoints {
+        amplitude: vec![0., 2., 0.],
+        detuning: vec![-1., 1.],
+    }
+    .breakpoints()
+    .is_err());
+
+    let constraints = Constraints::from_const(
+        [[-1., 2., 0.], [2., -1., 0.], [0., 0., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let register = fixture::atoms(&[("a", 0., 0.), ("b", 5., 0.), ("c", 20., 0.)]);
+
+    // A fake runner, which finds one of the optimal solutions half of the time.
+    let sampler = |_: &Sequence| {
+        Ok(vec![
+            Sample {
+                bitstring: "101".to_string(),
+                instances: 1,
+            },
+            Sample {
+                bitstring: "011".to_string(),
+                instances: 1,
+            },
+            Sample {
+                bitstring: "111".to_string(),
+                instances: 2,
+            },
+        ])
+    };
+    let (sequence, _, schedule, probability) = optimize(
+        &constraints,
+        &Device::analog(),
+        &register,
+        &Options {
+            half_duration_ns: Some(1_000.),
+            derive_sweep: false,
+            parametrize: false,
+            schedule: Schedule::Linear { points: 4 },
+        },
+        &OptimizeOptions { max_iters: 3 },
+        &sampler,
+    )
+    .unwrap();
+    sequence.to_validated_json().unwrap();
+    let Schedule::Breakpoints {
+        amplitude,
+        detuning,
+    } = schedule
+    else {
+        panic!("expected breakpoints")
+    };
+    assert_eq!((amplitude.len(), detuning.len()), (4, 4));
+    assert_eq!((amplitude[0], detuning[3]), (0., 1.));
+    assert_eq!(probability, 0.5);
+}
+
+#[test]
+fn test_qaa_report() {
+    let constraints = Constraints::from_const(
+        [[-1., 2., 0.], [2., -1., 0.], [0., 0., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let device = Device::analog();
+    let channel = device.global_rydberg_channel().unwrap();
+    let options = Options {
+        half_duration_ns: None,
+        derive_sweep: false,
+        parametrize: false,
+        schedule: Schedule::default(),
+    };
+
+    // By default, only the amplitude depends on the problem.
+    let report = Report::choose(&constraints, &device, channel, &options);
+    assert_eq!(report.omega, 2.);
+    assert_eq!(
+        (report.delta_0, report.delta_f),
+        (-DEFAULT_DELTA_F, DEFAULT_DELTA_F)
+    );
+    assert_eq!(report.duration_ns, DEFAULT_DURATION_NS);
+
+    let options = Options {
+        derive_sweep: true,
+        ..options
+    };
+    let report = Report::choose(&constraints, &device, channel, &options);
+    assert_eq!(report.omega, 2.);
+    assert_eq!((report.delta_0, report.delta_f), (-1., 1.));
+    // The gap between "101" (-2) and "100" (-1) requires ~5.9µs, more than the device allows.
+    assert_eq!(report.duration_ns, 4_000.);
+    assert!(report.reasoning.iter().any(|line| line.contains("clamped")));
+    assert!(report
+        .reasoning
+        .iter()
+        .any(|line| line.contains("gap proxy")));
+
+    let report = Report::choose(
+        &constraints,
+        &device,
+        channel,
+        &Options {
+            half_duration_ns: Some(1_500.),
+            ..options
+        },
+    );
+    assert_eq!(report.duration_ns, 1_500.);
 }
@@ -0,0 +1,273 @@
+//! Quantum Approximate Optimization Algorithm.
+//!
+//! Where QAA performs a single slow sweep, QAOA alternates `p` layers of
+//!
+//! 1. a mixing pulse (constant amplitude, no detuning), which lets atoms oscillate
+//!    between ground and Rydberg states;
+//! 2. a phase pulse (no amplitude, constant detuning), during which the state evolves
+//!    under the interactions and detuning only, i.e. under the cost Hamiltonian.
+//!
+//! The durations of these pulses (the "angles" of the algorithm) are then tuned by a
+//! classical optimizer, which evaluates each candidate by sampling the sequence with a
+//! runner and measuring the mean QUBO energy of the samples.
+
+use std::rc::Rc;
+
+use argmin::{
+    core::{CostFunction, Executor},
+    solver::neldermead::NelderMead,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::{
+        device::Device,
+        pulser::{pulse::Pulse, register::Register, sequence::Sequence, waveform::Waveform},
+        qubo::Constraints,
+    },
+    runtime::run::{Sample, Sampler},
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("device {0} has no global ground-rydberg channel")]
+    NoGlobalRydbergChannel(String),
+
+    #[error("QAOA needs at least one layer")]
+    NoLayers,
+
+    #[error("failed to sample sequence")]
+    Sampling(#[source] anyhow::Error),
+
+    #[error("failed to optimize angles")]
+    Optimization(#[source] anyhow::Error),
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Options {
+    /// The number of layers `p`.
+    pub layers: usize,
+
+    /// The total duration of the initial guess, in ns.
+    pub duration_ns: f64,
+
+    /// An upper bound to the number of iterations of the classical optimizer.
+    pub max_iters: u64,
+}
+
+/// The durations of the pulses in each layer, in ns.
+#[derive(Clone, Debug)]
+pub struct Angles {
+    pub mixing: Vec<f64>,
+    pub phase: Vec<f64>,
+}
+impl Angles {
+    /// A discretized linear ramp, which mimics an adiabatic sweep and is a common
+    /// starting point for QAOA: mixing layers get shorter, phase layers get longer.
+    pub fn linear_ramp(layers: usize, duration_ns: f64) -> Self {
+        let step = duration_ns / layers as f64;
+        let fraction = |k: usize| (k as f64 + 0.5) / layers as f64;
+        Self {
+            mixing: (0..layers).map(|k| step * (1. - fraction(k))).collect(),
+            phase: (0..layers).map(|k| step * fraction(k)).collect(),
+        }
+    }
+    fn to_param(&self) -> Vec<f64> {
+        self.mixing.iter().chain(&self.phase).cloned().collect()
+    }
+    fn from_param(param: &[f64]) -> Self {
+        let (mixing, phase) = param.split_at(param.len() / 2);
+        Self {
+            mixing: mixing.to_vec(),
+            phase: phase.to_vec(),
+        }
+    }
+}
+
+/// Compile a QAOA sequence with fixed angles.
+pub fn compile(
+    constraints: &Constraints,
+    device: Device,
+    register: Register,
+    angles: &Angles,
+) -> Result<Sequence, Error> {
+    let Some(channel) = device.global_rydberg_channel() else {
+        return Err(Error::NoGlobalRydbergChannel(device.name().to_string()));
+    };
+    if angles.mixing.is_empty() {
+        return Err(Error::NoLayers);
+    }
+    let omega = match channel.max_amp {
+        Some(max_amp) => f64::min(constraints.omega(), max_amp),
+        None => constraints.omega(),
+    };
+    // The QUBO diagonal is encoded as `-delta * n_i`, so we pick the typical diagonal weight.
+    let delta = constraints.diagonal_scale().unwrap_or(1.);
+    let delta = match channel.max_abs_detuning {
+        Some(max) => delta.clamp(-max, max),
+        None => delta,
+    };
+
+    // Durations must be multiples of the clock period, no shorter than the minimal duration.
+    let (clock_period, min_duration) = (channel.clock_period, channel.min_duration);
+    let quantize = |duration: f64| {
+        let duration = (duration / clock_period).round() * clock_period;
+        f64::max(duration, min_duration)
+    };
+
+    let channel_id: Rc<str> = channel.id.0.as_str().into();
+    let mut pulses = Vec::with_capacity(2 * angles.mixing.len());
+    for (mixing, phase) in angles.mixing.iter().zip(&angles.phase) {
+        let mixing = quantize(*mixing);
+        pulses.push(Pulse::new(
+            channel_id.clone(),
+            Waveform::constant(mixing, omega),
+            Waveform::constant(mixing, 0.),
+        ));
+        let phase = quantize(*phase);
+        pulses.push(Pulse::new(
+            channel_id.clone(),
+            Waveform::constant(phase, 0.),
+            Waveform::constant(phase, delta),
+        ));
+    }
+    Ok(Sequence::from_pulses(
+        device,
+        register,
+        pulses,
+        &[channel_id],
+    ))
+}
+
+/// The mean QUBO energy of a set of samples.
+pub fn mean_energy(constraints: &Constraints, samples: &[Sample]) -> Result<f64, anyhow::Error> {
+    let mut total = 0.;
+    let mut instances = 0;
+    for sample in samples {
+        total += constraints.energy(&sample.bitstring)? * sample.instances as f64;
+        instances += sample.instances;
+    }
+    if instances == 0 {
+        return Err(anyhow::anyhow!("no samples"));
+    }
+    Ok(total / instances as f64)
+}
+
+/// Compile a QAOA sequence, optimizing its angles against energies sampled with `sampler`.
+///
+/// Returns the sequence, the angles and the mean energy of the best candidate.
+pub fn optimize(
+    constraints: &Constraints,
+    device: &Device,
+    register: &Register,
+    options: &Options,
+    sampler: &Sampler,
+) -> Result<(Sequence, Angles, f64), Error> {
+    if options.layers == 0 {
+        return Err(Error::NoLayers);
+    }
+    let initial = Angles::linear_ramp(options.layers, options.duration_ns);
+
+    // Nelder-Mead needs dimensions + 1 starting points: the initial guess, plus
+    // the initial guess with each angle stretched in turn.
+    let start = initial.to_param();
+    let mut params = vec![start.clone()];
+    for i in 0..start.len() {
+        let mut point = start.clone();
+        point[i] *= 1.5;
+        params.push(point);
+    }
+    let cost = Cost {
+        constraints,
+        device,
+        register,
+        sampler,
+    };
+    let optimized = Executor::new(cost, NelderMead::new(params))
+        .configure(|state| state.max_iters(options.max_iters))
+        .run()
+        .map_err(Error::Optimization)?;
+    let angles = match optimized.state.best_param {
+        Some(ref param) => Angles::from_param(param),
+        None => initial,
+    };
+    let energy = optimized.state.best_cost;
+    let sequence = compile(constraints, device.clone(), register.clone(), &angles)?;
+    Ok((sequence, angles, energy))
+}
+
+struct Cost<'a> {
+    constraints: &'a Constraints,
+    device: &'a Device,
+    register: &'a Register,
+    sampler: &'a Sampler<'a>,
+}
+
+impl CostFunction for Cost<'_> {
+    type Param = Vec<f64>;
+    type Output = f64;
+
+    fn cost(&self, param: &Self::Param) -> Result<Self::Output, anyhow::Error> {
+        // Negative durations make no sense, don't even try them.
+        if param.iter().any(|d| *d < 0.) {
+            return Ok(f64::INFINITY);
+        }
+        let sequence = compile(
+            self.constraints,
+            self.device.clone(),
+            self.register.clone(),
+            &Angles::from_param(param),
+        )?;
+        let samples = (self.sampler)(&sequence).map_err(Error::Sampling)?;
+        let energy = mean_energy(self.constraints, &samples)?;
+        eprintln!("...QAOA angles {:?} => mean energy {}", param, energy);
+        Ok(energy)
+    }
+}
+
+#[test]
+fn test_qaoa_compile_and_optimize() {
+    use crate::backend::pulser::sequence::fixture;
+
+    let constraints = Constraints::from_const(
+        [[-1., 2., 0.], [2., -1., 0.], [0., 0., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let register = fixture::atoms(&[("a", 0., 0.), ("b", 5., 0.), ("c", 20., 0.)]);
+    let device = Device::analog();
+
+    let angles = Angles::linear_ramp(3, 3_000.);
+    let sequence = compile(&constraints, device.clone(), register.clone(), &angles).unwrap();
+    assert_eq!(sequence.operations().len(), 6);
+    sequence.to_validated_json().unwrap();
+
+    // A fake runner, which always finds one of the optimal solutions.
+    let sampler = |_: &Sequence| {
+        Ok(vec![
+            Sample {
+                bitstring: "101".to_string(),
+                instances: 3,
+            },
+            Sample {
+                bitstring: "111".to_string(),
+                instances: 1,
+            },
+        ])
+    };
+    let (sequence, angles, energy) = optimize(
+        &constraints,
+        &device,
+        &register,
+        &Options {
+            layers: 2,
+            duration_ns: 2_000.,
+            max_iters: 3,
+        },
+        &sampler,
+    )
+    .unwrap();
+    assert_eq!(sequence.operations().len(), 4);
+    assert_eq!(angles.mixing.len(), 2);
+    // Energies: "101" => -2, "111" => -3 + 2 * 2 = 1.
+    assert_eq!(energy, (3. * -2. + 1.) / 4.);
+}
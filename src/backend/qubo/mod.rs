@@ -21,7 +21,11 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize, Serializer};
 
 use crate::{
-    backend::{device::Device, pulser::register::Register},
+    backend::{
+        analysis::{Acceptance, LayoutReport},
+        device::Device,
+        pulser::register::Register,
+    },
     types::{
         units::{self, Coordinates, Inv, Micrometers, Microseconds, Mul, Rad},
         Quality,
@@ -37,11 +41,14 @@ pub enum Error {
         num_nodes: usize,
     },
 
-    #[error("layout error #?")]
-    Layout(argmin::core::Error),
+    #[error("register optimizer failed")]
+    Layout(#[source] argmin::core::Error),
+
+    #[error("failed to layout within {seeds} seeds, best attempt: {reason}")]
+    NoSolution { seeds: u64, reason: String },
 
-    #[error("failed to layout")]
-    NoSolution,
+    #[error("cannot measure the layout")]
+    Report(#[source] Box<crate::backend::analysis::Error>),
 
     #[error("algorithm cannot layout off-diagonal negative values")]
     NegativeValueOffDiagonal,
@@ -51,28 +58,96 @@ pub enum Error {
 
     #[error("value is infinite or not a number")]
     InfiniteValue,
+
+    #[error("invalid bitstring {0}")]
+    InvalidBitstring(String),
+
+    #[error("too many nodes ({0}) to enumerate all solutions")]
+    TooLargeForBruteForce(usize),
 }
 
-#[derive(Clone, Debug)]
+/// The largest problem for which we enumerate all assignments, to find the optimal solutions,
+/// the spectrum or the ground states of the register. The cost doubles with each variable.
+pub const MAX_NODES_BRUTE_FORCE: usize = 20;
+
+/// Energies closer than this are considered equal.
+const ENERGY_TOLERANCE: f64 = 1e-9;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Options {
     pub seed: u64,
     pub min_quality: Quality,
     pub max_iters: u64,
     pub overflow_protection_threshold: f64,
     pub overflow_protection_factor: f64,
+
+    /// How many seeds to try before giving up.
+    #[serde(default = "default_max_seeds")]
+    pub max_seeds: u64,
+
+    /// Thresholds on concrete metrics of the layout, in addition to `min_quality`.
+    #[serde(default)]
+    pub acceptance: Acceptance,
 }
 impl Default for Options {
     fn default() -> Self {
         Self {
             seed: 0,
-            min_quality: Quality::new(0.2),
+            min_quality: Quality::new(0.2).unwrap_or(Quality::WORST),
             max_iters: 4_000,
             overflow_protection_threshold: 0.9,
             overflow_protection_factor: 1_000.,
+            max_seeds: default_max_seeds(),
+            acceptance: Acceptance::default(),
         }
     }
 }
 
+/// Headroom kept below the strongest interaction of the device, so that the approximations
+/// of the layout do not push the closest atoms below the minimum distance.
+const INTERACTION_HEADROOM: f64 = 0.8;
+
+/// A global scale factor applied to the QUBO before compilation.
+///
+/// Scaling all the weights by a positive factor does not change the optimal solutions, but
+/// the scaled weights are interpreted in rad/µs, so the factor decides whether they fit the
+/// device. Energies of the scaled QUBO are divided by `factor` to report them in problem units.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Normalization {
+    /// rad/µs per problem unit.
+    pub factor: f64,
+
+    /// How the factor was chosen, one line per limit.
+    pub reasoning: Vec<String>,
+}
+
+impl Normalization {
+    /// Convert an energy of the scaled QUBO back to problem units.
+    pub fn to_problem_units(&self, energy: f64) -> f64 {
+        energy / self.factor
+    }
+}
+
+fn default_max_seeds() -> u64 {
+    10_000
+}
+
+/// A register laid out for a set of constraints, see [`Constraints::layout`].
+#[derive(Clone)]
+pub struct Layout {
+    /// The geometry, one atom per variable.
+    pub register: Register,
+
+    /// An abstract measure of quality, where 0 is really bad and 1 is optimal.
+    pub quality: Quality,
+
+    /// The seed with which we found this layout.
+    pub seed: u64,
+
+    /// Concrete measures of quality, e.g. the error on each coupling.
+    pub report: LayoutReport,
+}
+
 /// A set of qubo constraints.
 ///
 /// For (de)serialization, please use `format::Format`.
@@ -157,15 +232,24 @@ impl Constraints {
     /// In the current implementation, we run N concurrent instances of a Nelder-Mead optimizer,
     /// with distinct start states, where N is determined from the number of cores on the computer.
     ///
-    /// In case of success, returns:
-    /// - Register: the geometry;
-    /// - Quality: an abstract measure of quality, where 0 is really bad and 1 is optimal;
-    /// - seed: the seed with which we found a solution.
-    pub fn layout(&self, device: &Device, options: &Options) -> Option<(Register, Quality, u64)> {
+    /// A layout is accepted if its quality is at least `options.min_quality` and its report
+    /// passes `options.acceptance`. We try up to `options.max_seeds` seeds, starting from
+    /// `options.seed`, then give up with the reason why the best attempt was rejected.
+    pub fn layout(&self, device: &Device, options: &Options) -> Result<Layout, Error> {
         // self.check_compilable_subset().expect("invalid content");
         // FIXME: We should add laser channels to the parameters we optimize!
 
-        (0..u64::MAX).into_par_iter().find_map_any(|seed| {
+        // The rejected attempt of best quality, and why it was rejected.
+        let best_rejection = std::sync::Mutex::new(None::<(Quality, String)>);
+        let reject = |seed: u64, quality: Quality, reason: String| {
+            eprintln!("...testing seed {seed} => {reason}");
+            let mut best = best_rejection.lock().unwrap_or_else(|e| e.into_inner());
+            if best.as_ref().is_none_or(|(best, _)| quality > *best) {
+                *best = Some((quality, format!("seed {seed}, {reason}")));
+            }
+            None
+        };
+        let found = (0..options.max_seeds).into_par_iter().find_map_any(|seed| {
             let seed = seed.wrapping_add(options.seed);
             let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
 
@@ -190,16 +274,19 @@ impl Constraints {
                 options: options.clone(),
             };
 
-            let optimized = Executor::new(cost, solver)
+            let optimized = match Executor::new(cost, solver)
                 .configure(|state| state.max_iters(options.max_iters).target_cost(1e-6))
                 .run()
-                .expect("Error in the execution of register optimizer");
+            {
+                Ok(optimized) => optimized,
+                Err(err) => return Some(Err(Error::Layout(err))),
+            };
             let quality = 1. - optimized.state.best_cost.atan() / std::f64::consts::FRAC_PI_2;
-            let quality = Quality::new(quality);
+            // A NaN cost means that the optimizer got lost.
+            let quality = Quality::new(quality).unwrap_or(Quality::WORST);
             let coordinates = match optimized.state.best_param {
                 None => return None,
                 Some(v) => {
-                    assert!(v.len() % 2 == 0);
                     let mut iter = v.into_iter();
                     let mut coordinates = Vec::with_capacity(self.num_nodes);
                     while let Some((x, y)) = iter.next_tuple() {
@@ -213,15 +300,157 @@ impl Constraints {
                 coordinates: coordinates.into(),
             };
 
-            if quality >= options.min_quality {
-                Some((register, quality, seed))
-            } else {
-                eprintln!("...testing seed {seed} => insufficient quality {}", quality);
-                None
+            if quality < options.min_quality {
+                return reject(seed, quality, format!("insufficient quality {}", quality));
             }
+            let report = match LayoutReport::new(self, &register, device) {
+                Ok(report) => report,
+                Err(err) => return Some(Err(Error::Report(Box::new(err)))),
+            };
+            match report.rejection(&options.acceptance) {
+                Some(reason) => reject(seed, quality, reason),
+                None => Some(Ok(Layout {
+                    register,
+                    quality,
+                    seed,
+                    report,
+                })),
+            }
+        });
+        found.unwrap_or_else(|| {
+            let reason = best_rejection
+                .into_inner()
+                .unwrap_or_else(|e| e.into_inner())
+                .map_or_else(|| "no attempt".to_string(), |(_, reason)| reason);
+            Err(Error::NoSolution {
+                seeds: options.max_seeds,
+                reason,
+            })
         })
     }
 
+    /// The value of the QUBO objective for an assignment of the variables.
+    ///
+    /// `bitstring` is a string of `0` and `1`, in the order of the nodes, as produced by
+    /// the emulators.
+    pub fn energy(&self, bitstring: &str) -> Result<f64, Error> {
+        let bits = self.bits(bitstring)?;
+        let mut energy = 0.;
+        for i in (0..self.num_nodes).filter(|i| bits[*i]) {
+            for j in (0..self.num_nodes).filter(|j| bits[*j]) {
+                energy += self.at(i, j)?;
+            }
+        }
+        Ok(energy)
+    }
+
+    /// The value of the QUBO for an assignment of the variables, counting each pair of
+    /// variables once: `Σ_i Q_ii x_i + Σ_{i<j} Q_ij x_i x_j`.
+    ///
+    /// This is the convention of the Rydberg Hamiltonian, `Σ_{i<j} U_ij n_i n_j - δ Σ_i n_i`,
+    /// in which the interactions `U_ij` stand for the weights `Q_ij`, so the two energies may
+    /// be compared. [`Self::energy`] counts the pairs twice, as `x^T Q x`.
+    pub fn pair_energy(&self, bitstring: &str) -> Result<f64, Error> {
+        let bits = self.bits(bitstring)?;
+        let mut energy = 0.;
+        for i in (0..self.num_nodes).filter(|i| bits[*i]) {
+            for j in (i..self.num_nodes).filter(|j| bits[*j]) {
+                energy += self.at(i, j)?;
+            }
+        }
+        Ok(energy)
+    }
+
+    /// Parse an assignment of the variables.
+    fn bits(&self, bitstring: &str) -> Result<Vec<bool>, Error> {
+        let bits = bitstring
+            .chars()
+            .map(|c| match c {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(Error::InvalidBitstring(bitstring.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if bits.len() != self.num_nodes {
+            return Err(Error::InvalidBitstring(bitstring.to_string()));
+        }
+        Ok(bits)
+    }
+
+    /// The assignments minimizing the QUBO objective, as bitstrings.
+    ///
+    /// This is a brute-force search, so it is only available for small problems.
+    pub fn ground_states(&self) -> Result<Vec<String>, Error> {
+        let mut best = f64::INFINITY;
+        let mut ground_states = vec![];
+        for (bitstring, energy) in self.assignments()? {
+            let energy = energy?;
+            if energy < best - ENERGY_TOLERANCE {
+                best = energy;
+                ground_states.clear();
+            }
+            if energy <= best + ENERGY_TOLERANCE {
+                ground_states.push(bitstring);
+            }
+        }
+        Ok(ground_states)
+    }
+
+    /// The difference between the two lowest distinct values of the QUBO objective, or
+    /// `None` if all assignments have the same value.
+    ///
+    /// This is a brute-force search, so it is only available for small problems.
+    pub fn spectral_gap(&self) -> Result<Option<f64>, Error> {
+        let (mut lowest, mut second) = (f64::INFINITY, f64::INFINITY);
+        for (_, energy) in self.assignments()? {
+            let energy = energy?;
+            if energy < lowest - ENERGY_TOLERANCE {
+                second = lowest;
+                lowest = energy;
+            } else if energy > lowest + ENERGY_TOLERANCE && energy < second {
+                second = energy;
+            }
+        }
+        if second.is_finite() {
+            Ok(Some(second - lowest))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Enumerate all assignments, with their value.
+    fn assignments(
+        &self,
+    ) -> Result<impl Iterator<Item = (String, Result<f64, Error>)> + '_, Error> {
+        if self.num_nodes > MAX_NODES_BRUTE_FORCE {
+            return Err(Error::TooLargeForBruteForce(self.num_nodes));
+        }
+        Ok((0u64..(1 << self.num_nodes)).map(|assignment| {
+            let bitstring = (0..self.num_nodes)
+                .map(|i| if assignment & (1 << i) != 0 { '1' } else { '0' })
+                .collect::<String>();
+            let energy = self.energy(&bitstring);
+            (bitstring, energy)
+        }))
+    }
+
+    /// The typical magnitude of the (negative) diagonal weights, i.e. the detuning
+    /// needed to encode them, or `None` if there are no such weights.
+    pub fn diagonal_scale(&self) -> Option<f64> {
+        let diagonal = (0..self.num_nodes)
+            .map(|i| self.data[i * self.num_nodes + i])
+            .filter(|w| *w < 0.)
+            .collect_vec();
+        if diagonal.is_empty() {
+            return None;
+        }
+        Some(-diagonal.iter().sum::<f64>() / diagonal.len() as f64)
+    }
+
+    pub fn names(&self) -> &[Arc<str>] {
+        &self.names
+    }
+
     pub fn omega(&self) -> f64 {
         self.data
             .iter()
@@ -232,6 +461,100 @@ impl Constraints {
     }
 }
 
+impl Constraints {
+    /// Pick the largest scale factor for which the weights fit the limits of `device`.
+    ///
+    /// The largest factor makes for the strongest interactions, hence the widest spectral gap
+    /// and the shortest adiabatic sweep. The factor is bounded by:
+    /// - the strongest interaction, between atoms at the minimum distance (C6 / r_min^6);
+    /// - the maximal amplitude of the global channel, used for the median positive weight;
+    /// - the maximal detuning of the global channel, used for the diagonal.
+    ///
+    /// If the weakest weight then requires atoms further apart than the device allows, this
+    /// is reported in the reasoning, as no factor can represent all weights.
+    pub fn normalization(&self, device: &Device) -> Normalization {
+        let mut reasoning = vec![];
+        let c6 = device.interaction_coeff().value_rad_per_us_times_um_6();
+        let couplings = (0..self.num_nodes)
+            .flat_map(|y| (0..y).map(move |x| (x, y)))
+            .map(|(x, y)| self.data[self.num_nodes * y + x])
+            .filter(|w| *w > 0.)
+            .collect_vec();
+
+        let mut bounds = vec![];
+        if let Some(max) = couplings.iter().cloned().reduce(f64::max) {
+            let max_interaction = c6 / device.min_atom_distance().powi(6);
+            bounds.push((
+                INTERACTION_HEADROOM * max_interaction / max,
+                format!(
+                    "strongest weight {max} at {:.0}% of the interaction at the minimum distance of {} µm ({max_interaction:.3} rad/µs)",
+                    INTERACTION_HEADROOM * 100.,
+                    device.min_atom_distance()
+                ),
+            ));
+        }
+        if let Some(channel) = device.global_rydberg_channel() {
+            if let (Some(max_amp), false) = (channel.max_amp, couplings.is_empty()) {
+                let omega = self.omega();
+                bounds.push((
+                    max_amp / omega,
+                    format!(
+                        "median positive weight {omega} at the maximum amplitude of channel {} ({max_amp:.3} rad/µs)",
+                        channel.id.0
+                    ),
+                ));
+            }
+            if let (Some(max), Some(scale)) = (channel.max_abs_detuning, self.diagonal_scale()) {
+                bounds.push((
+                    max / scale,
+                    format!(
+                        "mean negative diagonal {scale} at the maximum detuning of channel {} ({max:.3} rad/µs)",
+                        channel.id.0
+                    ),
+                ));
+            }
+        }
+        let Some((factor, why)) = bounds
+            .into_iter()
+            .filter(|(factor, _)| factor.is_finite() && *factor > 0.)
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+        else {
+            reasoning.push("factor 1: no weight is limited by the device".to_string());
+            return Normalization {
+                factor: 1.,
+                reasoning,
+            };
+        };
+        reasoning.push(format!("factor {factor:.4} rad/µs per unit: {why}"));
+
+        // The weakest weight must remain stronger than the interaction between the two
+        // furthest atoms of the device.
+        let max_radius = device.max_sq_distance_to_center().sqrt();
+        if let (Some(min), true) = (
+            couplings.iter().cloned().reduce(f64::min),
+            max_radius.is_finite(),
+        ) {
+            let min_interaction = c6 / (2. * max_radius).powi(6);
+            if min * factor < min_interaction {
+                reasoning.push(format!(
+                    "warning: weakest weight {min} scales to {:.3e} rad/µs, below the interaction across the device ({min_interaction:.3e} rad/µs)",
+                    min * factor
+                ));
+            }
+        }
+        Normalization { factor, reasoning }
+    }
+
+    /// The same constraints, with all weights multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            data: self.data.iter().map(|w| w * factor).collect(),
+            num_nodes: self.num_nodes,
+            names: self.names.clone(),
+        }
+    }
+}
+
 impl Constraints {
     pub fn at(&self, x: usize, y: usize) -> Result<f64, Error> {
         let index = self.index(x, y)?;
@@ -276,10 +599,11 @@ impl Display for Constraints {
                 " [".to_string()
             };
             for x in 0..self.num_nodes {
+                let value = self.at(x, y).map_err(|_| std::fmt::Error)?;
                 if x == 0 {
-                    line.push_str(&format!("{}", self.at(x, y).unwrap()));
+                    line.push_str(&format!("{}", value));
                 } else {
-                    line.push_str(&format!(", {}", self.at(x, y).unwrap()));
+                    line.push_str(&format!(", {}", value));
                 }
             }
             buf.push_str(&format!(
@@ -303,19 +627,14 @@ impl Cost<'_> {
         first: Coordinates<Micrometers>,
         second: Coordinates<Micrometers>,
     ) -> units::Value<Mul<Rad, Inv<Microseconds>>> {
-        units::Value::new(
-            self.device
-                .interaction_coeff()
-                .value_rad_per_us_times_um_6()
-                / first.sqdist(&second).cube().into_inner(),
-        )
+        units::Value::new(self.device.interaction(first, second))
     }
     fn expected_interaction(
         &self,
         x: usize,
         y: usize,
-    ) -> units::Value<Mul<Rad, Inv<Microseconds>>> {
-        units::Value::new(self.constraints.at(x, y).unwrap())
+    ) -> Result<units::Value<Mul<Rad, Inv<Microseconds>>>, Error> {
+        Ok(units::Value::new(self.constraints.at(x, y)?))
     }
 }
 
@@ -336,7 +655,7 @@ impl CostFunction for Cost<'_> {
                 for j in i + 1..self.constraints.num_nodes {
                     let second = Coordinates::<Micrometers>::new(param[2 * j], param[2 * j + 1]);
                     let actual_interaction = self.actual_interaction(first, second);
-                    let expected_interaction = self.expected_interaction(i, j);
+                    let expected_interaction = self.expected_interaction(i, j)?;
                     let diff = (actual_interaction - expected_interaction).sq();
                     if i == j {
                         total += diff
@@ -354,8 +673,7 @@ impl CostFunction for Cost<'_> {
             .iter()
             .tuples()
             .map(|(x, y)| x * x + y * y)
-            .reduce(f64::max)
-            .unwrap();
+            .fold(0., f64::max);
         let overflow_risk = max_sq_distance_to_center / self.device.max_sq_distance_to_center();
         let overflow_cost = if overflow_risk < self.options.overflow_protection_threshold {
             0.
@@ -425,3 +743,94 @@ fn test_cost_function_vs_python() {
         diff
     );
 }
+
+#[test]
+fn test_normalization() {
+    let constraints = Constraints::from_const(
+        [[-1., 2., 2.], [2., -1., 1.], [2., 1., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let device = Device::analog();
+    let normalization = constraints.normalization(&device);
+    assert!(normalization.factor > 0.);
+    assert!(!normalization.reasoning.is_empty());
+
+    // The scaled weights fit the device.
+    let scaled = constraints.scaled(normalization.factor);
+    let max_interaction = device.interaction_coeff().value_rad_per_us_times_um_6()
+        / device.min_atom_distance().powi(6);
+    assert!(scaled.at(0, 1).unwrap() <= max_interaction);
+    let channel = device.global_rydberg_channel().unwrap();
+    let max_amp = channel.max_amp.unwrap();
+    let max_abs_detuning = channel.max_abs_detuning.unwrap();
+    assert!(scaled.diagonal_scale().unwrap() <= max_abs_detuning);
+    // Here, the amplitude is the limit.
+    assert!((scaled.omega() - max_amp).abs() < 1e-9);
+    assert!(normalization.reasoning[0].contains("maximum amplitude"));
+    assert!(!normalization
+        .reasoning
+        .iter()
+        .any(|line| line.starts_with("warning")));
+
+    // Optima are preserved, energies convert back to problem units.
+    assert_eq!(
+        scaled.ground_states().unwrap(),
+        constraints.ground_states().unwrap()
+    );
+    let energy = scaled.energy("011").unwrap();
+    assert!(
+        (normalization.to_problem_units(energy) - constraints.energy("011").unwrap()).abs() < 1e-9
+    );
+
+    // A strong diagonal makes the detuning the limit.
+    let constraints = Constraints::from_const(
+        [[-100., 2., 2.], [2., -100., 1.], [2., 1., -100.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let normalization = constraints.normalization(&device);
+    let scaled = constraints.scaled(normalization.factor);
+    assert!((scaled.diagonal_scale().unwrap() - max_abs_detuning).abs() < 1e-9);
+    assert!(scaled.omega() < max_amp);
+    assert!(normalization.reasoning[0].contains("maximum detuning"));
+
+    // Weights too weak to lay out, once scaled, are reported.
+    let constraints = Constraints::from_const(
+        [[-1., 1000., 1e-5], [1000., -1., 1000.], [1e-5, 1000., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let normalization = constraints.normalization(&device);
+    assert!(
+        normalization
+            .reasoning
+            .iter()
+            .any(|line| line.starts_with("warning: weakest weight 0.00001")),
+        "{:?}",
+        normalization.reasoning
+    );
+}
+
+#[test]
+fn test_layout_gives_up() {
+    let constraints = Constraints::from_const(
+        [[-1., 2., 2.], [2., -1., 1.], [2., 1., -1.]],
+        vec!["a".into(), "b".into(), "c".into()],
+    );
+    let options = Options {
+        min_quality: Quality::WORST,
+        max_iters: 100,
+        max_seeds: 4,
+        acceptance: Acceptance {
+            min_spacing_um: Some(1_000.),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    match constraints.layout(&Device::analog(), &options) {
+        Err(Error::NoSolution { seeds, reason }) => {
+            assert_eq!(seeds, 4);
+            assert!(reason.contains("min spacing"), "{reason}");
+        }
+        Err(err) => panic!("unexpected error {err}"),
+        Ok(_) => panic!("min spacing of 1000 µm should not be met"),
+    }
+}
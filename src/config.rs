@@ -0,0 +1,70 @@
+//! User configuration, e.g. remote runners and their credentials.
+//!
+//! Read from `$QLAFOUTEA_CONFIG` if set, otherwise from `qlafoutea/config.yaml` in
+//! `$XDG_CONFIG_HOME` (by default `~/.config`). A missing file is an empty configuration.
+
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::Deserialize;
+
+use crate::runtime::runner::{mps::MpsConfig, remote::RemoteConfig};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot read configuration file {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("invalid configuration file {0}")]
+    Parse(PathBuf, #[source] serde_yaml::Error),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Runners which submit jobs to an HTTP job API, by name.
+    #[serde(default)]
+    pub remotes: BTreeMap<String, RemoteConfig>,
+
+    /// Where to keep track of jobs, by default `qlafoutea/jobs` in `$XDG_DATA_HOME`
+    /// (by default `~/.local/share`).
+    #[serde(default)]
+    pub jobs_dir: Option<PathBuf>,
+
+    /// The Python interpreter running Pulser, unless `--python` is given or a virtual
+    /// environment is active.
+    #[serde(default)]
+    pub python: Option<PathBuf>,
+
+    /// The settings of the `mps` emulator.
+    #[serde(default)]
+    pub mps: MpsConfig,
+}
+
+impl Config {
+    /// Where the configuration is read from, if we can tell.
+    pub fn path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os("QLAFOUTEA_CONFIG") {
+            return Some(path.into());
+        }
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("qlafoutea").join("config.yaml"))
+    }
+
+    pub fn load() -> Result<Self, Error> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(Error::Io(path, err)),
+        };
+        if source.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        serde_yaml::from_str(&source).map_err(|err| Error::Parse(path, err))
+    }
+}
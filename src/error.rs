@@ -0,0 +1,124 @@
+//! The errors of the library, as a single type.
+//!
+//! Each stage of the compiler has its own error type. This module gathers them, so that
+//! applications embedding qlafoutea can handle any failure, from reading a source file
+//! to running a sequence, without the process aborting.
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backend::{
+        format,
+        pulser::{schema, variable},
+        qaa, qaoa, qubo,
+    },
+    frontend::{self, diagnostics::Diagnostic},
+};
+
+/// A position in a source file, 1-based.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot read {}", .path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("cannot parse {}: {message}", at(.path, .location))]
+    Parse {
+        path: PathBuf,
+        location: Option<Location>,
+        message: String,
+    },
+
+    #[error("invalid problem in {}:\n{}", .path.display(), render(.path, .diagnostics))]
+    Diagnostics {
+        path: PathBuf,
+        diagnostics: Vec<Diagnostic>,
+    },
+
+    #[error("cannot compile the problem to QUBO")]
+    Frontend(#[from] frontend::Error),
+
+    #[error("cannot lay out the QUBO")]
+    Layout(#[from] qubo::Error),
+
+    #[error("invalid sequence")]
+    Sequence(#[from] schema::Error),
+
+    #[error("cannot assign the variables of the sequence")]
+    Variables(#[from] variable::Error),
+
+    #[error("invalid compiled file")]
+    Format(#[from] format::Error),
+
+    #[error("cannot compile to QAA")]
+    Qaa(#[from] qaa::Error),
+
+    #[error("cannot compile to QAOA")]
+    Qaoa(#[from] qaoa::Error),
+
+    #[error("failed to run the sequence")]
+    Runtime(#[source] anyhow::Error),
+}
+
+impl Error {
+    /// A json syntax or structure error in `path`.
+    pub fn json(path: &Path, err: serde_json::Error) -> Self {
+        // serde_json reports line 0 for errors which do not come from the input itself.
+        let location = (err.line() > 0).then(|| Location {
+            line: err.line(),
+            column: err.column(),
+        });
+        Error::Parse {
+            path: path.to_path_buf(),
+            location,
+            message: err.to_string(),
+        }
+    }
+
+    /// The position in the source file, if the error can be traced to one.
+    pub fn location(&self) -> Option<Location> {
+        match *self {
+            Error::Parse { location, .. } => location,
+            Error::Diagnostics {
+                ref diagnostics, ..
+            } => diagnostics
+                .iter()
+                .find_map(|diagnostic| diagnostic.span.as_ref())
+                .map(|span| span.location),
+            _ => None,
+        }
+    }
+}
+
+fn at(path: &Path, location: &Option<Location>) -> String {
+    match location {
+        Some(location) => format!("{}:{location}", path.display()),
+        None => path.display().to_string(),
+    }
+}
+
+fn render(path: &Path, diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.render(path))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
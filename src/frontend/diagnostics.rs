@@ -0,0 +1,710 @@
+//! Checking problem files, with errors and warnings pointing into the source.
+//!
+//! serde reports structural errors without their position whenever the input goes through
+//! an internally tagged or flattened type, which is the case of every problem type. So we
+//! first check the shape of the document ourselves, on a [`Value`], and only deserialize
+//! it once we know it is well-formed. Positions are recovered from the text of the source,
+//! which is good enough for the block and flow styles users actually write.
+
+use std::{collections::HashMap, fmt::Write, path::Path};
+
+use itertools::Itertools;
+use serde_yaml::{Mapping, Value};
+
+use super::{registry, Input};
+use crate::error::Location;
+
+const EXPECTED_CLAUSE: &str = "exactly 3 literals, e.g. `or: [x1, NOT x2, x3]`";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A position in the source, with the line it belongs to.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub location: Location,
+
+    /// The number of characters covered.
+    pub len: usize,
+
+    /// The text of the line.
+    pub line: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+
+    /// The shape we expected, if the source doesn't have it.
+    pub expected: Option<String>,
+
+    /// How to fix the problem, e.g. the name of a variable that looks similar.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    fn error(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Error,
+            message: message.into(),
+            span,
+            expected: None,
+            help: None,
+        }
+    }
+
+    fn warning(message: impl Into<String>, span: Option<Span>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(message, span)
+        }
+    }
+
+    fn expected(self, expected: impl Into<String>) -> Self {
+        Self {
+            expected: Some(expected.into()),
+            ..self
+        }
+    }
+
+    fn help(self, help: Option<String>) -> Self {
+        Self { help, ..self }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Render as text, in the style of rustc, e.g.
+    ///
+    /// ```text
+    /// error: clause 2 has 2 literals
+    ///  --> problem.yaml:4:5
+    ///   |
+    /// 4 |   - or: [x1, x2]
+    ///   |     ^^
+    ///   = expected: exactly 3 literals, e.g. `or: [x1, NOT x2, x3]`
+    /// ```
+    pub fn render(&self, path: &Path) -> String {
+        let mut out = String::new();
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        let _ = writeln!(out, "{severity}: {}", self.message);
+        let gutter = match self.span {
+            None => {
+                let _ = writeln!(out, " --> {}", path.display());
+                1
+            }
+            Some(ref span) => {
+                let number = span.location.line.to_string();
+                let gutter = number.len() + 1;
+                let _ = writeln!(
+                    out,
+                    "{:>width$}--> {}:{}",
+                    "",
+                    path.display(),
+                    span.location,
+                    width = number.len()
+                );
+                let _ = writeln!(out, "{:>gutter$}|", "");
+                let _ = writeln!(out, "{number} | {}", span.line);
+                let _ = writeln!(
+                    out,
+                    "{:>gutter$}| {:>pad$}{}",
+                    "",
+                    "",
+                    "^".repeat(span.len.max(1)),
+                    pad = span.location.column - 1
+                );
+                gutter
+            }
+        };
+        if let Some(ref expected) = self.expected {
+            let _ = writeln!(out, "{:>gutter$}= expected: {expected}", "");
+        }
+        if let Some(ref help) = self.help {
+            let _ = writeln!(out, "{:>gutter$}= help: {help}", "");
+        }
+        out
+    }
+}
+
+/// Check a problem file.
+///
+/// Returns the problem, unless there is at least one error, along with all the
+/// diagnostics, errors and warnings alike.
+pub fn check(source: &str) -> (Option<Input>, Vec<Diagnostic>) {
+    let locator = Locator::new(source);
+    let mut diagnostics = vec![];
+    let value = match serde_yaml::from_str::<Value>(source) {
+        Ok(value) => value,
+        Err(err) => {
+            let span = err
+                .location()
+                .and_then(|location| locator.at(location.line(), location.column(), 1));
+            diagnostics.push(Diagnostic::error(syntax_message(&err), span));
+            return (None, diagnostics);
+        }
+    };
+    let Some(mapping) = value.as_mapping() else {
+        diagnostics.push(
+            Diagnostic::error("a problem must be a mapping", locator.at(1, 1, 1))
+                .expected("`type: qubo` or `type: max3sat`, followed by the problem"),
+        );
+        return (None, diagnostics);
+    };
+    let kinds = registry::kinds();
+    let expected_kinds = kinds.iter().map(|kind| format!("`{kind}`")).join(" or ");
+    match mapping.get("type").map(|kind| kind.as_str()) {
+        None => diagnostics.push(
+            Diagnostic::error("missing `type`", None)
+                .expected(format!("`type:` followed by {expected_kinds}")),
+        ),
+        Some(Some("qubo")) => check_qubo(mapping, &locator, &mut diagnostics),
+        Some(Some("max3sat")) => check_max3sat(mapping, &locator, &mut diagnostics),
+        // Problem types registered by applications are checked when parsed.
+        Some(Some(kind)) if kinds.iter().any(|known| known == kind) => {}
+        Some(kind) => {
+            let kind = kind.unwrap_or_default();
+            diagnostics.push(
+                Diagnostic::error(
+                    format!("unknown problem type `{kind}`"),
+                    locator.key("type", 0),
+                )
+                .expected(expected_kinds)
+                .help(did_you_mean(kind, kinds.iter().map(String::as_str))),
+            )
+        }
+    }
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        return (None, diagnostics);
+    }
+    match serde_yaml::from_value::<Input>(value) {
+        Ok(input) => (Some(input), diagnostics),
+        Err(err) => {
+            // Anything we did not anticipate above, e.g. a value of the wrong type.
+            diagnostics.push(Diagnostic::error(err.to_string(), None));
+            (None, diagnostics)
+        }
+    }
+}
+
+fn syntax_message(err: &serde_yaml::Error) -> String {
+    // The position is shown separately.
+    let message = err.to_string();
+    match message.find(" at line ") {
+        Some(index) => message[..index].to_string(),
+        None => message,
+    }
+}
+
+fn check_max3sat(mapping: &Mapping, locator: &Locator, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(clauses) = mapping.get("and").and_then(Value::as_sequence) else {
+        diagnostics.push(
+            Diagnostic::error("missing list of clauses `and`", locator.key("and", 0))
+                .expected("`and: [{or: [x1, NOT x2, x3]}, ...]`"),
+        );
+        return;
+    };
+
+    // Each clause seen so far, as its sorted literals, with its index.
+    let mut seen = HashMap::<Vec<&str>, usize>::new();
+    let mut variables = Vec::<&str>::new();
+    // Locate each clause by its own item, as a clause without `or` would shift the
+    // occurrences of `or:` in the text.
+    let items = locator.items("and");
+    for (i, clause) in clauses.iter().enumerate() {
+        let item = items.get(i);
+        let span = item.and_then(|item| {
+            let end = items.get(i + 1).map(|next| next.location);
+            locator
+                .key_within("or", item.location, end)
+                .or_else(|| Some(item.clone()))
+        });
+        let Some(literals) = clause.get("or").and_then(Value::as_sequence) else {
+            diagnostics.push(
+                Diagnostic::error(format!("clause {} has no `or`", i + 1), span)
+                    .expected(EXPECTED_CLAUSE),
+            );
+            continue;
+        };
+        let Some(literals) = literals
+            .iter()
+            .map(Value::as_str)
+            .collect::<Option<Vec<_>>>()
+        else {
+            diagnostics.push(
+                Diagnostic::error(
+                    format!("clause {} has a literal which is not a name", i + 1),
+                    span,
+                )
+                .expected(EXPECTED_CLAUSE),
+            );
+            continue;
+        };
+        if literals.len() != 3 {
+            diagnostics.push(
+                Diagnostic::error(
+                    format!("clause {} has {} literals", i + 1, literals.len()),
+                    span.clone(),
+                )
+                .expected(EXPECTED_CLAUSE),
+            );
+        }
+        variables.extend(
+            literals
+                .iter()
+                .map(|literal| literal.strip_prefix("NOT ").unwrap_or(literal).trim()),
+        );
+        let key = literals
+            .iter()
+            .map(|literal| literal.trim())
+            .sorted()
+            .collect_vec();
+        if let Some(first) = seen.get(&key) {
+            diagnostics.push(
+                Diagnostic::warning(format!("clause {} is a duplicate", i + 1), span).help(Some(
+                    format!("clause {} already has the same literals", first + 1),
+                )),
+            );
+        } else {
+            seen.insert(key, i);
+        }
+    }
+
+    if let Some(restrict) = mapping.get("restrict") {
+        let Some(restrict) = restrict.as_mapping() else {
+            diagnostics.push(
+                Diagnostic::error("`restrict` must be a mapping", locator.key("restrict", 0))
+                    .expected("`restrict: {x1: true, x2: false}`"),
+            );
+            return;
+        };
+        for (name, value) in restrict {
+            let name = match name {
+                Value::String(name) => name.clone(),
+                other => serde_yaml::to_string(other)
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string(),
+            };
+            let span = locator.key_after("restrict", &name);
+            if !variables.contains(&name.as_str()) {
+                diagnostics.push(
+                    Diagnostic::error(
+                        format!("unknown variable `{name}` in `restrict`"),
+                        span.clone(),
+                    )
+                    .help(
+                        did_you_mean(&name, variables.iter().cloned()).or_else(|| {
+                            Some("only variables which appear in a clause may be restricted".into())
+                        }),
+                    ),
+                );
+            }
+            if !value.is_bool() {
+                diagnostics.push(
+                    Diagnostic::error(format!("invalid restriction of `{name}`"), span)
+                        .expected("`true` or `false`"),
+                );
+            }
+        }
+    }
+}
+
+fn check_qubo(mapping: &Mapping, locator: &Locator, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(num_nodes) = mapping.get("num_nodes").and_then(Value::as_u64) else {
+        diagnostics.push(
+            Diagnostic::error(
+                "missing number of variables `num_nodes`",
+                locator.key("num_nodes", 0),
+            )
+            .expected("a positive integer, e.g. `num_nodes: 3`"),
+        );
+        return;
+    };
+    let num_nodes = num_nodes as usize;
+    let expected_data = format!(
+        "{num_nodes} × {num_nodes} = {} weights, row by row",
+        num_nodes * num_nodes
+    );
+    let data = match mapping.get("data").and_then(Value::as_sequence) {
+        None => {
+            diagnostics.push(
+                Diagnostic::error("missing weights `data`", locator.key("data", 0))
+                    .expected(expected_data),
+            );
+            return;
+        }
+        Some(data) => data,
+    };
+    let Some(data) = data.iter().map(Value::as_f64).collect::<Option<Vec<_>>>() else {
+        diagnostics.push(
+            Diagnostic::error("weights must be numbers", locator.key("data", 0))
+                .expected(expected_data),
+        );
+        return;
+    };
+    if data.len() != num_nodes * num_nodes {
+        diagnostics.push(
+            Diagnostic::error(format!("{} weights", data.len()), locator.key("data", 0))
+                .expected(expected_data),
+        );
+        return;
+    }
+
+    let names = mapping
+        .get("names")
+        .and_then(Value::as_sequence)
+        .and_then(|names| names.iter().map(Value::as_str).collect::<Option<Vec<_>>>());
+    let Some(names) = names else {
+        diagnostics.push(
+            Diagnostic::error("missing variable names `names`", locator.key("names", 0)).expected(
+                format!(
+                    "{num_nodes} names, e.g. `names: [{}]`",
+                    (0..num_nodes).map(|i| format!("x{i}")).join(", ")
+                ),
+            ),
+        );
+        return;
+    };
+    if names.len() != num_nodes {
+        diagnostics.push(
+            Diagnostic::error(format!("{} names", names.len()), locator.key("names", 0))
+                .expected(format!("{num_nodes} names, one per variable")),
+        );
+        return;
+    }
+    for (name, count) in names.iter().counts() {
+        if count > 1 {
+            diagnostics.push(Diagnostic::error(
+                format!("variable `{name}` is declared {count} times"),
+                locator.word_after("names", name, 1),
+            ));
+        }
+    }
+    for (i, name) in names.iter().enumerate() {
+        let unused =
+            (0..num_nodes).all(|j| data[i * num_nodes + j] == 0. && data[j * num_nodes + i] == 0.);
+        if unused {
+            diagnostics.push(
+                Diagnostic::warning(
+                    format!("variable `{name}` is unused"),
+                    locator.word_after("names", name, 0),
+                )
+                .help(Some(
+                    "all its weights are 0, so it may take any value".into(),
+                )),
+            );
+        }
+    }
+}
+
+/// The candidate closest to `name`, if it is close enough to be a likely typo.
+fn did_you_mean<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<String> {
+    let threshold = name.chars().count().div_ceil(3);
+    candidates
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!("did you mean `{candidate}`?"))
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect_vec();
+    let mut row = (0..=b.len()).collect_vec();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Finds keys and words in the text of the source.
+struct Locator<'a> {
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            lines: source.lines().collect(),
+        }
+    }
+
+    /// A span at a 1-based position.
+    ///
+    /// Positions past the end, as reported for unterminated values, point to the end of
+    /// the last line.
+    fn at(&self, line: usize, column: usize, len: usize) -> Option<Span> {
+        let last = self.lines.len();
+        if line > last && last > 0 {
+            let column = self.lines[last - 1].chars().count() + 1;
+            return self.at(last, column, len);
+        }
+        let text = self.lines.get(line.checked_sub(1)?)?;
+        Some(Span {
+            location: Location { line, column },
+            len,
+            line: text.to_string(),
+        })
+    }
+
+    /// All the occurrences of `word` as a token, starting from line index `from`, in order.
+    fn occurrences<'b>(
+        &'b self,
+        word: &'b str,
+        from: usize,
+        is_key: bool,
+    ) -> impl Iterator<Item = Span> + 'b {
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(from)
+            .flat_map(move |(index, line)| {
+                // Ignore comments.
+                let code = match line.find(" #") {
+                    Some(end) => &line[..end],
+                    None if line.trim_start().starts_with('#') => "",
+                    None => line,
+                };
+                code.match_indices(word)
+                    .filter(move |(start, _)| {
+                        let before = code[..*start].chars().next_back();
+                        let after = code[start + word.len()..].trim_start().chars().next();
+                        let bounded = matches!(before, None | Some(' ' | '-' | '{' | '[' | ','));
+                        let ends = if is_key {
+                            after == Some(':')
+                        } else {
+                            matches!(after, None | Some(',' | ']' | '}'))
+                        };
+                        bounded && ends
+                    })
+                    .map(move |(start, _)| Span {
+                        location: Location {
+                            line: index + 1,
+                            column: line[..start].chars().count() + 1,
+                        },
+                        len: word.chars().count(),
+                        line: line.to_string(),
+                    })
+                    .collect_vec()
+            })
+    }
+
+    /// The `nth` occurrence of `key:`, 0-based.
+    fn key(&self, key: &str, nth: usize) -> Option<Span> {
+        self.occurrences(key, 0, true).nth(nth)
+    }
+
+    /// The first occurrence of `key:` at or after `start`, and before `end` if specified.
+    fn key_within(&self, key: &str, start: Location, end: Option<Location>) -> Option<Span> {
+        let position = |location: Location| (location.line, location.column);
+        self.occurrences(key, start.line - 1, true)
+            .skip_while(|span| position(span.location) < position(start))
+            .take_while(|span| end.is_none_or(|end| position(span.location) < position(end)))
+            .next()
+    }
+
+    /// The start of each item of the list `section:`, in order, in block or flow style.
+    fn items(&self, section: &str) -> Vec<Span> {
+        let Some(key) = self.key(section, 0) else {
+            return vec![];
+        };
+        let index = key.location.line - 1;
+        let line = self.lines[index];
+        let start = line
+            .char_indices()
+            .nth(key.location.column - 1 + key.len)
+            .map_or(line.len(), |(start, _)| start);
+        let rest = line[start..].trim_start_matches([' ', ':']);
+        if rest.starts_with('[') {
+            return self.flow_items(index, line.len() - rest.len() + 1);
+        }
+
+        // Block style: the lines starting with `-`, at the indentation of the first one.
+        let mut items = vec![];
+        let mut indent = None;
+        for (index, line) in self.lines.iter().enumerate().skip(index + 1) {
+            let content = line.trim_start();
+            if content.is_empty() || content.starts_with('#') {
+                continue;
+            }
+            let column = line.len() - content.len();
+            let is_item = content == "-" || content.starts_with("- ");
+            match indent {
+                None if is_item => indent = Some(column),
+                None => break,
+                Some(indent) if column < indent || (column == indent && !is_item) => break,
+                Some(indent) if column > indent => continue,
+                Some(_) => {}
+            }
+            items.extend(self.at(index + 1, line[..column].chars().count() + 1, 1));
+        }
+        items
+    }
+
+    /// The start of each item of a flow list, which opens before byte `column` of line `first`.
+    fn flow_items(&self, first: usize, column: usize) -> Vec<Span> {
+        let mut items = vec![];
+        // The nesting within the list.
+        let mut depth = 0usize;
+        let mut expecting = true;
+        for (index, line) in self.lines.iter().enumerate().skip(first) {
+            let from = if index == first { column } else { 0 };
+            for (start, c) in line.char_indices().filter(|(start, _)| *start >= from) {
+                if depth == 0 {
+                    match c {
+                        ']' => return items,
+                        ',' => {
+                            expecting = true;
+                            continue;
+                        }
+                        ' ' | '\t' => continue,
+                        _ if expecting => {
+                            expecting = false;
+                            items.extend(self.at(index + 1, line[..start].chars().count() + 1, 1));
+                        }
+                        _ => {}
+                    }
+                }
+                match c {
+                    '[' | '{' => depth += 1,
+                    ']' | '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+        }
+        items
+    }
+
+    /// The first occurrence of `key:` after `section:`.
+    fn key_after(&self, section: &str, key: &str) -> Option<Span> {
+        let section = self.key(section, 0)?.location.line - 1;
+        self.occurrences(key, section, true).next()
+    }
+
+    /// The `nth` occurrence of the list item `word` after `section:`, 0-based.
+    fn word_after(&self, section: &str, word: &str, nth: usize) -> Option<Span> {
+        let section = self.key(section, 0)?.location.line - 1;
+        self.occurrences(word, section, false).nth(nth)
+    }
+}
+
+#[test]
+fn test_max3sat_diagnostics() {
+    let source = "\
+type: max3sat
+and:
+  - or: [x1, x2, x3]
+  - or: [x1, x2]
+  - or:
+    - x3
+    - x2
+    - x1
+restrict:
+  x11: true
+";
+    let (input, diagnostics) = check(source);
+    assert!(input.is_none());
+    let messages = diagnostics.iter().map(|d| d.message.as_str()).collect_vec();
+    assert_eq!(
+        messages,
+        vec![
+            "clause 2 has 2 literals",
+            "clause 3 is a duplicate",
+            "unknown variable `x11` in `restrict`"
+        ]
+    );
+    let span = diagnostics[0].span.as_ref().unwrap();
+    assert_eq!(span.location, Location { line: 4, column: 5 });
+    assert_eq!(diagnostics[1].severity, Severity::Warning);
+    assert_eq!(diagnostics[2].help.as_deref(), Some("did you mean `x1`?"));
+
+    let rendered = diagnostics[0].render(Path::new("problem.yaml"));
+    assert_eq!(
+        rendered,
+        "\
+error: clause 2 has 2 literals
+ --> problem.yaml:4:5
+  |
+4 |   - or: [x1, x2]
+  |     ^^
+  = expected: exactly 3 literals, e.g. `or: [x1, NOT x2, x3]`
+"
+    );
+}
+
+#[test]
+fn test_qubo_diagnostics() {
+    let source = "type: qubo\nnum_nodes: 2\nnames: [a, b]\ndata: [-1, 0, 0, 0]\n";
+    let (input, diagnostics) = check(source);
+    assert!(input.is_some());
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "variable `b` is unused");
+    assert_eq!(
+        diagnostics[0].span.as_ref().unwrap().location,
+        Location {
+            line: 3,
+            column: 12
+        }
+    );
+
+    let (input, diagnostics) = check("type: qbuo\n");
+    assert!(input.is_none());
+    assert_eq!(diagnostics[0].help.as_deref(), Some("did you mean `qubo`?"));
+
+    let (_, diagnostics) = check("type: qubo\nnum_nodes: [\n");
+    assert_eq!(diagnostics[0].span.as_ref().unwrap().location.line, 2);
+}
+
+#[test]
+fn test_clause_spans() {
+    // The first clause has no `or:`, which must not shift the spans of the others.
+    let source = "\
+type: max3sat
+and:
+  - of: [a, b, c]
+  - or: [a, b, c]
+  - or: [a, b]
+";
+    let (_, diagnostics) = check(source);
+    let spans = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), d.span.as_ref().unwrap().location))
+        .collect_vec();
+    assert_eq!(
+        spans,
+        vec![
+            ("clause 1 has no `or`", Location { line: 3, column: 3 }),
+            ("clause 3 has 2 literals", Location { line: 5, column: 5 }),
+        ]
+    );
+
+    let source = "type: max3sat\nand: [{of: [a, b, c]}, {or: [a, b, c]},\n  {or: [a, b]}]\n";
+    let (_, diagnostics) = check(source);
+    let spans = diagnostics
+        .iter()
+        .map(|d| (d.message.as_str(), d.span.as_ref().unwrap().location))
+        .collect_vec();
+    assert_eq!(
+        spans,
+        vec![
+            ("clause 1 has no `or`", Location { line: 2, column: 7 }),
+            ("clause 3 has 2 literals", Location { line: 3, column: 4 }),
+        ]
+    );
+}
@@ -3,7 +3,19 @@ use std::{borrow::Cow, collections::HashMap, fmt::Display, ops::Not, sync::Arc};
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
-use crate::{backend::qubo::Constraints, runtime::run::Sample};
+use crate::{
+    backend::qubo::{self, Constraints},
+    runtime::run::Sample,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("variable {0} has no value")]
+    Unassigned(Variable),
+
+    #[error("invalid constraints")]
+    Qubo(#[from] qubo::Error),
+}
 
 #[derive(Deserialize, Serialize, Default)]
 pub struct Input {
@@ -27,8 +39,13 @@ pub struct Disjunction {
     pub and: Vec<Conjunction>,
 }
 impl Disjunction {
-    pub fn eval(&self, env: &Env) -> bool {
-        self.and.iter().all(|literal| literal.eval(env))
+    pub fn eval(&self, env: &Env) -> Result<bool, Error> {
+        for conjunction in &self.and {
+            if !conjunction.eval(env)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
     }
 }
 
@@ -44,8 +61,13 @@ impl Conjunction {
     pub fn variables(&self) -> impl Iterator<Item = &Variable> {
         self.or.iter().map(|literal| &literal.variable)
     }
-    pub fn eval(&self, env: &Env) -> bool {
-        self.or.iter().any(|literal| literal.eval(env))
+    pub fn eval(&self, env: &Env) -> Result<bool, Error> {
+        for literal in &self.or {
+            if literal.eval(env)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
     }
 }
 
@@ -61,11 +83,11 @@ pub struct Literal {
     pub positive: bool,
 }
 impl Literal {
-    pub fn eval(&self, env: &Env) -> bool {
+    pub fn eval(&self, env: &Env) -> Result<bool, Error> {
         if self.positive {
             self.variable.eval(env)
         } else {
-            self.variable.eval(env).not()
+            self.variable.eval(env).map(bool::not)
         }
     }
 }
@@ -127,8 +149,17 @@ impl Variable {
         }
     }
 
-    pub fn eval(&self, env: &Env) -> bool {
-        *env.0.get(self).unwrap()
+    pub fn eval(&self, env: &Env) -> Result<bool, Error> {
+        env.0
+            .get(self)
+            .cloned()
+            .ok_or_else(|| Error::Unassigned(self.clone()))
+    }
+}
+
+impl Display for Variable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
     }
 }
 
@@ -147,7 +178,7 @@ impl Input {
         self.variables().sorted().dedup()
     }
 
-    pub fn to_qubo(&self) -> Constraints {
+    pub fn to_qubo(&self) -> Result<Constraints, Error> {
         // Collect variables and assign to each an index.
         let variables: HashMap<&Variable, usize> = self
             .ordered_variables()
@@ -174,18 +205,16 @@ impl Input {
             let mut delta_conj_var = 2.0;
             eprintln!("conjunction {} => index {}", conj_offset, conj_index);
             for (literal_1_index, literal_1) in conjunction.or.iter().enumerate() {
-                let var_1_index = variables.get(&literal_1.variable).cloned().unwrap();
+                let var_1_index = variables[&literal_1.variable];
                 eprintln!("literal {} => index {}", literal_1.variable.0, var_1_index);
 
                 // Encode term `-Lj`.
                 let delta_var_1 = if literal_1.positive { -1.0 } else { 1.0 };
-                constraints
-                    .delta_at(var_1_index, var_1_index, delta_var_1)
-                    .unwrap();
+                constraints.delta_at(var_1_index, var_1_index, delta_var_1)?;
 
                 // Encode term `Lj.Lk` for k > j.
                 for literal_2 in conjunction.or.iter().skip(literal_1_index + 1) {
-                    let var_2_index = variables.get(&literal_2.variable).cloned().unwrap();
+                    let var_2_index = variables[&literal_2.variable];
                     let (delta_product_2, delta_var_1, delta_var_2) =
                         match (literal_1.positive, literal_2.positive) {
                             (true, true) => {
@@ -205,15 +234,9 @@ impl Input {
                                 (1.0, -1.0, -1.0)
                             }
                         };
-                    constraints
-                        .delta_at(var_1_index, var_1_index, delta_var_1)
-                        .unwrap();
-                    constraints
-                        .delta_at(var_2_index, var_2_index, delta_var_2)
-                        .unwrap();
-                    constraints
-                        .delta_at(var_1_index, var_2_index, delta_product_2)
-                        .unwrap();
+                    constraints.delta_at(var_1_index, var_1_index, delta_var_1)?;
+                    constraints.delta_at(var_2_index, var_2_index, delta_var_2)?;
+                    constraints.delta_at(var_1_index, var_2_index, delta_product_2)?;
                 }
 
                 // Encode term `-Ci.Lj`
@@ -223,20 +246,16 @@ impl Input {
                 } else {
                     (1.0, -1.0)
                 };
-                constraints
-                    .delta_at(var_1_index, conj_index, delta_prod_3)
-                    .unwrap();
+                constraints.delta_at(var_1_index, conj_index, delta_prod_3)?;
 
                 delta_conj_var += additional_delta_conj_var;
             }
 
             // Encode term `2.Ci`
             eprintln!("C[{}]", conj_offset);
-            constraints
-                .delta_at(conj_index, conj_index, delta_conj_var)
-                .unwrap();
+            constraints.delta_at(conj_index, conj_index, delta_conj_var)?;
         }
-        constraints
+        Ok(constraints)
     }
 }
 
@@ -266,7 +285,7 @@ fn test_to_qubo() {
         },
         ..Default::default()
     };
-    let constraints = input.to_qubo();
+    let constraints = input.to_qubo().unwrap();
     assert_eq!(constraints.num_nodes(), 7); // 3 SAT variables, 4 terms.
     let expected = Constraints::from_const(
         [
@@ -301,10 +320,53 @@ fn test_to_qubo() {
     }
 }
 
+impl super::Problem for Input {
+    fn kind(&self) -> &str {
+        "max3sat"
+    }
+
+    fn to_value(&self) -> Result<serde_json::Value, super::Error> {
+        serde_json::to_value(self).map_err(|source| super::Error::Invalid {
+            kind: "max3sat".to_string(),
+            source,
+        })
+    }
+
+    fn to_constraints(&self) -> Result<Constraints, super::Error> {
+        Ok(self.to_qubo()?)
+    }
+
+    fn handle_results(&self, samples: &[Sample]) -> Result<(), anyhow::Error> {
+        Input::handle_results(self, samples)
+    }
+
+    /// A sample is a solution iff it satisfies all clauses and restrictions.
+    ///
+    /// Only the bits of the original variables matter, the others encode the clauses.
+    fn validate_solution(&self, bitstring: &str) -> Result<bool, super::Error> {
+        let mut env = Env(HashMap::new());
+        for (c, var) in bitstring.chars().zip(self.ordered_variables()) {
+            let value = c == '1';
+            if self
+                .restrict
+                .get(var)
+                .is_some_and(|restriction| *restriction != value)
+            {
+                return Ok(false);
+            }
+            env.0.insert(var.clone(), value);
+        }
+        Ok(self.disjunction.eval(&env)?)
+    }
+}
+
 impl Input {
     /// Do... something with the results.
     pub fn handle_results(&self, results: &[Sample]) -> Result<(), anyhow::Error> {
-        assert!(!results.is_empty());
+        if results.is_empty() {
+            eprintln!("...no results");
+            return Ok(());
+        }
         let variables = self.ordered_variables().collect_vec();
         let mut env = Env(HashMap::new());
         'per_result: for result in results {
@@ -336,7 +398,7 @@ impl Input {
             }
 
             // Double-check that the result is actually meaningful.
-            if self.disjunction.eval(&env) {
+            if self.disjunction.eval(&env)? {
                 println!("=> 1 [PASS]");
             } else {
                 println!("=> 0 [FAIL]");
@@ -1,48 +1,199 @@
+//! Problem types, and how they compile to QUBO.
+//!
+//! Each problem type is a [`Frontend`], registered under the value of the `type` tag of
+//! source files, see [`registry`]. Problem types defined outside of this crate are added
+//! with [`registry::register`].
+
+use std::{fmt::Debug, path::Path, sync::Arc};
+
 use serde::{Deserialize, Serialize};
 
-use crate::{backend, runtime::run::Sample};
+use crate::{backend::qubo::Constraints, runtime::run::Sample};
+use diagnostics::Diagnostic;
 
+pub mod diagnostics;
 pub mod max3sat;
+pub mod plugin;
+pub mod registry;
 
-/// Formats understood by the various frontends.
-///
-/// As of this writing, we read everything from yaml source files.
-#[derive(Deserialize, Serialize)]
-#[serde(tag = "type")]
-pub enum Input {
-    /// Raw QUBO constraints.
-    #[serde(rename = "qubo")]
-    Qubo(backend::qubo::Constraints),
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("invalid max3sat problem")]
+    Max3Sat(#[from] max3sat::Error),
+
+    #[error("error in frontend plugin")]
+    Plugin(#[from] plugin::Error),
+
+    #[error("invalid QUBO")]
+    Qubo(#[from] crate::backend::qubo::Error),
+
+    #[error("missing problem `type`")]
+    MissingKind,
+
+    #[error("unknown problem type `{0}`")]
+    UnknownKind(String),
+
+    #[error("problem type `{0}` is built in, and may not be replaced")]
+    BuiltinKind(String),
+
+    #[error("invalid {kind} problem")]
+    Invalid {
+        kind: String,
+        #[source]
+        source: serde_json::Error,
+    },
+}
+
+/// A problem type, i.e. a way to read problems from source files.
+pub trait Frontend: Send + Sync {
+    /// The value of the `type` tag of source files, e.g. `max3sat`.
+    fn kind(&self) -> &str;
+
+    /// Read a problem from the contents of a source file, without the `type` tag.
+    fn parse(&self, value: serde_json::Value) -> Result<Arc<dyn Problem>, Error>;
+}
+
+/// A problem, as read by a [`Frontend`].
+pub trait Problem: Send + Sync {
+    /// The value of the `type` tag of the frontend which read this problem.
+    fn kind(&self) -> &str;
+
+    /// The problem, as it may be read back by its frontend.
+    fn to_value(&self) -> Result<serde_json::Value, Error>;
 
-    /// An input for maximal 3SAT.
-    #[serde(rename = "max3sat")]
-    Max3Sat(max3sat::Input),
+    /// Compile the problem to a set of QUBO constraints.
+    fn to_constraints(&self) -> Result<Constraints, Error>;
+
+    /// Process results obtained from the QPU or emulator.
+    ///
+    /// Typically, turn them into something human-readable.
+    fn handle_results(&self, samples: &[Sample]) -> Result<(), anyhow::Error>;
+
+    /// Whether a sample, as a string of `0` and `1` in the order of the QUBO variables,
+    /// is a solution of the problem.
+    fn validate_solution(&self, bitstring: &str) -> Result<bool, Error>;
 }
 
+/// A problem, of any of the registered types.
+///
+/// Serialized as the contents of the source file, including the `type` tag.
+#[derive(Clone)]
+pub struct Input(Arc<dyn Problem>);
+
 impl Input {
-    /// Compile the input to a set of QUBO constraints.
-    pub fn to_constraints(&self) -> Result<backend::qubo::Constraints, anyhow::Error> {
-        match *self {
-            Self::Max3Sat(ref input) => Ok(input.to_qubo()),
-            Self::Qubo(ref input) => Ok(input.clone()),
+    pub fn new(problem: impl Problem + 'static) -> Self {
+        Self(Arc::new(problem))
+    }
+
+    /// Read a problem from a yaml source file.
+    ///
+    /// On success, also returns the warnings about the source, e.g. duplicate clauses.
+    pub fn load(path: &Path) -> Result<(Self, Vec<Diagnostic>), crate::Error> {
+        let source = std::fs::read_to_string(path).map_err(|source| crate::Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        match diagnostics::check(&source) {
+            (Some(input), warnings) => Ok((input, warnings)),
+            (None, diagnostics) => Err(crate::Error::Diagnostics {
+                path: path.to_path_buf(),
+                diagnostics,
+            }),
         }
     }
-}
 
-impl Input {
+    pub fn problem(&self) -> &dyn Problem {
+        self.0.as_ref()
+    }
+
+    /// The name of the problem type, as written in source files.
+    pub fn kind(&self) -> &str {
+        self.0.kind()
+    }
+
+    /// Compile the input to a set of QUBO constraints.
+    pub fn to_constraints(&self) -> Result<Constraints, Error> {
+        self.0.to_constraints()
+    }
+
     /// Process results obtained from the QPU or emulator.
-    ///
-    /// Typically, use the input to turn them into something human-readable.
     pub fn handle_results(&self, samples: &[Sample]) -> Result<(), anyhow::Error> {
-        match *self {
-            Self::Max3Sat(ref input) => input.handle_results(samples),
-            Self::Qubo(_) => {
-                let mut writer = csv::Writer::from_writer(std::io::stdout());
-                for record in samples {
-                    writer.serialize(record)?;
-                }
-                Ok(())
-            }
+        self.0.handle_results(samples)
+    }
+
+    /// Whether a sample is a solution of the problem.
+    pub fn validate_solution(&self, bitstring: &str) -> Result<bool, Error> {
+        self.0.validate_solution(bitstring)
+    }
+}
+
+impl From<Arc<dyn Problem>> for Input {
+    fn from(problem: Arc<dyn Problem>) -> Self {
+        Self(problem)
+    }
+}
+
+impl Debug for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Input({})", self.kind())
+    }
+}
+
+impl Serialize for Input {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut value = self.0.to_value().map_err(serde::ser::Error::custom)?;
+        let serde_json::Value::Object(ref mut fields) = value else {
+            return Err(serde::ser::Error::custom(format!(
+                "{} problems must serialize as objects",
+                self.kind()
+            )));
+        };
+        fields.insert("type".to_string(), self.kind().into());
+        value.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Input {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        registry::parse(value).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Raw QUBO constraints.
+impl Problem for Constraints {
+    fn kind(&self) -> &str {
+        "qubo"
+    }
+
+    fn to_value(&self) -> Result<serde_json::Value, Error> {
+        serde_json::to_value(self).map_err(|source| Error::Invalid {
+            kind: self.kind().to_string(),
+            source,
+        })
+    }
+
+    fn to_constraints(&self) -> Result<Constraints, Error> {
+        Ok(self.clone())
+    }
+
+    fn handle_results(&self, samples: &[Sample]) -> Result<(), anyhow::Error> {
+        let mut writer = csv::Writer::from_writer(std::io::stdout());
+        for record in samples {
+            writer.serialize(record)?;
         }
+        Ok(())
+    }
+
+    /// A sample is a solution iff it is a ground state.
+    fn validate_solution(&self, bitstring: &str) -> Result<bool, Error> {
+        self.energy(bitstring)?;
+        Ok(self.ground_states()?.iter().any(|state| state == bitstring))
     }
 }
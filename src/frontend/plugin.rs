@@ -0,0 +1,296 @@
+//! Problem types implemented as WebAssembly components, loaded at runtime.
+//!
+//! A plugin is a component, in binary or text format, without imports, which implements
+//! the `plugin` world of `wit/plugin.wit`:
+//! - `kind`, the value of the `type` tag of the problems it reads;
+//! - `compile`, from a problem to its QUBO;
+//! - `report`, from a problem and samples to a human-readable report;
+//! - `validate`, whether a bitstring is a solution of a problem, or `none` if the solutions
+//!   are the ground states of the QUBO.
+//!
+//! Problems are passed as JSON documents, i.e. the source file without its `type` tag.
+//! Plugins may be written in any language with bindings for WIT, e.g. with `wit-bindgen`
+//! or `cargo component` for Rust.
+//!
+//! Plugins are sandboxed: they have no access to the host besides their inputs, and their
+//! memory and execution time are bounded.
+
+use std::{path::Path, sync::Arc};
+
+use wasmtime::{
+    component::{Component, Linker},
+    Config, Engine, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+use super::{Frontend, Problem};
+use crate::{backend::qubo::Constraints, runtime::run::Sample};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "wit/plugin.wit",
+        world: "plugin",
+    });
+}
+
+/// The largest memory a plugin may use, in bytes.
+const MAX_MEMORY: usize = 256 << 20;
+
+/// The number of instructions, roughly, a plugin may execute per call, by default.
+pub const DEFAULT_FUEL: u64 = 10_000_000_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot load plugin")]
+    Load(#[source] anyhow::Error),
+
+    #[error("plugin `{0}` failed")]
+    Trap(&'static str, #[source] anyhow::Error),
+
+    #[error("plugin `{0}` returned an invalid QUBO: {1}")]
+    InvalidQubo(&'static str, String),
+
+    #[error("plugin reported an error: {0}")]
+    Reported(String),
+}
+
+/// A problem type implemented by a plugin.
+#[derive(Clone)]
+pub struct Plugin {
+    engine: Engine,
+    component: Component,
+    linker: Arc<Linker<StoreLimits>>,
+    kind: String,
+    fuel: u64,
+}
+
+impl Plugin {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let bytes = std::fs::read(path).map_err(|err| Error::Load(err.into()))?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// Load a plugin from its binary or text representation.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        config.wasm_component_model(true);
+        let engine = Engine::new(&config).map_err(Error::Load)?;
+        let component = Component::new(&engine, bytes).map_err(Error::Load)?;
+        // Plugins may not import anything.
+        let linker = Arc::new(Linker::new(&engine));
+        let mut plugin = Self {
+            engine,
+            component,
+            linker,
+            kind: String::new(),
+            fuel: DEFAULT_FUEL,
+        };
+        let (mut store, instance) = plugin.instantiate().map_err(Error::Load)?;
+        plugin.kind = instance
+            .call_kind(&mut store)
+            .map_err(|err| Error::Trap("kind", err))?;
+        Ok(plugin)
+    }
+
+    /// Bound the number of instructions executed per call.
+    pub fn with_fuel(self, fuel: u64) -> Self {
+        Self { fuel, ..self }
+    }
+
+    /// A fresh instance, so that calls do not share state.
+    fn instantiate(&self) -> Result<(Store<StoreLimits>, bindings::Plugin), anyhow::Error> {
+        let limits = StoreLimitsBuilder::new().memory_size(MAX_MEMORY).build();
+        let mut store = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(self.fuel)?;
+        let instance = bindings::Plugin::instantiate(&mut store, &self.component, &self.linker)?;
+        Ok((store, instance))
+    }
+
+    /// Call an export on a fresh instance.
+    fn call<T>(
+        &self,
+        export: &'static str,
+        f: impl FnOnce(&mut Store<StoreLimits>, &bindings::Plugin) -> Result<T, anyhow::Error>,
+    ) -> Result<T, Error> {
+        let (mut store, instance) = self.instantiate().map_err(|err| Error::Trap(export, err))?;
+        f(&mut store, &instance).map_err(|err| Error::Trap(export, err))
+    }
+}
+
+impl Frontend for Plugin {
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn parse(&self, value: serde_json::Value) -> Result<Arc<dyn Problem>, super::Error> {
+        Ok(Arc::new(PluginProblem {
+            plugin: self.clone(),
+            value,
+        }))
+    }
+}
+
+/// A problem read by a plugin.
+struct PluginProblem {
+    plugin: Plugin,
+    value: serde_json::Value,
+}
+
+impl Problem for PluginProblem {
+    fn kind(&self) -> &str {
+        &self.plugin.kind
+    }
+
+    fn to_value(&self) -> Result<serde_json::Value, super::Error> {
+        Ok(self.value.clone())
+    }
+
+    fn to_constraints(&self) -> Result<Constraints, super::Error> {
+        const EXPORT: &str = "compile";
+        let problem = self.value.to_string();
+        let bindings::Qubo { names, weights } = self
+            .plugin
+            .call(EXPORT, |store, instance| {
+                instance.call_compile(store, &problem)
+            })?
+            .map_err(Error::Reported)?;
+        let num_nodes = names.len();
+        if weights.len() != num_nodes || weights.iter().any(|row| row.len() != num_nodes) {
+            return Err(Error::InvalidQubo(
+                EXPORT,
+                format!("expected {num_nodes} rows of {num_nodes} weights, one per name"),
+            )
+            .into());
+        }
+        let data = weights.into_iter().flatten().collect();
+        let names = names.into_iter().map(Into::into).collect();
+        Constraints::try_new(num_nodes, data, names)
+            .ok_or_else(|| Error::InvalidQubo(EXPORT, "inconsistent dimensions".into()).into())
+    }
+
+    fn handle_results(&self, samples: &[Sample]) -> Result<(), anyhow::Error> {
+        let problem = self.value.to_string();
+        let samples = samples
+            .iter()
+            .map(|sample| bindings::Sample {
+                bitstring: sample.bitstring.clone(),
+                instances: sample.instances,
+            })
+            .collect::<Vec<_>>();
+        let report = self
+            .plugin
+            .call("report", |store, instance| {
+                instance.call_report(store, &problem, &samples)
+            })?
+            .map_err(Error::Reported)?;
+        println!("{report}");
+        Ok(())
+    }
+
+    fn validate_solution(&self, bitstring: &str) -> Result<bool, super::Error> {
+        let problem = self.value.to_string();
+        let valid = self.plugin.call("validate", |store, instance| {
+            instance.call_validate(store, &problem, bitstring)
+        })?;
+        match valid {
+            Some(valid) => Ok(valid),
+            None => self.to_constraints()?.validate_solution(bitstring),
+        }
+    }
+}
+
+/// Reads problems of type `pair`, whose QUBO has two variables, exactly one of which
+/// should be set. Its `validate` never returns.
+#[cfg(test)]
+const TEST_PLUGIN: &str = r#"
+(component
+  (core module $plugin
+    (memory (export "memory") 1)
+    ;; `kind`, and its return area.
+    (data (i32.const 0) "pair")
+    (data (i32.const 8) "\00\00\00\00\04\00\00\00")
+    ;; The names of the QUBO, and their list.
+    (data (i32.const 16) "ab")
+    (data (i32.const 24) "\10\00\00\00\01\00\00\00\11\00\00\00\01\00\00\00")
+    ;; The rows of weights, [-1, 2] and [2, -1], and their list.
+    (data (i32.const 40) "\00\00\00\00\00\00\f0\bf\00\00\00\00\00\00\00\40")
+    (data (i32.const 56) "\00\00\00\00\00\00\00\40\00\00\00\00\00\00\f0\bf")
+    (data (i32.const 72) "\28\00\00\00\02\00\00\00\38\00\00\00\02\00\00\00")
+    ;; `ok` of the QUBO.
+    (data (i32.const 88) "\00\00\00\00\18\00\00\00\02\00\00\00\48\00\00\00\02\00\00\00")
+    ;; `ok` of the report.
+    (data (i32.const 112) "one of a, b")
+    (data (i32.const 128) "\00\00\00\00\70\00\00\00\0b\00\00\00")
+    (global $next (mut i32) (i32.const 1024))
+    (func (export "realloc") (param i32 i32 i32 i32) (result i32)
+      (local $ptr i32)
+      (local.set $ptr (global.get $next))
+      (global.set $next
+        (i32.add
+          (local.get $ptr)
+          (i32.and (i32.add (local.get 3) (i32.const 7)) (i32.const -8))))
+      (local.get $ptr))
+    (func (export "kind") (result i32)
+      (i32.const 8))
+    (func (export "compile") (param i32 i32) (result i32)
+      (i32.const 88))
+    (func (export "report") (param i32 i32 i32 i32) (result i32)
+      (i32.const 128))
+    (func (export "validate") (param i32 i32 i32 i32) (result i32)
+      (loop $forever (br $forever))
+      (unreachable)))
+  (core instance $plugin (instantiate $plugin))
+  (alias core export $plugin "memory" (core memory $memory))
+  (alias core export $plugin "realloc" (core func $realloc))
+  (type $qubo' (record (field "names" (list string)) (field "weights" (list (list f64)))))
+  (export $qubo "qubo" (type $qubo'))
+  (type $sample' (record (field "bitstring" string) (field "instances" u64)))
+  (export $sample "sample" (type $sample'))
+  (func (export "kind") (result string)
+    (canon lift (core func $plugin "kind") (memory $memory)))
+  (func (export "compile") (param "problem" string) (result (result $qubo (error string)))
+    (canon lift (core func $plugin "compile") (memory $memory) (realloc $realloc)))
+  (func (export "report")
+    (param "problem" string) (param "samples" (list $sample))
+    (result (result string (error string)))
+    (canon lift (core func $plugin "report") (memory $memory) (realloc $realloc)))
+  (func (export "validate")
+    (param "problem" string) (param "bitstring" string) (result (option bool))
+    (canon lift (core func $plugin "validate") (memory $memory) (realloc $realloc)))
+)
+"#;
+
+#[test]
+fn test_plugin() {
+    let plugin = Plugin::from_bytes(TEST_PLUGIN.as_bytes())
+        .unwrap()
+        .with_fuel(100_000);
+    assert_eq!(Frontend::kind(&plugin), "pair");
+
+    let mut registry = super::registry::Registry::default();
+    registry.register(Arc::new(plugin)).unwrap();
+    let input = registry
+        .parse(serde_json::json!({ "type": "pair", "size": 2 }))
+        .unwrap();
+    let constraints = input.to_constraints().unwrap();
+    assert_eq!(constraints.names(), &["a".into(), "b".into()]);
+    assert_eq!(constraints.at(0, 1).unwrap(), 2.);
+    assert_eq!(
+        serde_json::to_value(&input).unwrap(),
+        serde_json::json!({ "type": "pair", "size": 2 })
+    );
+
+    input
+        .handle_results(&[Sample {
+            bitstring: "10".to_string(),
+            instances: 3,
+        }])
+        .unwrap();
+
+    // Plugins cannot hang the compiler.
+    assert!(matches!(
+        input.validate_solution("10"),
+        Err(super::Error::Plugin(Error::Trap("validate", _)))
+    ));
+}
@@ -0,0 +1,208 @@
+//! The problem types known to the compiler, by the value of their `type` tag.
+//!
+//! The process-wide registry starts with the problem types of this crate, which may not be
+//! replaced. Applications embedding qlafoutea may add their own with [`register`] before
+//! loading any problem.
+
+use std::{
+    collections::BTreeMap,
+    marker::PhantomData,
+    sync::{Arc, OnceLock, PoisonError, RwLock},
+};
+
+use serde::de::DeserializeOwned;
+
+use super::{max3sat, Error, Frontend, Input, Problem};
+use crate::backend::qubo::Constraints;
+
+/// The tags of the problem types of this crate.
+pub const BUILTIN_KINDS: [&str; 2] = ["max3sat", "qubo"];
+
+pub struct Registry {
+    frontends: BTreeMap<String, Arc<dyn Frontend>>,
+}
+
+impl Registry {
+    /// A registry without any problem type.
+    pub fn empty() -> Self {
+        Self {
+            frontends: BTreeMap::new(),
+        }
+    }
+
+    /// Add a problem type, replacing any previous problem type with the same tag, unless
+    /// it is one of [`BUILTIN_KINDS`].
+    pub fn register(
+        &mut self,
+        frontend: Arc<dyn Frontend>,
+    ) -> Result<Option<Arc<dyn Frontend>>, Error> {
+        let kind = frontend.kind();
+        if BUILTIN_KINDS.contains(&kind) && self.frontends.contains_key(kind) {
+            return Err(Error::BuiltinKind(kind.to_string()));
+        }
+        Ok(self.frontends.insert(kind.to_string(), frontend))
+    }
+
+    pub fn get(&self, kind: &str) -> Option<&Arc<dyn Frontend>> {
+        self.frontends.get(kind)
+    }
+
+    /// The tags of all the problem types, in alphabetical order.
+    pub fn kinds(&self) -> impl Iterator<Item = &str> {
+        self.frontends.keys().map(String::as_str)
+    }
+
+    /// Read a problem from the contents of a source file, dispatching on its `type` tag.
+    pub fn parse(&self, mut value: serde_json::Value) -> Result<Input, Error> {
+        let kind = match value
+            .as_object_mut()
+            .and_then(|fields| fields.remove("type"))
+        {
+            Some(serde_json::Value::String(kind)) => kind,
+            Some(other) => return Err(Error::UnknownKind(other.to_string())),
+            None => return Err(Error::MissingKind),
+        };
+        let frontend = self.get(&kind).ok_or(Error::UnknownKind(kind))?;
+        Ok(frontend.parse(value)?.into())
+    }
+}
+
+impl Default for Registry {
+    /// The problem types of this crate.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        let frontends: [Arc<dyn Frontend>; 2] = [
+            Arc::new(Serde::<Constraints>::new("qubo")),
+            Arc::new(Serde::<max3sat::Input>::new("max3sat")),
+        ];
+        for frontend in frontends {
+            registry
+                .frontends
+                .insert(frontend.kind().to_string(), frontend);
+        }
+        registry
+    }
+}
+
+/// A problem type whose problems are read with serde.
+pub struct Serde<T> {
+    kind: String,
+    problem: PhantomData<fn() -> T>,
+}
+
+impl<T> Serde<T> {
+    pub fn new(kind: &str) -> Self {
+        Self {
+            kind: kind.to_string(),
+            problem: PhantomData,
+        }
+    }
+}
+
+impl<T> Frontend for Serde<T>
+where
+    T: DeserializeOwned + Problem + 'static,
+{
+    fn kind(&self) -> &str {
+        &self.kind
+    }
+
+    fn parse(&self, value: serde_json::Value) -> Result<Arc<dyn Problem>, Error> {
+        let problem: T = serde_json::from_value(value).map_err(|source| Error::Invalid {
+            kind: self.kind.clone(),
+            source,
+        })?;
+        Ok(Arc::new(problem))
+    }
+}
+
+fn global() -> &'static RwLock<Registry> {
+    static GLOBAL: OnceLock<RwLock<Registry>> = OnceLock::new();
+    GLOBAL.get_or_init(|| RwLock::new(Registry::default()))
+}
+
+/// Add a problem type to the process-wide registry, see [`Registry::register`].
+pub fn register(frontend: Arc<dyn Frontend>) -> Result<Option<Arc<dyn Frontend>>, Error> {
+    global()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register(frontend)
+}
+
+/// The tags of all the problem types of the process-wide registry.
+pub fn kinds() -> Vec<String> {
+    global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .kinds()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read a problem with the process-wide registry.
+pub fn parse(value: serde_json::Value) -> Result<Input, Error> {
+    global()
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .parse(value)
+}
+
+#[test]
+fn test_registry() {
+    use crate::runtime::run::Sample;
+
+    /// Is there a 1 among the bits?
+    #[derive(serde::Deserialize, serde::Serialize)]
+    struct Any {
+        bits: usize,
+    }
+    impl Problem for Any {
+        fn kind(&self) -> &str {
+            "any"
+        }
+        fn to_value(&self) -> Result<serde_json::Value, Error> {
+            Ok(serde_json::json!({ "bits": self.bits }))
+        }
+        fn to_constraints(&self) -> Result<Constraints, Error> {
+            let names = (0..self.bits).map(|i| format!("b{i}").into()).collect();
+            let mut constraints = Constraints::new(self.bits, names);
+            for i in 0..self.bits {
+                constraints.delta_at(i, i, -1.)?;
+            }
+            Ok(constraints)
+        }
+        fn handle_results(&self, _: &[Sample]) -> Result<(), anyhow::Error> {
+            Ok(())
+        }
+        fn validate_solution(&self, bitstring: &str) -> Result<bool, Error> {
+            Ok(bitstring.contains('1'))
+        }
+    }
+
+    let mut registry = Registry::default();
+    assert_eq!(
+        registry.kinds().collect::<Vec<_>>(),
+        vec!["max3sat", "qubo"]
+    );
+    let source = serde_json::json!({ "type": "any", "bits": 3 });
+    assert!(matches!(
+        registry.parse(source.clone()),
+        Err(Error::UnknownKind(_))
+    ));
+
+    assert!(registry
+        .register(Arc::new(Serde::<Any>::new("any")))
+        .unwrap()
+        .is_none());
+    let input = registry.parse(source.clone()).unwrap();
+    assert_eq!(input.kind(), "any");
+    assert_eq!(input.to_constraints().unwrap().num_nodes(), 3);
+    assert!(input.validate_solution("010").unwrap());
+    assert_eq!(serde_json::to_value(&input).unwrap(), source);
+
+    // Built-in problem types may not be replaced.
+    assert!(matches!(
+        registry.register(Arc::new(Serde::<Any>::new("qubo"))),
+        Err(Error::BuiltinKind(kind)) if kind == "qubo"
+    ));
+}
@@ -1,6 +1,14 @@
 pub mod backend;
+pub mod config;
+pub mod error;
 pub mod frontend;
 pub mod path;
+pub mod plot;
 pub mod runtime;
 pub mod studio;
 pub mod types;
+
+pub use error::Error;
+
+#[cfg(feature = "python")]
+pub mod python;
@@ -1,10 +1,22 @@
-use std::{fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use anyhow::Context;
 use clap::Parser;
 use qlafoutea::{
-    backend::{device::Device, format::Code, qaa, qubo},
+    backend::{
+        analysis,
+        device::Device,
+        format::{self, Code},
+        pulser::waveform::Waveform,
+        qaa, qaoa, qubo,
+    },
+    frontend::{self, plugin::Plugin, Frontend},
     path::PathExt,
-    runtime,
+    plot, runtime,
     types::Quality,
 };
 
@@ -13,23 +25,51 @@ struct Build {
     /// The file to compile.
     source: PathBuf,
 
+    #[command(flatten)]
+    options: BuildOptions,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+struct BuildOptions {
+    /// The device to compile for.
+    ///
+    /// Either the name of a built-in device (AnalogDevice, DigitalAnalogDevice, MockDevice)
+    /// or the path to a JSON device specification, in Pulser's abstract representation.
+    #[arg(long, default_value = "AnalogDevice")]
+    device: String,
+
     /// A seed to use for random number generation.
     #[arg(long, default_value_t = 0)]
     seed: u64,
 
-    /// The minimal quality to accept for compilation.
-    #[arg(long, default_value_t = 0.2)]
-    min_quality: f64,
+    /// The minimal quality to accept for compilation. Defaults to 0.2, or with `--normalize`
+    /// to 0, as the quality measures absolute errors.
+    #[arg(long)]
+    min_quality: Option<f64>,
 
-    /// How long we should run the pulse for.
-    #[arg(long, default_value_t = 4_000)]
-    half_duration_ns: u64,
+    /// How long we should run the pulse for. Defaults to 4000.
+    ///
+    /// With `--target qaa --derive-sweep`, if unspecified, pick a duration long enough for
+    /// the sweep to be adiabatic, within the limits of the device.
+    #[arg(long)]
+    half_duration_ns: Option<u64>,
+
+    /// With `--target qaa`, derive the detuning range and, unless `--half-duration-ns` is
+    /// given, the duration from the problem and the device, instead of sweeping the detuning
+    /// from -5 to 5 rad/µs over 4000 ns.
+    #[arg(long, default_value_t = false)]
+    derive_sweep: bool,
 
     /// An upper bound to the number of iterations in each attempt we make to find an optimal
     /// register.
     #[arg(long, default_value_t = 4_000)]
     max_iters: u64,
 
+    /// How many seeds to try, starting from `--seed`, before giving up on finding a layout
+    /// which meets `--min-quality` and the other thresholds.
+    #[arg(long, default_value_t = 10_000)]
+    max_seeds: u64,
+
     /// While laying out qubo, we make it costly to place atoms too close to the physical limits
     /// of the device. This value determines how much we worry when atoms are laid out in the
     /// unacceptable zone.
@@ -42,22 +82,146 @@ struct Build {
     /// physical limits of the device".
     #[arg(long, default_value_t = 0.95)]
     overflow_protection_threshold: f64,
+
+    /// Also reject layouts in which the interaction between two atoms is off by more than
+    /// this fraction of its QUBO weight, e.g. `0.1` for 10%.
+    #[arg(long)]
+    max_relative_error: Option<f64>,
+
+    /// Also reject layouts in which interactions are off by more than this fraction of their
+    /// QUBO weight, on average.
+    #[arg(long)]
+    max_mean_relative_error: Option<f64>,
+
+    /// Also reject layouts which leave less than this distance, in µm, between an atom and
+    /// the edge of the device.
+    #[arg(long)]
+    min_overflow_margin: Option<f64>,
+
+    /// Also reject layouts in which two atoms are closer than this, in µm.
+    #[arg(long)]
+    min_spacing: Option<f64>,
+
+    /// Scale the QUBO by the largest factor which fits the interactions, amplitude and
+    /// detuning of the device. Energies are still reported in the units of the problem.
+    ///
+    /// Requires `--max-relative-error` or `--max-mean-relative-error`, to accept layouts.
+    #[arg(long, default_value_t = false)]
+    normalize: bool,
+
+    /// Declare the duration, amplitude and detuning endpoints of the pulse as variables,
+    /// which may be changed with `run --set`.
+    #[arg(long, default_value_t = false)]
+    parametrize: bool,
+
+    /// The algorithm to compile to.
+    #[arg(long, default_value_t = Target::Qaa)]
+    target: Target,
+
+    /// With `--target qaoa`, the number of layers.
+    #[arg(long, default_value_t = 2)]
+    qaoa_layers: usize,
+
+    /// With `--target qaoa`, an upper bound to the number of iterations of the optimizer
+    /// tuning the layers. Each iteration runs the sequence at least once.
+    #[arg(long, default_value_t = 20)]
+    qaoa_max_iters: u64,
+
+    /// With `--target qaa`, the shape of the sweep.
+    #[arg(long, default_value_t = ScheduleKind::Linear)]
+    schedule: ScheduleKind,
+
+    /// With `--schedule linear`, `--schedule center-slowdown` or `--schedule gap-aware`, the
+    /// number of interpolation points of each waveform.
+    #[arg(long, default_value_t = 3)]
+    schedule_points: usize,
+
+    /// With `--schedule center-slowdown`, how much to slow down around the middle of the
+    /// sweep, in [0, 1).
+    #[arg(long, default_value_t = 0.5)]
+    center_slowdown: f64,
+
+    /// With `--schedule breakpoints`, the amplitude breakpoints as fractions of the maximal
+    /// amplitude, e.g. `0,0.8,1,0.8,0`.
+    #[arg(long, value_delimiter = ',')]
+    amplitude_breakpoints: Vec<f64>,
+
+    /// With `--schedule breakpoints`, the detuning breakpoints as fractions of the final
+    /// detuning, e.g. `-1,-0.2,0.2,1`.
+    #[arg(long, value_delimiter = ',', allow_hyphen_values = true)]
+    detuning_breakpoints: Vec<f64>,
+
+    /// With `--target qaa`, tune the interior breakpoints of the schedule to maximize the
+    /// probability of finding an optimal solution. Requires a problem small enough to
+    /// enumerate its optimal solutions.
+    #[arg(long, default_value_t = false)]
+    optimize_schedule: bool,
+
+    /// With `--optimize-schedule`, an upper bound to the number of iterations of the
+    /// optimizer. Each iteration runs the sequence at least once.
+    #[arg(long, default_value_t = 20)]
+    schedule_max_iters: u64,
+
+    /// The runner used to evaluate candidates with `--target qaoa` or `--optimize-schedule`.
+    #[arg(long, alias = "qaoa-runner", default_value = DEFAULT_RUNNER, value_parser = parse_runner)]
+    tuning_runner: String,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+enum ScheduleKind {
+    /// Sweep at constant speed.
+    Linear,
+
+    /// Sweep more slowly around the middle, where the detuning crosses zero.
+    CenterSlowdown,
+
+    /// Sweep more slowly where the estimated gap of the Rydberg Hamiltonian is small. Needs
+    /// at most 20 atoms.
+    GapAware,
+
+    /// Use `--amplitude-breakpoints` and `--detuning-breakpoints`.
+    Breakpoints,
+}
+impl Display for ScheduleKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Linear => write!(f, "linear"),
+            Self::CenterSlowdown => write!(f, "center-slowdown"),
+            Self::GapAware => write!(f, "gap-aware"),
+            Self::Breakpoints => write!(f, "breakpoints"),
+        }
+    }
 }
 
+/// The total duration of the initial guess with `--target qaoa`, in ns.
+const QAOA_DEFAULT_DURATION_NS: u64 = 4_000;
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug)]
-enum Runner {
-    PyPulser,
-    PulserStudio,
+enum Target {
+    /// Quantum Adiabatic Algorithm: a single adiabatic sweep.
+    Qaa,
+
+    /// Quantum Approximate Optimization Algorithm: alternating layers, tuned by sampling.
+    Qaoa,
 }
-impl Display for Runner {
+impl Display for Target {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match *self {
-            Self::PyPulser => write!(f, "py-pulser"),
-            Self::PulserStudio => write!(f, "pulser-studio"),
+            Self::Qaa => write!(f, "qaa"),
+            Self::Qaoa => write!(f, "qaoa"),
         }
     }
 }
 
+/// The runner used unless specified otherwise, see `runners`.
+const DEFAULT_RUNNER: &str = "py-pulser";
+
+/// Check that a runner exists, keeping its name.
+fn parse_runner(source: &str) -> Result<String, runtime::runner::Error> {
+    runtime::runner::by_name(source)?;
+    Ok(source.to_string())
+}
+
 #[derive(clap::Parser, Debug)]
 struct Run {
     /// The file to run.
@@ -65,6 +229,16 @@ struct Run {
     /// It must have been compiled already.
     source: PathBuf,
 
+    #[command(flatten)]
+    options: RunOptions,
+
+    /// Return immediately, leaving the run in the background. See `jobs`.
+    #[arg(long, default_value_t = false)]
+    detach: bool,
+}
+
+#[derive(clap::Args, Debug)]
+struct RunOptions {
     /// How many results to display.
     ///
     /// If this value is in [0., 1.], discard any result
@@ -73,99 +247,791 @@ struct Run {
     #[arg(long, default_value_t = 0.5)]
     result_sample_threshold: f64,
 
-    #[arg(long, default_value_t = Runner::PyPulser)]
-    runner: Runner,
+    /// The runner used to sample the sequence, see `runners`.
+    #[arg(long, default_value = DEFAULT_RUNNER, value_parser = parse_runner)]
+    runner: String,
+
+    /// How many times to sample the sequence, if the runner supports it.
+    #[arg(long, default_value_t = runtime::runner::DEFAULT_SHOTS)]
+    shots: u64,
+
+    /// A seed for sampling, if the runner supports it.
+    #[arg(long)]
+    sampling_seed: Option<u64>,
+
+    /// Assign a value to a variable declared by the sequence, e.g. `--set duration=2000`.
+    ///
+    /// May be repeated.
+    #[arg(long = "set", value_parser = parse_assignment)]
+    variables: Vec<(String, f64)>,
+}
+
+#[derive(clap::Parser, Debug)]
+struct Solve {
+    /// The file to compile and run.
+    source: PathBuf,
+
+    #[command(flatten)]
+    build: BuildOptions,
+
+    #[command(flatten)]
+    run: RunOptions,
+
+    /// Also write the compiled file, by default next to where `build` would write it.
+    #[arg(long)]
+    save: Option<Option<PathBuf>>,
+}
+
+#[derive(clap::Parser, Debug)]
+struct Sweep {
+    /// The file to compile and run.
+    source: PathBuf,
+
+    /// An option of `build` to sweep over, with its values, e.g. `--vary seed=0..8`,
+    /// `--vary half-duration-ns=2000,4000` or `--vary min-quality=0.1..0.5:0.1`.
+    ///
+    /// May be repeated, in which case every combination is tried.
+    #[arg(long, required = true, value_parser = parse_axis)]
+    vary: Vec<runtime::sweep::Axis>,
+
+    /// The options of `build` which are not swept over.
+    #[command(flatten)]
+    build: BuildOptions,
+
+    /// The runner used to sample each configuration, see `runners`.
+    #[arg(long, default_value = DEFAULT_RUNNER, value_parser = parse_runner)]
+    runner: String,
+
+    /// The number of layout seeds tried for each configuration, at most, unless
+    /// `max-seeds` is swept over. Configurations which cannot be laid out within
+    /// this budget are reported as `no layout` rather than stalling the sweep.
+    #[arg(long, default_value_t = 1_000)]
+    point_max_seeds: u64,
+
+    /// Where to write the summary. Defaults to `<source>.sweep.csv`, in the current
+    /// directory.
+    #[arg(long)]
+    output: Option<PathBuf>,
+}
+
+fn parse_axis(source: &str) -> Result<runtime::sweep::Axis, anyhow::Error> {
+    let axis = runtime::sweep::Axis::parse(source)?;
+    // Fail early on options we cannot sweep over.
+    if !BuildOptions::SWEEPABLE.contains(&axis.name.as_str()) {
+        return Err(anyhow::anyhow!(
+            "cannot sweep over `{}`, expected one of {}",
+            axis.name,
+            BuildOptions::SWEEPABLE.join(", ")
+        ));
+    }
+    Ok(axis)
+}
+
+impl BuildOptions {
+    /// The options which may be swept over, as spelled on the command line.
+    const SWEEPABLE: &'static [&'static str] = &[
+        "seed",
+        "min-quality",
+        "half-duration-ns",
+        "max-iters",
+        "max-seeds",
+        "overflow-protection-factor",
+        "overflow-protection-threshold",
+        "max-relative-error",
+        "max-mean-relative-error",
+        "min-overflow-margin",
+        "min-spacing",
+        "schedule-points",
+        "center-slowdown",
+        "schedule-max-iters",
+        "qaoa-layers",
+        "qaoa-max-iters",
+    ];
+
+    /// A copy of these options, with option `name` (as spelled on the command line) set
+    /// to `value`.
+    fn with(&self, name: &str, value: f64) -> Result<Self, anyhow::Error> {
+        let integer = || {
+            if value < 0. || value.fract() != 0. {
+                Err(anyhow::anyhow!(
+                    "{name} expects a non-negative integer, got {value}"
+                ))
+            } else {
+                Ok(value as u64)
+            }
+        };
+        let mut options = self.clone();
+        match name {
+            "seed" => options.seed = integer()?,
+            "min-quality" => options.min_quality = Some(value),
+            "half-duration-ns" => options.half_duration_ns = Some(integer()?),
+            "max-iters" => options.max_iters = integer()?,
+            "max-seeds" => options.max_seeds = integer()?,
+            "overflow-protection-factor" => options.overflow_protection_factor = value,
+            "overflow-protection-threshold" => options.overflow_protection_threshold = value,
+            "max-relative-error" => options.max_relative_error = Some(value),
+            "max-mean-relative-error" => options.max_mean_relative_error = Some(value),
+            "min-overflow-margin" => options.min_overflow_margin = Some(value),
+            "min-spacing" => options.min_spacing = Some(value),
+            "schedule-points" => options.schedule_points = integer()? as usize,
+            "center-slowdown" => options.center_slowdown = value,
+            "schedule-max-iters" => options.schedule_max_iters = integer()?,
+            "qaoa-layers" => options.qaoa_layers = integer()? as usize,
+            "qaoa-max-iters" => options.qaoa_max_iters = integer()?,
+            _ => return Err(anyhow::anyhow!("cannot sweep over `{name}`")),
+        }
+        Ok(options)
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+struct Inspect {
+    /// The file to inspect.
+    source: PathBuf,
+}
+
+#[derive(clap::Parser, Debug)]
+struct Plot {
+    /// The file to plot.
+    source: PathBuf,
+
+    /// Where to write the register. Defaults to `<source>.register.svg`, in the current
+    /// directory.
+    #[arg(long)]
+    register: Option<PathBuf>,
+
+    /// Where to write the pulses. Defaults to `<source>.pulses.svg`, in the current
+    /// directory.
+    #[arg(long)]
+    pulses: Option<PathBuf>,
+}
+
+#[derive(clap::Parser, Debug)]
+struct Migrate {
+    /// The files to upgrade, in place.
+    #[arg(required = true)]
+    sources: Vec<PathBuf>,
+}
+
+#[derive(clap::Parser, Debug)]
+struct Jobs {
+    #[command(subcommand)]
+    command: JobsCommand,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum JobsCommand {
+    /// List the jobs, oldest first.
+    List,
+
+    /// Show the status of a job.
+    Status { id: String },
+
+    /// Report the results of a finished job, as `run` would.
+    Fetch { id: String },
+
+    /// Cancel a job.
+    Cancel { id: String },
+
+    /// Execute a job on a local runner, on behalf of `run --detach`.
+    #[command(hide = true)]
+    Work { id: String },
+}
+
+#[derive(clap::Parser, Debug)]
+struct MockServer {
+    /// The address to listen on.
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// The runner executing the jobs, see `runners`.
+    #[arg(long, default_value = DEFAULT_RUNNER, value_parser = parse_runner)]
+    runner: String,
+
+    /// Require clients to authenticate with this token.
+    #[arg(long)]
+    token: Option<String>,
+}
+
+fn parse_assignment(source: &str) -> Result<(String, f64), anyhow::Error> {
+    let Some((name, value)) = source.split_once('=') else {
+        return Err(anyhow::anyhow!("expected `name=value`, got `{source}`"));
+    };
+    let value = value
+        .trim()
+        .parse()
+        .with_context(|| format!("invalid value for variable `{}`", name.trim()))?;
+    Ok((name.trim().to_string(), value))
 }
 
 #[derive(Debug, Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+
+    /// A WebAssembly module implementing a problem type, see `frontend::plugin`.
+    ///
+    /// May be repeated. Problems of this type may then be built, and the files built
+    /// from them run or inspected.
+    #[arg(long, global = true)]
+    frontend_plugin: Vec<PathBuf>,
+
+    /// The Python interpreter running Pulser, e.g. `venv/bin/python`.
+    ///
+    /// By default, the interpreter of the active virtual environment, then the one given
+    /// as `python` in the configuration, then `python3` in the `PATH`.
+    #[arg(long, global = true)]
+    python: Option<PathBuf>,
+}
+
+#[derive(Debug, clap::Subcommand)]
 enum Command {
     /// Build from high-level code.
     Build(Build),
 
     /// Launch a previously built program.
     Run(Run),
+
+    /// Build then immediately run, without writing intermediate files.
+    Solve(Solve),
+
+    /// Build and run every combination of a set of options, then summarize the results.
+    Sweep(Sweep),
+
+    /// Summarize a previously built program.
+    Inspect(Inspect),
+
+    /// Render the register and pulses of a previously built program as SVG.
+    Plot(Plot),
+
+    /// Upgrade files built by older versions of qlafoutea to the current format.
+    Migrate(Migrate),
+
+    /// List the runners, i.e. emulators and QPUs, and what they accept.
+    Runners,
+
+    /// Serve the job API of remote runners locally, running jobs with another runner.
+    MockServer(MockServer),
+
+    /// Track the runs started with `run --detach`.
+    Jobs(Jobs),
 }
 
-fn build(args: Build) -> Result<(), anyhow::Error> {
-    let device = Device::analog();
-    let path_source = args.source.as_path();
+fn load_device(spec: &str) -> Result<Device, anyhow::Error> {
+    if let Some(device) = Device::preset(spec) {
+        return Ok(device);
+    }
+    let file = std::fs::File::open(spec).with_context(|| {
+        format!(
+            "`{spec}` is neither a known device ({}) nor a device file",
+            Device::PRESETS.join(", ")
+        )
+    })?;
+    Device::from_json(file).with_context(|| format!("Invalid device specification in {spec}"))
+}
+
+/// Compile a source file, in memory.
+fn compile(path_source: &Path, args: BuildOptions) -> Result<Code, anyhow::Error> {
+    let device = load_device(&args.device)?;
+    eprintln!("...compiling for device {}", device.name());
 
     // Step: parse source.
-    let source = std::fs::File::open(path_source).expect("Failed to open source file");
-    let problem = serde_yaml::from_reader::<_, qlafoutea::frontend::Input>(source)
-        .expect("Failed to parse source file");
+    let (problem, warnings) = frontend::Input::load(path_source)?;
+    for warning in warnings {
+        eprint!("{}", warning.render(path_source));
+    }
 
     // Step: compile to qubo.
-    let constraints = problem.to_constraints().expect("Failed to compile to QUBO");
+    let constraints = problem.to_constraints().map_err(qlafoutea::Error::from)?;
+
+    // Step: optionally, scale the qubo to the device.
+    if args.normalize && args.max_relative_error.is_none() && args.max_mean_relative_error.is_none()
+    {
+        return Err(anyhow::anyhow!(
+            "--normalize requires --max-relative-error or --max-mean-relative-error, as the layout quality measures absolute errors"
+        ));
+    }
+    let (constraints, normalization) = if args.normalize {
+        let normalization = constraints.normalization(&device);
+        eprintln!(
+            "...normalizing the QUBO by a factor {:.4}",
+            normalization.factor
+        );
+        for line in &normalization.reasoning {
+            eprintln!("...   {line}");
+        }
+        (
+            constraints.scaled(normalization.factor),
+            Some(normalization),
+        )
+    } else {
+        (constraints, None)
+    };
 
     // Step: compile the qubo to a register.
     eprintln!("...compiling {} constraints", constraints.num_constraints());
     eprintln!("{}", constraints);
-    let (register, quality, seed) = constraints
-        .layout(
-            &device,
-            &qubo::Options {
-                seed: args.seed,
-                min_quality: Quality::new(args.min_quality),
-                max_iters: args.max_iters,
-                overflow_protection_factor: args.overflow_protection_factor,
-                overflow_protection_threshold: args.overflow_protection_threshold,
-            },
-        )
-        .expect("Failed to compile qubo");
+    let qubo_options = qubo::Options {
+        seed: args.seed,
+        min_quality: match args.min_quality {
+            Some(min_quality) => Quality::new(min_quality)?,
+            None if args.normalize => Quality::WORST,
+            None => qubo::Options::default().min_quality,
+        },
+        max_iters: args.max_iters,
+        max_seeds: args.max_seeds,
+        overflow_protection_factor: args.overflow_protection_factor,
+        overflow_protection_threshold: args.overflow_protection_threshold,
+        acceptance: analysis::Acceptance {
+            max_relative_error: args.max_relative_error,
+            max_mean_relative_error: args.max_mean_relative_error,
+            min_overflow_margin_um: args.min_overflow_margin,
+            min_spacing_um: args.min_spacing,
+        },
+    };
+    let qubo::Layout {
+        register,
+        quality,
+        seed,
+        report: layout_report,
+    } = constraints
+        .layout(&device, &qubo_options)
+        .map_err(qlafoutea::Error::from)?;
     eprintln!(
         "...compiled to {} qubits with a quality of {} (using seed {})",
         register.len(),
         quality,
         seed
     );
+    for line in layout_report.to_string().lines() {
+        eprintln!("...   {line}");
+    }
 
-    // Step: integrate QAA.
-    let sequence = qaa::compile(
-        &constraints,
-        device,
-        register,
-        &qaa::Options {
-            half_duration_ns: args.half_duration_ns as f64,
-        },
-    );
+    let (sequence, qaa_options, report, qaoa_options) = match args.target {
+        Target::Qaa => {
+            // Step: integrate QAA.
+            let schedule = match args.schedule {
+                ScheduleKind::Linear => qaa::Schedule::Linear {
+                    points: args.schedule_points,
+                },
+                ScheduleKind::CenterSlowdown => qaa::Schedule::CenterSlowdown {
+                    points: args.schedule_points,
+                    slowdown: args.center_slowdown,
+                },
+                ScheduleKind::GapAware => qaa::Schedule::GapAware {
+                    points: args.schedule_points,
+                },
+                ScheduleKind::Breakpoints => qaa::Schedule::Breakpoints {
+                    amplitude: args.amplitude_breakpoints,
+                    detuning: args.detuning_breakpoints,
+                },
+            };
+            let options = qaa::Options {
+                half_duration_ns: args.half_duration_ns.map(|d| d as f64),
+                derive_sweep: args.derive_sweep,
+                parametrize: args.parametrize,
+                schedule,
+            };
+            if args.optimize_schedule {
+                let runner = runtime::runner::by_name(&args.tuning_runner)?;
+                let (sequence, report, schedule, probability) = qaa::optimize(
+                    &constraints,
+                    &device,
+                    &register,
+                    &options,
+                    &qaa::OptimizeOptions {
+                        max_iters: args.schedule_max_iters,
+                    },
+                    &|sequence| runtime::run::sample(sequence, runner.as_ref()),
+                )?;
+                eprintln!(
+                    "...optimized QAA schedule to a ground-state probability of {} ({:?})",
+                    probability, schedule
+                );
+                (sequence, Some(options), Some(report), None)
+            } else {
+                let (sequence, report) = qaa::compile(&constraints, device, register, &options)?;
+                (sequence, Some(options), Some(report), None)
+            }
+        }
+        Target::Qaoa => {
+            // Step: integrate QAOA, tuning the layers against the runner.
+            let runner = runtime::runner::by_name(&args.tuning_runner)?;
+            let options = qaoa::Options {
+                layers: args.qaoa_layers,
+                duration_ns: args.half_duration_ns.unwrap_or(QAOA_DEFAULT_DURATION_NS) as f64,
+                max_iters: args.qaoa_max_iters,
+            };
+            let (sequence, angles, energy) =
+                qaoa::optimize(&constraints, &device, &register, &options, &|sequence| {
+                    runtime::run::sample(sequence, runner.as_ref())
+                })?;
+            let energy = match normalization {
+                Some(ref normalization) => normalization.to_problem_units(energy),
+                None => energy,
+            };
+            eprintln!(
+                "...optimized {} QAOA layers to a mean energy of {} (mixing {:?} ns, phase {:?} ns)",
+                args.qaoa_layers, energy, angles.mixing, angles.phase
+            );
+            (sequence, None, None, Some(options))
+        }
+    };
+    if let Some(ref report) = report {
+        eprintln!(
+            "...sweeping amplitude up to {:.3} rad/µs and detuning from {:.3} to {:.3} rad/µs over {} ns",
+            report.omega, report.delta_0, report.delta_f, report.duration_ns
+        );
+        for line in &report.reasoning {
+            eprintln!("...   {line}");
+        }
+    }
+
+    // Step: check that the ground states of the Rydberg Hamiltonian solve the QUBO.
+    let ground_states = if constraints.num_nodes() > qubo::MAX_NODES_BRUTE_FORCE {
+        eprintln!(
+            "...skipping ground state analysis, {} atoms are too many",
+            constraints.num_nodes()
+        );
+        None
+    } else {
+        match analysis::GroundStates::of(&constraints, &sequence) {
+            Ok(analysis) if analysis.is_preserved() => {
+                eprintln!("...{}", analysis.summary(normalization.as_ref()));
+                Some(analysis)
+            }
+            Ok(analysis) => {
+                eprintln!("...warning: {}", analysis.summary(normalization.as_ref()));
+                eprintln!("...   consider another --seed or a higher --min-quality");
+                Some(analysis)
+            }
+            Err(err) => {
+                eprintln!(
+                    "...cannot analyze ground states: {:#}",
+                    anyhow::Error::from(err)
+                );
+                None
+            }
+        }
+    };
 
     // Step: write "bytecode".
-    let code = Code::try_new(problem, sequence).expect("Couldn't generate code");
+    let mut code = Code::try_new(
+        problem,
+        constraints,
+        &sequence,
+        format::Layout {
+            quality,
+            seed,
+            options: qubo_options,
+        },
+    )
+    .context("Couldn't generate code")?;
+    code.qaa_options = qaa_options;
+    code.qaa = report;
+    code.qaoa_options = qaoa_options;
+    code.ground_states = ground_states;
+    code.normalization = normalization;
+    Ok(code)
+}
 
-    // Write pulser output.
-    // In the future, we'll probably write more data in the file.
-    let path_dest = path_source.here_with_ext("qlaf");
+fn save(code: &Code, path_dest: &Path) -> Result<(), anyhow::Error> {
     eprintln!("...generating {}", path_dest.display());
     let out_dest = std::fs::File::create(path_dest)?;
-    serde_json::to_writer_pretty(out_dest, &code)?;
+    serde_json::to_writer_pretty(out_dest, code)?;
     Ok(())
 }
 
-fn run(args: Run) -> Result<(), anyhow::Error> {
+fn build(args: Build) -> Result<(), anyhow::Error> {
+    let code = compile(&args.source, args.options)?;
+    save(&code, &args.source.as_path().here_with_ext("qlaf"))
+}
+
+fn run(args: Run, plugins: &[PathBuf], python: Option<&Path>) -> Result<(), anyhow::Error> {
+    if args.detach {
+        return detach(args, plugins, python);
+    }
     eprintln!("...loading code");
-    let input = std::fs::File::open(args.source).expect("Failed to open code");
-    let code: Code = serde_yaml::from_reader(input).expect("Failed to parse code");
+    let code = load_code(&args.source)?;
+    execute(code, args.options)
+}
 
+/// Start a job, in the background.
+fn detach(args: Run, plugins: &[PathBuf], python: Option<&Path>) -> Result<(), anyhow::Error> {
+    let store = runtime::jobs::Store::open_default()?;
+    let record = store.submit(
+        &args.source,
+        runtime::jobs::JobOptions {
+            runner: args.options.runner,
+            shots: args.options.shots,
+            seed: args.options.sampling_seed,
+            variables: args.options.variables.into_iter().collect(),
+            result_sample_threshold: args.options.result_sample_threshold,
+        },
+    )?;
+    if record.needs_worker() {
+        let log = store.log_path(&record.id);
+        let stderr = std::fs::File::create(&log)
+            .with_context(|| format!("Cannot create {}", log.display()))?;
+        let mut cmd = std::process::Command::new(std::env::current_exe()?);
+        for plugin in plugins {
+            cmd.arg("--frontend-plugin").arg(plugin);
+        }
+        if let Some(python) = python {
+            cmd.arg("--python").arg(python);
+        }
+        cmd.args(["jobs", "work", &record.id])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(stderr)
+            .spawn()
+            .context("Failed to start job")?;
+    }
+    eprintln!(
+        "...started job {}, check on it with `jobs status {}`",
+        record.id, record.id
+    );
+    println!("{}", record.id);
+    Ok(())
+}
+
+fn jobs(args: Jobs) -> Result<(), anyhow::Error> {
+    let store = runtime::jobs::Store::open_default()?;
+    let mut out = std::io::stdout().lock();
+    match args.command {
+        JobsCommand::List => runtime::jobs::print_list(&store.list()?, &mut out),
+        JobsCommand::Status { id } => runtime::jobs::print_status(&store.refresh(&id)?, &mut out),
+        JobsCommand::Fetch { id } => {
+            let record = store.refresh(&id)?;
+            match (record.state, record.samples) {
+                (runtime::runner::JobState::Done, Some(samples)) => runtime::run::report(
+                    &record.code,
+                    samples,
+                    record.options.result_sample_threshold,
+                ),
+                (runtime::runner::JobState::Failed, _) => Err(anyhow::anyhow!(
+                    "job {id} failed: {}",
+                    record.error.unwrap_or_default()
+                )),
+                (state, _) => Err(anyhow::anyhow!("job {id} is {state}, no results to fetch")),
+            }
+        }
+        JobsCommand::Cancel { id } => {
+            let record = store.cancel(&id)?;
+            eprintln!("...job {id} is {}", record.state);
+            Ok(())
+        }
+        JobsCommand::Work { id } => Ok(store.work(&id)?),
+    }
+}
+
+fn execute(code: Code, args: RunOptions) -> Result<(), anyhow::Error> {
     eprintln!("...starting emulation");
     runtime::run::run(
         code,
         runtime::run::Options {
             result_sample_threshold: args.result_sample_threshold,
-            runner: match args.runner {
-                Runner::PulserStudio => runtime::run::Runner::PulserStudio,
-                Runner::PyPulser => runtime::run::Runner::PyPulser,
+            runner: runtime::runner::by_name(&args.runner)?,
+            sampling: runtime::runner::Options {
+                shots: args.shots,
+                seed: args.sampling_seed,
             },
+            variables: args.variables.into_iter().collect(),
         },
     )?;
 
     Ok(())
 }
 
+fn solve(args: Solve) -> Result<(), anyhow::Error> {
+    let code = compile(&args.source, args.build)?;
+    match args.save {
+        None => {}
+        Some(None) => save(&code, &args.source.as_path().here_with_ext("qlaf"))?,
+        Some(Some(ref path)) => save(&code, path)?,
+    }
+    execute(code, args.run)
+}
+
+fn sweep(args: Sweep) -> Result<(), anyhow::Error> {
+    let points = runtime::sweep::grid(&args.vary)?;
+    eprintln!("...sweeping over {} configurations", points.len());
+    let runner = runtime::runner::by_name(&args.runner)?;
+    let summaries = runtime::sweep::run(&points, |point| {
+        let mut options = args.build.clone();
+        options.max_seeds = options.max_seeds.min(args.point_max_seeds);
+        for (name, value) in point {
+            options = options.with(name, *value)?;
+        }
+        let code = match compile(&args.source, options) {
+            Ok(code) => code,
+            Err(err) if is_no_layout(&err) => {
+                return Ok(runtime::sweep::Summary {
+                    error: Some(format!("no layout: {err:#}")),
+                    ..Default::default()
+                })
+            }
+            Err(err) => return Err(err),
+        };
+        let quality = match code.layout {
+            Some(ref layout) => layout.quality.into(),
+            None => f64::NAN,
+        };
+        let sequence = code.sequence()?.bind(&Default::default())?;
+        match runtime::run::sample(&sequence, runner.as_ref()) {
+            Ok(samples) => runtime::sweep::Summary::from_samples(
+                &code.qubo,
+                code.normalization.as_ref(),
+                quality,
+                &samples,
+            ),
+            // Keep what we learnt from compilation.
+            Err(err) => Ok(runtime::sweep::Summary {
+                quality: Some(quality),
+                error: Some(format!("{err:#}")),
+                ..Default::default()
+            }),
+        }
+    });
+
+    let path_dest = args
+        .output
+        .unwrap_or_else(|| args.source.as_path().here_with_ext("sweep.csv"));
+    eprintln!("...generating {}", path_dest.display());
+    let out_dest = std::fs::File::create(path_dest)?;
+    runtime::sweep::write_csv(&args.vary, &points, &summaries, out_dest)?;
+    Ok(())
+}
+
+/// Whether `err` is caused by a layout search which ran out of seeds.
+fn is_no_layout(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<qubo::Error>(),
+            Some(qubo::Error::NoSolution { .. })
+        )
+    })
+}
+
+fn load_code(path: &Path) -> Result<Code, anyhow::Error> {
+    let input = std::fs::File::open(path).map_err(|source| qlafoutea::Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Ok(serde_json::from_reader(input).map_err(|err| qlafoutea::Error::json(path, err))?)
+}
+
+fn inspect(args: Inspect) -> Result<(), anyhow::Error> {
+    let code = load_code(&args.source)?;
+    runtime::inspect::inspect(&code, &mut std::io::stdout().lock())
+}
+
+fn plot(args: Plot) -> Result<(), anyhow::Error> {
+    let code = load_code(&args.source)?;
+    let sequence = code.sequence()?;
+
+    // Draw the blockade radius at the peak amplitude of the sequence.
+    let peak = sequence
+        .bind(&Default::default())?
+        .pulses()
+        .filter_map(|pulse| match *pulse.amplitude() {
+            Waveform::Interpolated { ref values, .. } => {
+                values.iter().filter_map(|v| v.as_value()).reduce(f64::max)
+            }
+            Waveform::Constant { ref value, .. } => value.as_value(),
+        })
+        .reduce(f64::max)
+        .filter(|peak| *peak > 0.);
+    let blockade_radius = peak.map(|peak| sequence.device().rydberg_blockade_radius(peak));
+
+    let path_register = args
+        .register
+        .unwrap_or_else(|| args.source.as_path().here_with_ext("register.svg"));
+    eprintln!("...generating {}", path_register.display());
+    std::fs::write(
+        &path_register,
+        plot::register::register(&code.register, sequence.device(), blockade_radius),
+    )?;
+
+    let path_pulses = args
+        .pulses
+        .unwrap_or_else(|| args.source.as_path().here_with_ext("pulses.svg"));
+    eprintln!("...generating {}", path_pulses.display());
+    std::fs::write(&path_pulses, plot::pulse::pulses(&sequence)?)?;
+    Ok(())
+}
+
+fn mock_server(args: MockServer) -> Result<(), anyhow::Error> {
+    let runner = runtime::runner::by_name(&args.runner)?;
+    let server = runtime::runner::remote::mock::MockServer::start(
+        &args.listen,
+        runner,
+        runtime::runner::remote::mock::Options {
+            token: args.token,
+            ..Default::default()
+        },
+    )?;
+    eprintln!("...serving jobs with {} on {}", args.runner, server.url());
+    server.wait();
+    Ok(())
+}
+
+fn migrate(args: Migrate) -> Result<(), anyhow::Error> {
+    for path in args.sources {
+        let input = std::fs::File::open(&path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        let value: serde_json::Value = serde_json::from_reader(input)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let version = Code::version_of(&value);
+        if version == format::VERSION {
+            eprintln!("...{} is already up to date", path.display());
+            continue;
+        }
+        let code = Code::from_value(value)
+            .with_context(|| format!("Failed to migrate {}", path.display()))?;
+        let out_dest = std::fs::File::create(&path)?;
+        serde_json::to_writer_pretty(out_dest, &code)?;
+        eprintln!(
+            "...migrated {} from version {} to version {}",
+            path.display(),
+            version,
+            format::VERSION
+        );
+    }
+    Ok(())
+}
+
+fn load_plugins(paths: &[PathBuf]) -> Result<(), anyhow::Error> {
+    for path in paths {
+        let plugin = Plugin::load(path)
+            .with_context(|| format!("Failed to load frontend plugin {}", path.display()))?;
+        eprintln!(
+            "...loaded problem type {} from {}",
+            Frontend::kind(&plugin),
+            path.display()
+        );
+        frontend::registry::register(Arc::new(plugin))
+            .with_context(|| format!("Failed to load frontend plugin {}", path.display()))?;
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), anyhow::Error> {
-    let args = Command::parse();
-    match args {
+    let cli = Cli::parse();
+    load_plugins(&cli.frontend_plugin)?;
+    if let Some(ref python) = cli.python {
+        runtime::runner::python::set_interpreter(python);
+    }
+    match cli.command {
         Command::Build(args) => build(args),
-        Command::Run(args) => run(args),
+        Command::Run(args) => run(args, &cli.frontend_plugin, cli.python.as_deref()),
+        Command::Solve(args) => solve(args),
+        Command::Sweep(args) => sweep(args),
+        Command::Inspect(args) => inspect(args),
+        Command::Plot(args) => plot(args),
+        Command::Migrate(args) => migrate(args),
+        Command::Runners => runtime::runner::list(&mut std::io::stdout().lock()),
+        Command::MockServer(args) => mock_server(args),
+        Command::Jobs(args) => jobs(args),
     }
 }
@@ -6,16 +6,14 @@ pub trait PathExt {
 
 impl PathExt for PathBuf {
     fn here_with_ext(&self, extension: &str) -> PathBuf {
-        let mut buf = PathBuf::new();
-        buf.set_file_name(self.file_name().unwrap());
-        buf.with_extension(extension)
+        self.as_path().here_with_ext(extension)
     }
 }
 
 impl PathExt for &Path {
     fn here_with_ext(&self, extension: &str) -> PathBuf {
-        let mut buf = PathBuf::new();
-        buf.set_file_name(self.file_name().unwrap());
-        buf.with_extension(extension)
+        // Paths such as `..` have no file name, fall back to a generic one.
+        let name = self.file_name().unwrap_or("out".as_ref());
+        PathBuf::from(name).with_extension(extension)
     }
 }
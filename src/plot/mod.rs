@@ -0,0 +1,104 @@
+//! SVG rendering of registers and pulse schedules.
+//!
+//! The output is standalone SVG, without scripts or external resources, so that it may
+//! be embedded in reports as-is.
+
+pub mod pulse;
+pub mod register;
+
+use std::fmt::Write;
+
+const FONT: &str = "font-family=\"sans-serif\"";
+
+/// A minimal SVG document builder.
+struct Svg {
+    width: f64,
+    height: f64,
+    body: String,
+}
+
+impl Svg {
+    fn new(width: f64, height: f64) -> Self {
+        Self {
+            width,
+            height,
+            body: String::new(),
+        }
+    }
+
+    fn circle(&mut self, cx: f64, cy: f64, r: f64, style: &str) {
+        let _ = writeln!(
+            self.body,
+            "<circle cx=\"{cx:.2}\" cy=\"{cy:.2}\" r=\"{r:.2}\" {style}/>"
+        );
+    }
+
+    fn line(&mut self, (x1, y1): (f64, f64), (x2, y2): (f64, f64), style: &str) {
+        let _ = writeln!(
+            self.body,
+            "<line x1=\"{x1:.2}\" y1=\"{y1:.2}\" x2=\"{x2:.2}\" y2=\"{y2:.2}\" {style}/>"
+        );
+    }
+
+    fn polyline(&mut self, points: &[(f64, f64)], style: &str) {
+        let _ = write!(self.body, "<polyline points=\"");
+        for (i, (x, y)) in points.iter().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            let _ = write!(self.body, "{sep}{x:.2},{y:.2}");
+        }
+        let _ = writeln!(self.body, "\" fill=\"none\" {style}/>");
+    }
+
+    /// Write `text` at (x, y). `anchor` is one of `start`, `middle`, `end`.
+    fn text(&mut self, x: f64, y: f64, anchor: &str, size: f64, text: &str) {
+        let _ = writeln!(
+            self.body,
+            "<text x=\"{x:.2}\" y=\"{y:.2}\" text-anchor=\"{anchor}\" font-size=\"{size}\" {FONT}>{}</text>",
+            escape(text)
+        );
+    }
+
+    fn finish(self) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">\n<rect width=\"{w}\" height=\"{h}\" fill=\"white\"/>\n{body}</svg>\n",
+            w = self.width,
+            h = self.height,
+            body = self.body
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Round tick positions covering [min, max], about `count` of them.
+fn ticks(min: f64, max: f64, count: usize) -> Vec<f64> {
+    let span = max - min;
+    if span <= 0. || !span.is_finite() {
+        return vec![min];
+    }
+    let raw = span / count as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .into_iter()
+        .map(|k| k * magnitude)
+        .find(|step| *step >= raw)
+        .unwrap_or(10. * magnitude);
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|k| k as f64 * step).collect()
+}
+
+#[test]
+fn test_ticks() {
+    assert_eq!(
+        ticks(0., 4_000., 4),
+        vec![0., 1_000., 2_000., 3_000., 4_000.]
+    );
+    assert_eq!(ticks(-5., 5., 4), vec![-5., 0., 5.]);
+    assert_eq!(escape("<a & b>"), "&lt;a &amp; b&gt;");
+}
@@ -0,0 +1,179 @@
+//! Rendering of pulse schedules.
+
+use std::collections::HashMap;
+
+use crate::{
+    backend::pulser::{
+        sequence::Sequence,
+        variable::{self, Parameter},
+        waveform::Waveform,
+    },
+    plot::Svg,
+};
+
+const WIDTH: f64 = 700.;
+const PANEL_HEIGHT: f64 = 200.;
+const MARGIN: f64 = 60.;
+
+const COLORS: [&str; 4] = ["darkgreen", "darkorange", "steelblue", "purple"];
+
+/// Render the amplitude and detuning of the pulses of `sequence`, one panel each, with
+/// one curve per channel.
+///
+/// Variables are replaced by their default value. Interpolated waveforms are drawn
+/// piecewise-linearly between their interpolation points.
+pub fn pulses(sequence: &Sequence) -> Result<String, variable::Error> {
+    let sequence = sequence.bind(&HashMap::new())?;
+
+    // Pulses on a channel are played one after the other.
+    let mut clocks = HashMap::<&str, f64>::new();
+    let mut amplitude = Vec::<(&str, Vec<(f64, f64)>)>::new();
+    let mut detuning = Vec::<(&str, Vec<(f64, f64)>)>::new();
+    for pulse in sequence.pulses() {
+        let channel = pulse.channel();
+        let start = clocks.get(channel).cloned().unwrap_or(0.);
+        let end = start + value(pulse.amplitude().duration());
+        clocks.insert(channel, end);
+        for (curves, waveform) in [
+            (&mut amplitude, pulse.amplitude()),
+            (&mut detuning, pulse.detuning()),
+        ] {
+            let index = match curves.iter().position(|(c, _)| *c == channel) {
+                Some(index) => index,
+                None => {
+                    curves.push((channel, vec![]));
+                    curves.len() - 1
+                }
+            };
+            curves[index].1.extend(samples(waveform, start));
+        }
+    }
+    let duration = clocks.values().cloned().fold(0., f64::max);
+
+    let mut svg = Svg::new(WIDTH + 2. * MARGIN, 2. * PANEL_HEIGHT + 3. * MARGIN);
+    panel(
+        &mut svg,
+        MARGIN,
+        "amplitude (rad/µs)",
+        &amplitude,
+        duration,
+        false,
+    );
+    panel(
+        &mut svg,
+        2. * MARGIN + PANEL_HEIGHT,
+        "detuning (rad/µs)",
+        &detuning,
+        duration,
+        true,
+    );
+    Ok(svg.finish())
+}
+
+fn value(parameter: &Parameter) -> f64 {
+    // The sequence is bound, so all parameters are values.
+    parameter.as_value().unwrap_or_default()
+}
+
+/// The points of `waveform`, starting at `start` ns.
+fn samples(waveform: &Waveform, start: f64) -> Vec<(f64, f64)> {
+    match *waveform {
+        Waveform::Interpolated {
+            ref duration,
+            ref times,
+            ref values,
+        } => times
+            .iter()
+            .zip(values.iter())
+            .map(|(t, v)| (start + t * value(duration), value(v)))
+            .collect(),
+        Waveform::Constant {
+            ref duration,
+            value: ref v,
+        } => vec![(start, value(v)), (start + value(duration), value(v))],
+    }
+}
+
+fn panel(
+    svg: &mut Svg,
+    top: f64,
+    title: &str,
+    curves: &[(&str, Vec<(f64, f64)>)],
+    duration: f64,
+    time_axis: bool,
+) {
+    let values = curves
+        .iter()
+        .flat_map(|(_, points)| points.iter().map(|p| p.1));
+    let (min, max) = values.fold((0f64, 0f64), |(min, max), v| (min.min(v), max.max(v)));
+    let (min, max) = if max - min <= 0. {
+        (min - 1., max + 1.)
+    } else {
+        let pad = 0.05 * (max - min);
+        (min - pad, max + pad)
+    };
+    let duration = if duration > 0. { duration } else { 1. };
+    let to_px = |t: f64, v: f64| {
+        (
+            MARGIN + t / duration * WIDTH,
+            top + (max - v) / (max - min) * PANEL_HEIGHT,
+        )
+    };
+
+    // Axes and grid.
+    let axis = "stroke=\"black\"";
+    let grid = "stroke=\"lightgray\" stroke-dasharray=\"2 3\"";
+    svg.line(to_px(0., min), to_px(0., max), axis);
+    svg.line(to_px(0., min), to_px(duration, min), axis);
+    for tick in super::ticks(min, max, 4) {
+        let (x, y) = to_px(0., tick);
+        svg.line((x, y), to_px(duration, tick), grid);
+        svg.text(x - 6., y + 4., "end", 11., &format!("{tick}"));
+    }
+    for tick in super::ticks(0., duration, 8) {
+        let (x, y) = to_px(tick, min);
+        svg.line((x, y), (x, y + 4.), axis);
+        if time_axis {
+            svg.text(x, y + 16., "middle", 11., &format!("{tick}"));
+        }
+    }
+    if time_axis {
+        let (x, y) = to_px(duration / 2., min);
+        svg.text(x, y + 34., "middle", 12., "time (ns)");
+    }
+    svg.text(MARGIN, top - 10., "start", 13., title);
+
+    for (i, (channel, points)) in curves.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let points = points
+            .iter()
+            .map(|(t, v)| to_px(*t, *v))
+            .collect::<Vec<_>>();
+        svg.polyline(&points, &format!("stroke=\"{color}\" stroke-width=\"2\""));
+        svg.text(
+            MARGIN + WIDTH,
+            top - 10. + 14. * i as f64,
+            "end",
+            11.,
+            channel,
+        );
+    }
+}
+
+#[test]
+fn test_pulses() {
+    use crate::backend::pulser::{sequence::fixture, variable::Variable};
+
+    let sequence = fixture::sequence(
+        fixture::atoms(&[("a", 0., 0.)]),
+        Waveform::parametrized(
+            Parameter::Variable("duration".into()),
+            &[0f64.into(), 1f64.into(), 0f64.into()],
+        ),
+        Waveform::interpolated(1_000., &[-1., 0., 1.]),
+    )
+    .with_variables(vec![Variable::int("duration", 1_000.)]);
+    let svg = pulses(&sequence).unwrap();
+    assert_eq!(svg.matches("<polyline").count(), 2);
+    assert!(svg.contains("time (ns)"));
+}
@@ -0,0 +1,113 @@
+//! Rendering of registers.
+
+use crate::{
+    backend::{device::Device, pulser::register::Register},
+    plot::Svg,
+};
+
+/// The size of the drawing, in px, excluding margins.
+const SIZE: f64 = 500.;
+const MARGIN: f64 = 40.;
+
+/// Render the atoms of `register`, as placed on `device`.
+///
+/// If `blockade_radius` is specified, each atom is surrounded by a circle of half this
+/// radius, so that two atoms are within the blockade radius of each other if and only if
+/// their circles overlap. The boundary of the device and its pre-calibrated traps are
+/// drawn when known.
+pub fn register(register: &Register, device: &Device, blockade_radius: Option<f64>) -> String {
+    let atoms = register
+        .coordinates
+        .iter()
+        .map(|(c, name)| (c.x.into_inner(), c.y.into_inner(), name.as_ref()))
+        .collect::<Vec<_>>();
+    let traps = device
+        .pre_calibrated_layouts()
+        .first()
+        .map(|layout| layout.coordinates.to_vec())
+        .unwrap_or_default();
+
+    // The half-width of the area to draw, in µm.
+    let halo = blockade_radius.unwrap_or(0.) / 2.;
+    let extent = atoms
+        .iter()
+        .map(|(x, y, _)| f64::max(x.abs(), y.abs()) + halo)
+        .chain(traps.iter().map(|[x, y]| f64::max(x.abs(), y.abs())))
+        .chain(device.max_radial_distance().map(f64::from))
+        .fold(1f64, f64::max)
+        * 1.1;
+    let scale = SIZE / (2. * extent);
+    let to_px = |x: f64, y: f64| (MARGIN + (x + extent) * scale, MARGIN + (extent - y) * scale);
+
+    let mut svg = Svg::new(SIZE + 2. * MARGIN, SIZE + 2. * MARGIN);
+    if let Some(radius) = device.max_radial_distance() {
+        let (cx, cy) = to_px(0., 0.);
+        svg.circle(
+            cx,
+            cy,
+            f64::from(radius) * scale,
+            "fill=\"none\" stroke=\"gray\" stroke-dasharray=\"6 4\"",
+        );
+    }
+    for [x, y] in &traps {
+        let (cx, cy) = to_px(*x, *y);
+        svg.circle(cx, cy, 2., "fill=\"lightgray\"");
+    }
+    if let Some(radius) = blockade_radius {
+        for (x, y, _) in &atoms {
+            let (cx, cy) = to_px(*x, *y);
+            svg.circle(
+                cx,
+                cy,
+                radius / 2. * scale,
+                "fill=\"steelblue\" fill-opacity=\"0.15\" stroke=\"steelblue\" stroke-opacity=\"0.5\"",
+            );
+        }
+    }
+    for (x, y, name) in &atoms {
+        let (cx, cy) = to_px(*x, *y);
+        svg.circle(cx, cy, 5., "fill=\"darkred\"");
+        svg.text(cx + 7., cy - 7., "start", 12., name);
+    }
+
+    // Scale bar.
+    let bar = super::ticks(0., extent, 3).get(1).cloned().unwrap_or(1.);
+    let y = SIZE + 1.5 * MARGIN;
+    svg.line(
+        (MARGIN, y),
+        (MARGIN + bar * scale, y),
+        "stroke=\"black\" stroke-width=\"2\"",
+    );
+    svg.text(
+        MARGIN + bar * scale + 6.,
+        y + 4.,
+        "start",
+        12.,
+        &format!("{bar} µm"),
+    );
+    let mut title = device.name().to_string();
+    if let Some(radius) = blockade_radius {
+        title.push_str(&format!(", blockade radius {radius:.2} µm"));
+    }
+    svg.text(MARGIN, MARGIN / 2., "start", 14., &title);
+    svg.finish()
+}
+
+#[test]
+fn test_register() {
+    use crate::types::units::Coordinates;
+
+    let register = Register {
+        coordinates: vec![
+            (Coordinates::new(0., 0.), "a".into()),
+            (Coordinates::new(6., 0.), "<b>".into()),
+        ]
+        .into(),
+    };
+    let device = Device::analog();
+    let svg = self::register(&register, &device, Some(8.));
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.contains("&lt;b&gt;"));
+    // Boundary, 61 traps, 2 blockade circles, 2 atoms.
+    assert_eq!(svg.matches("<circle").count(), 1 + 61 + 2 + 2);
+}
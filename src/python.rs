@@ -0,0 +1,576 @@
+//! Python bindings, built with the `python` feature.
+//!
+//! Build the extension module with `maturin develop` (see `pyproject.toml`), then:
+//!
+//! ```python
+//! import qlafoutea
+//!
+//! problem = qlafoutea.load_problem("problem.yaml")
+//! constraints = problem.to_constraints()
+//! layout = constraints.layout(device="AnalogDevice", seed=0)
+//! sequence, report = qlafoutea.qaa_compile(constraints, layout.register)
+//! pulser_sequence = sequence.to_pulser()
+//! samples = qlafoutea.run(sequence, runner="py-pulser", shots=500)
+//! ```
+//!
+//! Errors are raised as `qlafoutea.QlafouteaError`, warnings about source files as
+//! `UserWarning`.
+
+use std::path::{Path, PathBuf};
+
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyUserWarning},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::{
+    backend::{
+        analysis::Acceptance,
+        device::Device,
+        pulser::{register::Register, sequence::Sequence},
+        qaa,
+        qubo::{self, Constraints},
+    },
+    frontend::{self, diagnostics::Diagnostic, Input},
+    runtime::runner,
+    types::Quality,
+};
+
+create_exception!(qlafoutea, QlafouteaError, PyException);
+
+/// Raise a `QlafouteaError`, with the chain of causes.
+fn error(err: impl Into<anyhow::Error>) -> PyErr {
+    QlafouteaError::new_err(format!("{:#}", err.into()))
+}
+
+/// A built-in device, by name, or a device in Pulser's JSON format.
+fn device(spec: &str) -> PyResult<Device> {
+    match Device::preset(spec) {
+        Some(device) => Ok(device),
+        None => Device::from_json(spec.as_bytes()).map_err(|err| {
+            error(anyhow::Error::from(err).context(format!(
+                "expected one of {} or a JSON device specification",
+                Device::PRESETS.join(", ")
+            )))
+        }),
+    }
+}
+
+fn warn(py: Python<'_>, path: &Path, warnings: &[Diagnostic]) -> PyResult<()> {
+    for warning in warnings {
+        PyErr::warn_bound(
+            py,
+            &py.get_type_bound::<PyUserWarning>(),
+            &warning.render(path),
+            1,
+        )?;
+    }
+    Ok(())
+}
+
+/// QUBO constraints: a symmetric matrix of weights, with one named variable per row.
+#[pyclass(name = "Constraints", module = "qlafoutea")]
+#[derive(Clone)]
+pub struct PyConstraints(Constraints);
+
+#[pymethods]
+impl PyConstraints {
+    #[new]
+    #[pyo3(signature = (weights, names = None))]
+    fn new(weights: Vec<Vec<f64>>, names: Option<Vec<String>>) -> PyResult<Self> {
+        let num_nodes = weights.len();
+        if weights.iter().any(|row| row.len() != num_nodes) {
+            return Err(error(anyhow::anyhow!(
+                "expected {num_nodes} rows of {num_nodes} weights"
+            )));
+        }
+        let names = names.unwrap_or_else(|| (0..num_nodes).map(|i| format!("x{i}")).collect());
+        let names = names.into_iter().map(Into::into).collect();
+        let data = weights.into_iter().flatten().collect();
+        Constraints::try_new(num_nodes, data, names)
+            .map(Self)
+            .ok_or_else(|| error(anyhow::anyhow!("expected one name per row of weights")))
+    }
+
+    #[getter]
+    fn num_nodes(&self) -> usize {
+        self.0.num_nodes()
+    }
+
+    #[getter]
+    fn names(&self) -> Vec<String> {
+        self.0.names().iter().map(|name| name.to_string()).collect()
+    }
+
+    fn weight(&self, i: usize, j: usize) -> PyResult<f64> {
+        self.0.at(i, j).map_err(error)
+    }
+
+    /// The energy of a string of `0` and `1`, one per variable.
+    fn energy(&self, bitstring: &str) -> PyResult<f64> {
+        self.0.energy(bitstring).map_err(error)
+    }
+
+    /// The bitstrings of minimal energy, by exhaustive search.
+    fn ground_states(&self) -> PyResult<Vec<String>> {
+        self.0.ground_states().map_err(error)
+    }
+
+    /// Scale the weights by the largest factor which fits the device, as `(constraints,
+    /// factor)`. Divide energies of the scaled constraints by `factor` for problem units.
+    #[pyo3(signature = (device = "AnalogDevice"))]
+    fn normalize(&self, device: &str) -> PyResult<(Self, f64)> {
+        let normalization = self.0.normalization(&self::device(device)?);
+        Ok((
+            Self(self.0.scaled(normalization.factor)),
+            normalization.factor,
+        ))
+    }
+
+    /// Place one atom per variable, so that their interactions match the weights.
+    ///
+    /// Besides `min_quality`, layouts may be required to match each weight within
+    /// `max_relative_error`, all weights within `max_mean_relative_error` on average, to stay
+    /// `min_overflow_margin` µm from the edge of the device and to keep atoms `min_spacing`
+    /// µm apart. Up to `max_seeds` seeds are tried, starting from `seed`.
+    #[pyo3(signature = (
+        device = "AnalogDevice",
+        seed = 0,
+        min_quality = 0.2,
+        max_iters = 4_000,
+        max_seeds = 10_000,
+        max_relative_error = None,
+        max_mean_relative_error = None,
+        min_overflow_margin = None,
+        min_spacing = None,
+    ))]
+    #[allow(clippy::too_many_arguments)]
+    fn layout(
+        &self,
+        py: Python<'_>,
+        device: &str,
+        seed: u64,
+        min_quality: f64,
+        max_iters: u64,
+        max_seeds: u64,
+        max_relative_error: Option<f64>,
+        max_mean_relative_error: Option<f64>,
+        min_overflow_margin: Option<f64>,
+        min_spacing: Option<f64>,
+    ) -> PyResult<PyLayout> {
+        let device = self::device(device)?;
+        let options = qubo::Options {
+            seed,
+            min_quality: Quality::new(min_quality).map_err(error)?,
+            max_iters,
+            max_seeds,
+            acceptance: Acceptance {
+                max_relative_error,
+                max_mean_relative_error,
+                min_overflow_margin_um: min_overflow_margin,
+                min_spacing_um: min_spacing,
+            },
+            ..Default::default()
+        };
+        let layout = py
+            .allow_threads(|| self.0.layout(&device, &options))
+            .map_err(error)?;
+        let report = serde_json::to_string(&layout.report).map_err(error)?;
+        Ok(PyLayout {
+            register: PyRegister(layout.register),
+            quality: layout.quality.into(),
+            seed: layout.seed,
+            report: py
+                .import_bound("json")?
+                .call_method1("loads", (report,))?
+                .unbind(),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Constraints(names={:?})", self.names())
+    }
+}
+
+/// The result of `Constraints.layout`.
+#[pyclass(name = "Layout", module = "qlafoutea", get_all)]
+#[derive(Clone)]
+pub struct PyLayout {
+    register: PyRegister,
+
+    /// How well the interactions match the weights, in [0, 1].
+    quality: f64,
+
+    /// The seed which produced this layout.
+    seed: u64,
+
+    /// Concrete measures of quality, e.g. the relative error of each coupling, as a dict.
+    report: PyObject,
+}
+
+/// Atoms, with their names and coordinates in µm.
+#[pyclass(name = "Register", module = "qlafoutea")]
+#[derive(Clone)]
+pub struct PyRegister(Register);
+
+#[pymethods]
+impl PyRegister {
+    /// `(name, x, y)` for each atom.
+    #[getter]
+    fn atoms(&self) -> Vec<(String, f64, f64)> {
+        self.0
+            .coordinates
+            .iter()
+            .map(|(c, name)| (name.to_string(), c.x.into_inner(), c.y.into_inner()))
+            .collect()
+    }
+
+    /// The register as a `pulser.Register`.
+    fn to_pulser<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        let qubits = pyo3::types::PyDict::new_bound(py);
+        for (name, x, y) in self.atoms() {
+            qubits.set_item(name, (x, y))?;
+        }
+        py.import_bound("pulser")?
+            .getattr("Register")?
+            .call1((qubits,))
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.len()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Register({} atoms)", self.0.len())
+    }
+}
+
+/// A sequence, in Pulser's abstract representation.
+#[pyclass(name = "Sequence", module = "qlafoutea")]
+#[derive(Clone)]
+pub struct PySequence {
+    // Sequences are not `Send`, so we keep their JSON.
+    value: serde_json::Value,
+}
+
+impl PySequence {
+    fn new(sequence: &Sequence) -> PyResult<Self> {
+        Ok(Self {
+            value: serde_json::to_value(sequence).map_err(error)?,
+        })
+    }
+
+    fn sequence(&self) -> PyResult<Sequence> {
+        Sequence::deserialize(&self.value).map_err(error)
+    }
+}
+
+#[pymethods]
+impl PySequence {
+    /// Read a sequence in Pulser's abstract representation, e.g. from
+    /// `pulser.Sequence.to_abstract_repr()`.
+    #[staticmethod]
+    fn from_json(source: &str) -> PyResult<Self> {
+        let value: serde_json::Value = serde_json::from_str(source).map_err(error)?;
+        let sequence = Sequence::deserialize(&value).map_err(error)?;
+        Self::new(&sequence)
+    }
+
+    fn to_json(&self) -> String {
+        self.value.to_string()
+    }
+
+    /// The sequence as a `pulser.Sequence`.
+    fn to_pulser<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        py.import_bound("pulser")?
+            .getattr("Sequence")?
+            .call_method1("from_abstract_repr", (self.to_json(),))
+    }
+
+    /// The names of the variables declared by the sequence.
+    #[getter]
+    fn variables(&self) -> PyResult<Vec<String>> {
+        Ok(self
+            .sequence()?
+            .variables()
+            .iter()
+            .map(|variable| variable.name.to_string())
+            .collect())
+    }
+
+    #[getter]
+    fn register(&self) -> PyResult<PyRegister> {
+        Ok(PyRegister(self.sequence()?.register().clone()))
+    }
+
+    fn __repr__(&self) -> PyResult<String> {
+        let sequence = self.sequence()?;
+        Ok(format!(
+            "Sequence({} atoms, {} pulses)",
+            sequence.register().len(),
+            sequence.pulses().count()
+        ))
+    }
+}
+
+/// A problem, of any of the registered types.
+#[pyclass(name = "Problem", module = "qlafoutea")]
+#[derive(Clone)]
+pub struct PyProblem(Input);
+
+#[pymethods]
+impl PyProblem {
+    /// The value of the `type` tag of the problem, e.g. `max3sat`.
+    #[getter]
+    fn kind(&self) -> String {
+        self.0.kind().to_string()
+    }
+
+    fn to_constraints(&self) -> PyResult<PyConstraints> {
+        self.0.to_constraints().map(PyConstraints).map_err(error)
+    }
+
+    /// Whether a sample, in the order of the QUBO variables, is a solution.
+    fn validate_solution(&self, bitstring: &str) -> PyResult<bool> {
+        self.0.validate_solution(bitstring).map_err(error)
+    }
+
+    /// The problem as JSON, including its `type` tag.
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.0).map_err(error)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Problem({})", self.0.kind())
+    }
+}
+
+/// Read a problem from a YAML source file.
+#[pyfunction]
+fn load_problem(py: Python<'_>, path: PathBuf) -> PyResult<PyProblem> {
+    let (input, warnings) = Input::load(&path).map_err(error)?;
+    warn(py, &path, &warnings)?;
+    Ok(PyProblem(input))
+}
+
+/// Read a problem from the contents of a YAML source file.
+#[pyfunction]
+fn parse_problem(py: Python<'_>, source: &str) -> PyResult<PyProblem> {
+    let path = Path::new("<string>");
+    match frontend::diagnostics::check(source) {
+        (Some(input), warnings) => {
+            warn(py, path, &warnings)?;
+            Ok(PyProblem(input))
+        }
+        (None, diagnostics) => Err(error(crate::Error::Diagnostics {
+            path: path.to_path_buf(),
+            diagnostics,
+        })),
+    }
+}
+
+/// The values of the `type` tag of the registered problem types.
+#[pyfunction]
+fn problem_types() -> Vec<String> {
+    frontend::registry::kinds()
+}
+
+/// Compile to a single adiabatic sweep, returning the sequence and how its parameters were
+/// chosen. With `derive_sweep`, the detuning range and, unless given, the duration are
+/// derived from the problem and the device.
+#[pyfunction]
+#[pyo3(signature = (constraints, register, device = "AnalogDevice", half_duration_ns = None, parametrize = false, derive_sweep = false))]
+fn qaa_compile<'py>(
+    py: Python<'py>,
+    constraints: &PyConstraints,
+    register: &PyRegister,
+    device: &str,
+    half_duration_ns: Option<f64>,
+    parametrize: bool,
+    derive_sweep: bool,
+) -> PyResult<(PySequence, Bound<'py, PyAny>)> {
+    let options = qaa::Options {
+        half_duration_ns,
+        derive_sweep,
+        parametrize,
+        schedule: qaa::Schedule::default(),
+    };
+    let (sequence, report) = qaa::compile(
+        &constraints.0,
+        self::device(device)?,
+        register.0.clone(),
+        &options,
+    )
+    .map_err(error)?;
+    let report = serde_json::to_string(&report).map_err(error)?;
+    let report = py.import_bound("json")?.call_method1("loads", (report,))?;
+    Ok((PySequence::new(&sequence)?, report))
+}
+
+/// The names of the available runners.
+///
+/// If the configuration file cannot be loaded, only the built-in runners.
+#[pyfunction]
+fn runners() -> Vec<String> {
+    let (runners, err) = runner::available();
+    if let Some(err) = err {
+        eprintln!(
+            "...ignoring the configuration file: {:#}",
+            anyhow::Error::from(err)
+        );
+    }
+    runners
+        .iter()
+        .map(|runner| runner.name().to_string())
+        .collect()
+}
+
+/// Sample a sequence, returning `(bitstring, instances)` by decreasing number of instances.
+#[pyfunction]
+#[pyo3(signature = (sequence, runner = "py-pulser", shots = runner::DEFAULT_SHOTS, seed = None))]
+fn run(
+    py: Python<'_>,
+    sequence: &PySequence,
+    runner: &str,
+    shots: u64,
+    seed: Option<u64>,
+) -> PyResult<Vec<(String, u64)>> {
+    let runner = runner::by_name(runner).map_err(error)?;
+    let value = sequence.value.clone();
+    let output = py
+        .allow_threads(|| -> Result<runner::Output, anyhow::Error> {
+            let sequence = Sequence::deserialize(&value)?;
+            runner::check(runner.as_ref(), &sequence)?;
+            Ok(runner.run(&sequence, &runner::Options { shots, seed })?)
+        })
+        .map_err(error)?;
+    Ok(output
+        .samples
+        .into_iter()
+        .map(|sample| (sample.bitstring, sample.instances))
+        .collect())
+}
+
+#[pymodule]
+fn qlafoutea(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    // Run Pulser with the interpreter which imported us.
+    let executable: Option<PathBuf> = m
+        .py()
+        .import_bound("sys")?
+        .getattr("executable")?
+        .extract()?;
+    if let Some(executable) = executable.filter(|path| !path.as_os_str().is_empty()) {
+        runner::python::set_interpreter(&executable);
+    }
+
+    m.add("QlafouteaError", m.py().get_type_bound::<QlafouteaError>())?;
+    m.add_class::<PyConstraints>()?;
+    m.add_class::<PyLayout>()?;
+    m.add_class::<PyRegister>()?;
+    m.add_class::<PySequence>()?;
+    m.add_class::<PyProblem>()?;
+    m.add_function(wrap_pyfunction!(load_problem, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_problem, m)?)?;
+    m.add_function(wrap_pyfunction!(problem_types, m)?)?;
+    m.add_function(wrap_pyfunction!(qaa_compile, m)?)?;
+    m.add_function(wrap_pyfunction!(runners, m)?)?;
+    m.add_function(wrap_pyfunction!(run, m)?)?;
+    Ok(())
+}
+
+#[test]
+fn test_bindings() {
+    use pyo3::types::IntoPyDict;
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let weights = vec![vec![-1., 2.], vec![2., -1.]];
+        let constraints = PyConstraints::new(weights.clone(), None).unwrap();
+        assert_eq!(constraints.names(), vec!["x0", "x1"]);
+        let raised = |result: PyResult<()>| matches!(result, Err(err) if err.is_instance_of::<QlafouteaError>(py));
+        assert!(raised(
+            PyConstraints::new(vec![vec![-1., 2.], vec![2.]], None).map(|_| ())
+        ));
+        assert!(raised(
+            PyConstraints::new(weights, Some(vec!["a".into()])).map(|_| ())
+        ));
+
+        let layout = constraints
+            .layout(
+                py,
+                "AnalogDevice",
+                0,
+                0.2,
+                4_000,
+                100,
+                None,
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(layout.register.__len__(), 2);
+        assert!(layout.quality >= 0.2);
+        let (sequence, report) = qaa_compile(
+            py,
+            &constraints,
+            &layout.register,
+            "AnalogDevice",
+            None,
+            false,
+            false,
+        )
+        .unwrap();
+        sequence.sequence().unwrap().to_validated_json().unwrap();
+        assert_eq!(
+            report
+                .get_item("duration_ns")
+                .unwrap()
+                .extract::<f64>()
+                .unwrap(),
+            qaa::DEFAULT_DURATION_NS
+        );
+        assert!(raised(
+            constraints
+                .layout(
+                    py,
+                    "NoSuchDevice",
+                    0,
+                    0.2,
+                    4_000,
+                    100,
+                    None,
+                    None,
+                    None,
+                    None
+                )
+                .map(|_| ())
+        ));
+
+        // The same, from Python, with the default arguments.
+        let module = PyModule::new_bound(py, "qlafoutea").unwrap();
+        qlafoutea(&module).unwrap();
+        let locals = [("qlafoutea", module)].into_py_dict_bound(py);
+        py.run_bound(
+            r#"
+constraints = qlafoutea.Constraints([[-1, 2], [2, -1]], names=["a", "b"])
+assert sorted(constraints.ground_states()) == ["01", "10"]
+layout = constraints.layout(max_seeds=100)
+assert len(layout.register) == 2
+assert "max_relative_error" in layout.report, layout.report
+sequence, report = qlafoutea.qaa_compile(constraints, layout.register)
+assert report["delta_f"] == 5, report
+try:
+    constraints.layout(min_spacing=1000, max_seeds=2)
+    assert False, "expected an error"
+except qlafoutea.QlafouteaError as err:
+    assert "failed to layout within 2 seeds" in str(err), err
+"#,
+            None,
+            Some(&locals),
+        )
+        .unwrap();
+    });
+}
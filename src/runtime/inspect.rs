@@ -0,0 +1,206 @@
+//! Human-readable summary of a compiled file.
+
+use std::io::Write;
+
+use anyhow::Context;
+
+use crate::backend::{analysis::LayoutReport, format::Code, pulser::operation::Operation};
+
+/// Print a summary of `code`: the problem, how each variable was laid out, how well the
+/// interactions between atoms match the QUBO, the pulses and the device.
+pub fn inspect(code: &Code, out: &mut impl Write) -> Result<(), anyhow::Error> {
+    let sequence = code.sequence().context("Invalid sequence")?;
+    let device = sequence.device();
+    let qubo = &code.qubo;
+    let register = &code.register;
+
+    writeln!(out, "# Artifact")?;
+    writeln!(out, "format version: {}", code.version)?;
+    writeln!(out, "compiler: {}", code.compiler)?;
+    writeln!(out, "problem: {}", code.problem.kind())?;
+    match code.layout {
+        Some(ref layout) => writeln!(
+            out,
+            "layout: quality {} (seed {})",
+            layout.quality, layout.seed
+        )?,
+        None => writeln!(out, "layout: unknown (migrated from an older version)")?,
+    }
+    if let Some(ref normalization) = code.normalization {
+        writeln!(
+            out,
+            "normalization: QUBO scaled by {:.4} rad/µs per problem unit",
+            normalization.factor
+        )?;
+        for line in &normalization.reasoning {
+            writeln!(out, "  {line}")?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "# Register")?;
+    writeln!(
+        out,
+        "{:>5}  {:<12} {:>10} {:>10}",
+        "var", "atom", "x (µm)", "y (µm)"
+    )?;
+    for (i, (coordinates, name)) in register.coordinates.iter().enumerate() {
+        writeln!(
+            out,
+            "{:>5}  {:<12} {:>10.3} {:>10.3}",
+            i,
+            name,
+            coordinates.x.into_inner(),
+            coordinates.y.into_inner()
+        )?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "# Interactions (rad/µs)")?;
+    writeln!(
+        out,
+        "{:<12} {:<12} {:>10} {:>12} {:>12} {:>10}",
+        "atom", "atom", "dist (µm)", "actual", "qubo", "error"
+    )?;
+    let mut max_error = 0f64;
+    for i in 0..register.len() {
+        for j in i + 1..register.len() {
+            let (first, first_name) = &register.coordinates[i];
+            let (second, second_name) = &register.coordinates[j];
+            let actual = device.interaction(*first, *second);
+            let expected = qubo.at(i, j)?;
+            let error = actual - expected;
+            max_error = max_error.max(error.abs());
+            writeln!(
+                out,
+                "{:<12} {:<12} {:>10.3} {:>12.4} {:>12.4} {:>+10.4}",
+                first_name,
+                second_name,
+                first.sqdist(second).sqrt().into_inner(),
+                actual,
+                expected,
+                error
+            )?;
+        }
+    }
+    writeln!(out, "max absolute error: {max_error:.4}")?;
+    let report = LayoutReport::new(qubo, register, device)?;
+    for line in report.to_string().lines() {
+        writeln!(out, "{line}")?;
+    }
+
+    if let Some(ref analysis) = code.ground_states {
+        writeln!(out)?;
+        writeln!(out, "# Ground states")?;
+        writeln!(out, "final detuning: {:.3} rad/µs", analysis.detuning)?;
+        writeln!(out, "energies count each pair of atoms or variables once")?;
+        writeln!(
+            out,
+            "rydberg: {} (energy {:.4} rad/µs)",
+            analysis.rydberg.join(", "),
+            analysis.rydberg_energy
+        )?;
+        match code.normalization {
+            Some(ref normalization) => writeln!(
+                out,
+                "qubo: {} (value {:.4}, {:.4} in problem units)",
+                analysis.qubo.join(", "),
+                analysis.qubo_energy,
+                normalization.to_problem_units(analysis.qubo_energy)
+            )?,
+            None => writeln!(
+                out,
+                "qubo: {} (value {:.4})",
+                analysis.qubo.join(", "),
+                analysis.qubo_energy
+            )?,
+        }
+        writeln!(out, "{}", analysis.summary(code.normalization.as_ref()))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "# Pulses")?;
+    for (i, op) in sequence.operations().iter().enumerate() {
+        match **op {
+            Operation::Pulse(ref pulse) => {
+                writeln!(out, "{i}. channel {}", pulse.channel())?;
+                writeln!(out, "   amplitude (rad/µs): {}", pulse.amplitude())?;
+                writeln!(out, "   detuning (rad/µs): {}", pulse.detuning())?;
+            }
+            Operation::Other(ref value) => writeln!(out, "{i}. {} {value}", op.name())?,
+        }
+    }
+    for variable in sequence.variables() {
+        writeln!(
+            out,
+            "variable ${} ({}) = {}",
+            variable.name, variable.kind, variable.default
+        )?;
+    }
+    if let Some(ref report) = code.qaa {
+        for line in &report.reasoning {
+            writeln!(out, "- {line}")?;
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "# Device")?;
+    writeln!(out, "name: {}", device.name())?;
+    writeln!(out, "rydberg level: {}", device.rydberg_level())?;
+    if let Some(radius) = device.max_radial_distance() {
+        writeln!(out, "max radial distance: {radius} µm")?;
+    }
+    writeln!(out, "min atom distance: {} µm", device.min_atom_distance())?;
+    for channel in device.channels() {
+        let limit = |value: Option<f64>| match value {
+            Some(value) => format!("{value:.3}"),
+            None => "unlimited".to_string(),
+        };
+        writeln!(
+            out,
+            "channel {}: max amplitude {} rad/µs, max detuning {} rad/µs",
+            channel.id.0,
+            limit(channel.max_amp),
+            limit(channel.max_abs_detuning)
+        )?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_inspect() {
+    use crate::{
+        backend::{
+            format::Layout,
+            pulser::sequence::fixture,
+            qubo::{self, Constraints},
+        },
+        frontend::Input,
+        types::Quality,
+    };
+
+    let qubo = Constraints::from_const([[-1., 2.], [2., -1.]], vec!["a".into(), "b".into()]);
+    let sequence = fixture::sweep(fixture::atoms(&[("a", 0., 0.), ("b", 10., 0.)]));
+    let code = Code::try_new(
+        Input::new(qubo.clone()),
+        qubo,
+        &sequence,
+        Layout {
+            quality: Quality::new(0.5).unwrap(),
+            seed: 0,
+            options: qubo::Options::default(),
+        },
+    )
+    .unwrap();
+
+    let mut out = vec![];
+    inspect(&code, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    // C6 / 10^6 = 0.8657 rad/µs vs an expected 2.
+    let line = out.lines().find(|line| line.starts_with("a ")).unwrap();
+    assert!(
+        line.contains("0.8657") && line.contains("-1.1343"),
+        "{line}"
+    );
+    assert!(out.contains("name: AnalogDevice"));
+}
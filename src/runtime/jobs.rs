@@ -0,0 +1,545 @@
+//! Runs which proceed in the background, and their results.
+//!
+//! Each job is a JSON record in the job directory, see [`Store`]. Jobs on asynchronous
+//! runners, e.g. remote QPUs, are submitted to the runner and refreshed when checked upon.
+//! Jobs on other runners are executed by a separate process, see [`Store::work`], which
+//! writes the results to the record.
+//!
+//! Records are read, modified and written back under the lock of the directory, see
+//! [`Store::update`], so that e.g. a cancellation is not overwritten by the results of the
+//! job it cancels.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::format::{self, Code},
+    backend::pulser::variable,
+    config::Config,
+    runtime::{
+        run::Sample,
+        runner::{self, JobId, JobState, Status},
+    },
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("cannot access {0}")]
+    Io(PathBuf, #[source] std::io::Error),
+
+    #[error("invalid job record {0}")]
+    Record(PathBuf, #[source] serde_json::Error),
+
+    #[error("no job `{0}`")]
+    NotFound(String),
+
+    #[error("cannot tell where to store jobs, please set `jobs_dir` in the configuration")]
+    NoDirectory,
+
+    #[error("cannot load {0}")]
+    Code(PathBuf, #[source] crate::Error),
+
+    #[error("invalid sequence")]
+    Sequence(#[from] format::Error),
+
+    #[error("cannot assign sequence variables")]
+    Variables(#[from] variable::Error),
+
+    #[error(transparent)]
+    Runner(#[from] runner::Error),
+
+    #[error(transparent)]
+    Config(#[from] crate::config::Error),
+}
+
+/// How a job runs, as given to `run`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JobOptions {
+    pub runner: String,
+    pub shots: u64,
+    pub seed: Option<u64>,
+
+    /// Values for the variables declared by the sequence.
+    #[serde(default)]
+    pub variables: BTreeMap<String, f64>,
+
+    /// Which results are reported, see `run::Options`.
+    pub result_sample_threshold: f64,
+}
+
+/// A job, as recorded in the job directory.
+#[derive(Serialize, Deserialize)]
+pub struct Record {
+    pub id: String,
+
+    /// The compiled file, as an absolute path.
+    pub source: PathBuf,
+
+    /// A copy of the compiled file, as submitted, so that the job neither runs nor reports
+    /// a file changed or removed since.
+    pub code: Code,
+    pub options: JobOptions,
+
+    /// The id of the job on the runner, for asynchronous runners.
+    #[serde(default)]
+    pub remote_id: Option<JobId>,
+
+    pub state: JobState,
+
+    /// With `failed`, what went wrong.
+    #[serde(default)]
+    pub error: Option<String>,
+
+    /// Seconds since the epoch.
+    pub submitted_at: u64,
+    pub updated_at: u64,
+
+    /// With `done`, the samples, by decreasing number of instances.
+    #[serde(default)]
+    pub samples: Option<Vec<Sample>>,
+
+    #[serde(default)]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+impl Record {
+    /// Whether a separate process must execute the job, see [`Store::work`].
+    pub fn needs_worker(&self) -> bool {
+        self.remote_id.is_none() && !self.state.is_final()
+    }
+}
+
+/// A directory of job records.
+pub struct Store {
+    dir: PathBuf,
+}
+
+impl Store {
+    /// The directory from the configuration, or the default one.
+    pub fn open_default() -> Result<Self, Error> {
+        let dir = match Config::load()?.jobs_dir {
+            Some(dir) => dir,
+            None => {
+                let base = match std::env::var_os("XDG_DATA_HOME") {
+                    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+                    _ => PathBuf::from(std::env::var_os("HOME").ok_or(Error::NoDirectory)?)
+                        .join(".local")
+                        .join("share"),
+                };
+                base.join("qlafoutea").join("jobs")
+            }
+        };
+        Self::open(&dir)
+    }
+
+    pub fn open(dir: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(dir).map_err(|err| Error::Io(dir.to_path_buf(), err))?;
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// Where the process executing a job may write its logs.
+    pub fn log_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.log"))
+    }
+
+    pub fn get(&self, id: &str) -> Result<Record, Error> {
+        let path = self.path(id);
+        let source = match std::fs::read_to_string(&path) {
+            Ok(source) => source,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::NotFound(id.to_string()))
+            }
+            Err(err) => return Err(Error::Io(path, err)),
+        };
+        serde_json::from_str(&source).map_err(|err| Error::Record(path, err))
+    }
+
+    /// Write a record, atomically, so that readers never see a partial record.
+    pub fn save(&self, record: &mut Record) -> Result<(), Error> {
+        record.updated_at = now();
+        let path = self.path(&record.id);
+        let tmp = self.dir.join(format!(".{}.json.tmp", record.id));
+        let json =
+            serde_json::to_vec_pretty(record).map_err(|err| Error::Record(path.clone(), err))?;
+        std::fs::write(&tmp, json).map_err(|err| Error::Io(tmp.clone(), err))?;
+        std::fs::rename(&tmp, &path).map_err(|err| Error::Io(path, err))
+    }
+
+    /// Read, modify and write a record, holding the lock of the directory.
+    ///
+    /// `change` returns whether it modified the record, in which case it is saved.
+    /// Returns the record, as saved.
+    pub fn update(
+        &self,
+        id: &str,
+        change: impl FnOnce(&mut Record) -> bool,
+    ) -> Result<Record, Error> {
+        let path = self.dir.join(".lock");
+        let lock = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|err| Error::Io(path.clone(), err))?;
+        lock.lock().map_err(|err| Error::Io(path, err))?;
+        let mut record = self.get(id)?;
+        if change(&mut record) {
+            self.save(&mut record)?;
+        }
+        // The lock is released as `lock` is closed.
+        Ok(record)
+    }
+
+    /// All the jobs, oldest first.
+    pub fn list(&self) -> Result<Vec<Record>, Error> {
+        let entries =
+            std::fs::read_dir(&self.dir).map_err(|err| Error::Io(self.dir.clone(), err))?;
+        let mut records = vec![];
+        for entry in entries {
+            let path = entry
+                .map_err(|err| Error::Io(self.dir.clone(), err))?
+                .path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    records.push(self.get(id)?);
+                }
+            }
+        }
+        records.sort_by_key(|record| (record.submitted_at, record.id.clone()));
+        Ok(records)
+    }
+
+    /// Start running a compiled file.
+    ///
+    /// With an asynchronous runner, the job is submitted to the runner. Otherwise, the
+    /// caller must have [`Store::work`] execute it, typically in a separate process.
+    pub fn submit(&self, source: &Path, options: JobOptions) -> Result<Record, Error> {
+        let source = std::path::absolute(source).map_err(|err| Error::Io(source.into(), err))?;
+        let code = load(&source)?;
+        let runner = runner::by_name(&options.runner)?;
+        let sequence = code.sequence()?.bind(&variables(&options))?;
+        runner::check(runner.as_ref(), &sequence)?;
+        let remote_id = if runner.capabilities().asynchronous {
+            Some(runner.submit(&sequence, &sampling(&options))?)
+        } else {
+            None
+        };
+
+        // A short id, unique in this directory.
+        let id = loop {
+            let id = format!("{:08x}", rand::random::<u32>());
+            if !self.path(&id).exists() {
+                break id;
+            }
+        };
+        let mut record = Record {
+            id,
+            source,
+            code,
+            options,
+            remote_id,
+            state: JobState::Pending,
+            error: None,
+            submitted_at: now(),
+            updated_at: now(),
+            samples: None,
+            metadata: BTreeMap::new(),
+        };
+        self.save(&mut record)?;
+        Ok(record)
+    }
+
+    /// Execute a job on a synchronous runner, recording its results.
+    pub fn work(&self, id: &str) -> Result<(), Error> {
+        let record = self.update(id, |record| {
+            if !record.needs_worker() {
+                return false;
+            }
+            record.state = JobState::Running;
+            true
+        })?;
+        if !record.needs_worker() {
+            return Ok(());
+        }
+
+        let result = (|| -> Result<runner::Output, Error> {
+            let runner = runner::by_name(&record.options.runner)?;
+            let sequence = record.code.sequence()?.bind(&variables(&record.options))?;
+            runner::check(runner.as_ref(), &sequence)?;
+            Ok(runner.run(&sequence, &sampling(&record.options))?)
+        })();
+
+        self.update(id, |record| {
+            // The job may have been cancelled in the meantime.
+            if record.state == JobState::Cancelled {
+                return false;
+            }
+            match result {
+                Ok(output) => {
+                    record.state = JobState::Done;
+                    record.samples = Some(output.samples);
+                    record.metadata = output.metadata;
+                }
+                Err(err) => {
+                    record.state = JobState::Failed;
+                    record.error = Some(format!("{:#}", anyhow::Error::from(err)));
+                }
+            }
+            true
+        })?;
+        Ok(())
+    }
+
+    /// Get a job, asking the runner for news if the job is still in progress.
+    pub fn refresh(&self, id: &str) -> Result<Record, Error> {
+        let record = self.get(id)?;
+        let Some(ref remote_id) = record.remote_id else {
+            return Ok(record);
+        };
+        if record.state.is_final() {
+            return Ok(record);
+        }
+        let status = runner::by_name(&record.options.runner)?.status(remote_id)?;
+        self.update(id, |record| {
+            // The job may have been cancelled in the meantime.
+            if record.state.is_final() {
+                return false;
+            }
+            record.state = status.state();
+            match status {
+                Status::Done(output) => {
+                    record.samples = Some(output.samples);
+                    record.metadata = output.metadata;
+                }
+                Status::Failed(message) => record.error = Some(message),
+                Status::Pending | Status::Running | Status::Cancelled => {}
+            }
+            true
+        })
+    }
+
+    /// Cancel a job, unless it is over.
+    ///
+    /// A job executed by a separate process runs to completion, but its results are
+    /// discarded.
+    pub fn cancel(&self, id: &str) -> Result<Record, Error> {
+        let record = self.refresh(id)?;
+        if record.state.is_final() {
+            return Ok(record);
+        }
+        if let Some(ref remote_id) = record.remote_id {
+            runner::by_name(&record.options.runner)?.cancel(remote_id)?;
+        }
+        // Unless the job completed in the meantime.
+        self.update(id, |record| {
+            if record.state.is_final() {
+                return false;
+            }
+            record.state = JobState::Cancelled;
+            true
+        })
+    }
+}
+
+/// Load the compiled file of a job.
+fn load(source: &Path) -> Result<Code, Error> {
+    let file = std::fs::File::open(source).map_err(|err| Error::Io(source.to_path_buf(), err))?;
+    serde_json::from_reader(file)
+        .map_err(|err| Error::Code(source.to_path_buf(), crate::Error::json(source, err)))
+}
+
+/// Print one line per job.
+pub fn print_list(records: &[Record], out: &mut impl Write) -> Result<(), anyhow::Error> {
+    writeln!(
+        out,
+        "{:<10} {:<10} {:<16} {:>10}  source",
+        "id", "status", "runner", "submitted"
+    )?;
+    let now = now();
+    for record in records {
+        writeln!(
+            out,
+            "{:<10} {:<10} {:<16} {:>10}  {}",
+            record.id,
+            record.state.to_string(),
+            record.options.runner,
+            age(now.saturating_sub(record.submitted_at)),
+            record.source.display()
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the details of a job, without its samples.
+pub fn print_status(record: &Record, out: &mut impl Write) -> Result<(), anyhow::Error> {
+    let now = now();
+    writeln!(out, "job: {}", record.id)?;
+    writeln!(out, "status: {}", record.state)?;
+    if let Some(ref error) = record.error {
+        writeln!(out, "error: {error}")?;
+    }
+    writeln!(out, "source: {}", record.source.display())?;
+    write!(out, "runner: {}", record.options.runner)?;
+    match record.remote_id {
+        Some(ref remote_id) => writeln!(out, " (job {remote_id})")?,
+        None => writeln!(out)?,
+    }
+    write!(out, "shots: {}", record.options.shots)?;
+    match record.options.seed {
+        Some(seed) => writeln!(out, ", seed: {seed}")?,
+        None => writeln!(out)?,
+    }
+    for (name, value) in &record.options.variables {
+        writeln!(out, "variable: {name} = {value}")?;
+    }
+    writeln!(
+        out,
+        "submitted: {}, updated: {}",
+        age(now.saturating_sub(record.submitted_at)),
+        age(now.saturating_sub(record.updated_at))
+    )?;
+    if let Some(ref samples) = record.samples {
+        writeln!(out, "samples: {} distinct bitstrings", samples.len())?;
+    }
+    for (key, value) in &record.metadata {
+        writeln!(out, "{key}: {value}")?;
+    }
+    Ok(())
+}
+
+fn variables(options: &JobOptions) -> HashMap<String, f64> {
+    options
+        .variables
+        .iter()
+        .map(|(name, value)| (name.clone(), *value))
+        .collect()
+}
+
+fn sampling(options: &JobOptions) -> runner::Options {
+    runner::Options {
+        shots: options.shots,
+        seed: options.seed,
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// A human-readable duration, e.g. `3m ago`.
+fn age(seconds: u64) -> String {
+    match seconds {
+        0..=59 => format!("{seconds}s ago"),
+        60..=3_599 => format!("{}m ago", seconds / 60),
+        3_600..=86_399 => format!("{}h ago", seconds / 3_600),
+        _ => format!("{}d ago", seconds / 86_400),
+    }
+}
+
+#[test]
+fn test_store() {
+    use crate::{
+        backend::{
+            format::Layout,
+            pulser::sequence::fixture,
+            qubo::{self, Constraints},
+        },
+        frontend::Input,
+        types::Quality,
+    };
+
+    let qubo = Constraints::from_const([[-1., 2.], [2., -1.]], vec!["q0".into(), "q1".into()]);
+    let code = Code::try_new(
+        Input::new(qubo.clone()),
+        qubo,
+        &fixture::sweep(fixture::register(2)),
+        Layout {
+            quality: Quality::new(0.5).unwrap(),
+            seed: 0,
+            options: qubo::Options::default(),
+        },
+    )
+    .unwrap();
+
+    let dir = std::env::temp_dir().join(format!("qlafoutea-jobs-{:08x}", rand::random::<u32>()));
+    let store = Store::open(&dir).unwrap();
+    assert!(matches!(store.get("nope"), Err(Error::NotFound(_))));
+
+    let record = |id: &str, submitted_at| Record {
+        id: id.to_string(),
+        source: "/problem.qlaf".into(),
+        code: serde_json::from_value(serde_json::to_value(&code).unwrap()).unwrap(),
+        options: JobOptions {
+            runner: "py-pulser".to_string(),
+            shots: 10,
+            seed: Some(1),
+            variables: BTreeMap::new(),
+            result_sample_threshold: 0.5,
+        },
+        remote_id: None,
+        state: JobState::Pending,
+        error: None,
+        submitted_at,
+        updated_at: submitted_at,
+        samples: None,
+        metadata: BTreeMap::new(),
+    };
+    store.save(&mut record("b", 2)).unwrap();
+    store.save(&mut record("a", 3)).unwrap();
+    let ids = store
+        .list()
+        .unwrap()
+        .into_iter()
+        .map(|record| record.id)
+        .collect::<Vec<_>>();
+    assert_eq!(ids, vec!["b", "a"]);
+    assert!(store.get("a").unwrap().needs_worker());
+
+    // Cancelled jobs are not executed, and stay cancelled.
+    assert_eq!(store.cancel("a").unwrap().state, JobState::Cancelled);
+    store.work("a").unwrap();
+    let a = store.get("a").unwrap();
+    assert_eq!(a.state, JobState::Cancelled);
+    assert!(!a.needs_worker());
+    assert_eq!(store.cancel("a").unwrap().state, JobState::Cancelled);
+
+    // Records carry the compiled file, as submitted.
+    assert_eq!(a.code.qubo, code.qubo);
+
+    // Concurrent updates are not lost.
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..10 {
+                    store
+                        .update("b", |record| {
+                            let count = record.metadata.get("count").and_then(|n| n.as_u64());
+                            record
+                                .metadata
+                                .insert("count".into(), (count.unwrap_or(0) + 1).into());
+                            true
+                        })
+                        .unwrap();
+                }
+            });
+        }
+    });
+    assert_eq!(store.get("b").unwrap().metadata["count"], 40);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
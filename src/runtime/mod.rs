@@ -1 +1,5 @@
+pub mod inspect;
+pub mod jobs;
 pub mod run;
+pub mod runner;
+pub mod sweep;
@@ -1,23 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use anyhow::Context;
 use itertools::Itertools;
-use pyo3::{
-    types::{PyAnyMethods, PyDict},
-    Bound, Python,
-};
 use serde::{Deserialize, Serialize};
 
 use crate::{
     backend::{format::Code, pulser::sequence::Sequence},
-    studio,
+    runtime::runner::{self, Runner},
 };
 
-pub enum Runner {
-    PyPulser,
-    PulserStudio,
-}
-
 pub struct Options {
     /// How many results to display.
     ///
@@ -26,19 +17,39 @@ pub struct Options {
     /// best result * result_sample_threshold.
     pub result_sample_threshold: f64,
 
-    pub runner: Runner,
+    pub runner: Arc<dyn Runner>,
+
+    /// How many times, and with which seed, the sequence is sampled.
+    pub sampling: runner::Options,
+
+    /// Values for the variables declared by the sequence.
+    ///
+    /// Variables that are not specified keep the value chosen at compile time.
+    pub variables: HashMap<String, f64>,
 }
 
 pub fn run(code: Code, options: Options) -> Result<(), anyhow::Error> {
-    let mut sorted_samples = match options.runner {
-        Runner::PyPulser => run_python(&code.sequence)?,
-        Runner::PulserStudio => run_studio(&code.sequence)?,
-    };
+    let sequence = code.sequence().context("Invalid sequence")?;
+    let sequence = sequence
+        .bind(&options.variables)
+        .context("Failed to assign sequence variables")?;
+    runner::check(options.runner.as_ref(), &sequence)?;
+    let output = options.runner.run(&sequence, &options.sampling)?;
+    report(&code, output.samples, options.result_sample_threshold)
+}
 
+/// Hand the best samples, as per `result_sample_threshold`, to the problem.
+///
+/// `sorted_samples` are sorted by decreasing number of instances.
+pub fn report(
+    code: &Code,
+    mut sorted_samples: Vec<Sample>,
+    result_sample_threshold: f64,
+) -> Result<(), anyhow::Error> {
     // Only keep the best entries.
     let maybe_cut_at = if let Some(best) = sorted_samples.first() {
         if let Some((first, _)) = sorted_samples.iter().find_position(|sample| {
-            (sample.instances as f64 / best.instances as f64) < options.result_sample_threshold
+            (sample.instances as f64 / best.instances as f64) < result_sample_threshold
         }) {
             Some(first)
         } else {
@@ -48,67 +59,28 @@ pub fn run(code: Code, options: Options) -> Result<(), anyhow::Error> {
         None
     };
     if let Some(cut_at) = maybe_cut_at {
-        sorted_samples.resize_with(cut_at, || panic!());
+        sorted_samples.truncate(cut_at);
     }
 
     code.problem.handle_results(&sorted_samples)?;
     Ok(())
 }
 
+/// A function running a sequence on some runner, e.g. an emulator.
+///
+/// Used by compilation passes which tune a sequence against actual samples.
+pub type Sampler<'a> = dyn Fn(&Sequence) -> Result<Vec<Sample>, anyhow::Error> + Sync + 'a;
+
+/// Run a sequence with a runner, with the default options.
+///
+/// Samples are sorted by decreasing number of instances.
+pub fn sample(sequence: &Sequence, runner: &dyn Runner) -> Result<Vec<Sample>, anyhow::Error> {
+    runner::check(runner, sequence)?;
+    Ok(runner.run(sequence, &runner::Options::default())?.samples)
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Sample {
     pub bitstring: String,
     pub instances: u64,
 }
-
-pub fn run_python(source: &str) -> Result<Vec<Sample>, anyhow::Error> {
-    let Some(ref executable) = pyo3_build_config::get().executable else {
-        return Err(anyhow::anyhow!("Cannot find a Python environment"));
-    };
-    let mut cmd = std::process::Command::new(executable);
-    cmd.args(["-m", "pip", "install", "pulser"]);
-    cmd.spawn()
-        .context("Failed to launch setup")?
-        .wait()
-        .context("Error while setting up dependencies")?;
-
-    pyo3::prepare_freethreaded_python();
-    let samples = Python::with_gil(|py| -> Result<HashMap<String, u64>, pyo3::PyErr> {
-        let sequence_builder = py.import_bound("pulser")?.getattr("Sequence")?;
-        let qutip_emulator = py
-            .import_bound("pulser_simulation")?
-            .getattr("QutipEmulator")?;
-        let sequence = sequence_builder.call_method1("from_abstract_repr", (source,))?;
-        let simulator = qutip_emulator.call_method1("from_sequence", (sequence,))?;
-        let result = simulator.call_method0("run")?;
-        let np_samples: Bound<PyDict> = result.call_method0("sample_final_state")?.extract()?;
-
-        let mut samples = HashMap::new();
-        for (k, v) in np_samples {
-            let k: String = k.extract()?;
-            let v: u64 = v.extract()?;
-            samples.insert(k, v);
-        }
-        Ok(samples)
-    })
-    .context("Failed to run simulator")?;
-    let sorted = samples
-        .into_iter()
-        .sorted_by(|a, b| Ord::cmp(&b.1, &a.1))
-        .map(|(bitstring, instances)| Sample {
-            bitstring,
-            instances,
-        })
-        .collect_vec();
-
-    eprintln!("simulation complete");
-    Ok(sorted)
-}
-
-pub fn run_studio(source: &str) -> Result<Vec<Sample>, anyhow::Error> {
-    let sequence: Sequence = serde_json::from_str(source).context("Invalid sequence")?;
-    let studio = studio::Runner::new()?;
-    let mut simulator = studio.simulator()?;
-    simulator.simulate_sequence(sequence)?;
-    unimplemented!()
-}
@@ -0,0 +1,464 @@
+//! Execution backends, e.g. emulators or remote QPUs.
+//!
+//! Each backend is a [`Runner`], which describes what it accepts with its
+//! [`Capabilities`], so that sequences it cannot run are rejected before running them.
+//! Runners are found by name with [`by_name`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    io::Write,
+    sync::Arc,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::pulser::{sequence::Sequence, waveform::Waveform},
+    config::Config,
+};
+
+use super::run::Sample;
+
+pub mod mps;
+pub mod python;
+pub mod remote;
+pub mod studio;
+
+/// The number of shots, if the caller does not specify it.
+pub const DEFAULT_SHOTS: u64 = 1_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("unknown runner `{name}`, expected one of {}", available.join(", "))]
+    Unknown {
+        name: String,
+        available: Vec<String>,
+    },
+
+    #[error("runner `{runner}` accepts at most {max} qubits, the sequence uses {qubits}")]
+    TooManyQubits {
+        runner: String,
+        qubits: usize,
+        max: usize,
+    },
+
+    #[error("runner `{runner}` does not support {feature}")]
+    UnsupportedFeature { runner: String, feature: Feature },
+
+    #[error("runner `{runner}` does not support {operation}")]
+    Unsupported {
+        runner: String,
+        operation: &'static str,
+    },
+
+    #[error("cannot read configuration")]
+    Config(#[from] crate::config::Error),
+
+    #[error("runner `{runner}` failed")]
+    Failed {
+        runner: String,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+/// Something a sequence may use, and a runner may not support.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Feature {
+    /// Waveforms holding a constant value.
+    ConstantWaveforms,
+
+    /// Waveforms interpolated between values.
+    InterpolatedWaveforms,
+
+    /// More than one pulse, e.g. QAOA layers.
+    MultiplePulses,
+
+    /// Operations other than pulses, e.g. `delay` or `phase_shift`, from sequences written
+    /// by Pulser.
+    OtherOperations,
+
+    /// Variables which have not been assigned a value.
+    Variables,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::ConstantWaveforms,
+        Feature::InterpolatedWaveforms,
+        Feature::MultiplePulses,
+        Feature::OtherOperations,
+        Feature::Variables,
+    ];
+
+    /// The features used by a sequence.
+    pub fn of(sequence: &Sequence) -> BTreeSet<Feature> {
+        let mut features = BTreeSet::new();
+        for pulse in sequence.pulses() {
+            for waveform in [pulse.amplitude(), pulse.detuning()] {
+                features.insert(match waveform {
+                    Waveform::Constant { .. } => Feature::ConstantWaveforms,
+                    Waveform::Interpolated { .. } => Feature::InterpolatedWaveforms,
+                });
+            }
+        }
+        if sequence.pulses().count() > 1 {
+            features.insert(Feature::MultiplePulses);
+        }
+        if sequence.operations().len() > sequence.pulses().count() {
+            features.insert(Feature::OtherOperations);
+        }
+        if !sequence.variables().is_empty() {
+            features.insert(Feature::Variables);
+        }
+        features
+    }
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::ConstantWaveforms => write!(f, "constant waveforms"),
+            Self::InterpolatedWaveforms => write!(f, "interpolated waveforms"),
+            Self::MultiplePulses => write!(f, "multiple pulses"),
+            Self::OtherOperations => write!(f, "operations other than pulses"),
+            Self::Variables => write!(f, "unassigned variables"),
+        }
+    }
+}
+
+/// What a runner accepts.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    /// The largest register the runner accepts, if bounded.
+    pub max_qubits: Option<usize>,
+
+    /// The features of sequences the runner accepts.
+    pub features: BTreeSet<Feature>,
+
+    /// Whether the number of shots may be chosen.
+    pub shots: bool,
+
+    /// Whether results are reproducible given a seed.
+    pub seed: bool,
+
+    /// Whether jobs may be submitted and checked upon later, see [`Runner::submit`].
+    pub asynchronous: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// How many times the sequence is sampled.
+    pub shots: u64,
+
+    /// A seed for the randomness of the runner, if it supports one.
+    pub seed: Option<u64>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            shots: DEFAULT_SHOTS,
+            seed: None,
+        }
+    }
+}
+
+/// The results of running a sequence.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Output {
+    /// Sorted by decreasing number of instances.
+    pub samples: Vec<Sample>,
+
+    /// Whatever the runner wishes to record about the run, e.g. versions or timings.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+/// The identifier of a job submitted to an asynchronous runner.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct JobId(pub String);
+
+impl Display for JobId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// The status of a job submitted to an asynchronous runner.
+#[derive(Debug)]
+pub enum Status {
+    Pending,
+    Running,
+    Done(Output),
+    Failed(String),
+    Cancelled,
+}
+
+impl Status {
+    pub fn state(&self) -> JobState {
+        match *self {
+            Self::Pending => JobState::Pending,
+            Self::Running => JobState::Running,
+            Self::Done(_) => JobState::Done,
+            Self::Failed(_) => JobState::Failed,
+            Self::Cancelled => JobState::Cancelled,
+        }
+    }
+}
+
+/// The status of a job, without its results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    /// Whether the job is over, successfully or not.
+    pub fn is_final(self) -> bool {
+        matches!(self, Self::Done | Self::Failed | Self::Cancelled)
+    }
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Pending => write!(f, "pending"),
+            Self::Running => write!(f, "running"),
+            Self::Done => write!(f, "done"),
+            Self::Failed => write!(f, "failed"),
+            Self::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// An execution backend.
+pub trait Runner: Send + Sync {
+    /// The name of the runner, as given on the command line, e.g. `py-pulser`.
+    fn name(&self) -> &str;
+
+    /// A one-line, human-readable description.
+    fn description(&self) -> &str;
+
+    fn capabilities(&self) -> Capabilities;
+
+    /// Run a sequence to completion.
+    ///
+    /// Callers should [`check`] the sequence first.
+    fn run(&self, sequence: &Sequence, options: &Options) -> Result<Output, Error>;
+
+    /// Start running a sequence, without waiting for the results.
+    fn submit(&self, _sequence: &Sequence, _options: &Options) -> Result<JobId, Error> {
+        Err(self.unsupported("submitting jobs"))
+    }
+
+    fn status(&self, _job: &JobId) -> Result<Status, Error> {
+        Err(self.unsupported("checking jobs"))
+    }
+
+    fn cancel(&self, _job: &JobId) -> Result<(), Error> {
+        Err(self.unsupported("cancelling jobs"))
+    }
+
+    #[doc(hidden)]
+    fn unsupported(&self, operation: &'static str) -> Error {
+        Error::Unsupported {
+            runner: self.name().to_string(),
+            operation,
+        }
+    }
+}
+
+/// Make sure that `runner` can run `sequence`.
+pub fn check(runner: &dyn Runner, sequence: &Sequence) -> Result<(), Error> {
+    let capabilities = runner.capabilities();
+    let qubits = sequence.register().len();
+    if let Some(max) = capabilities.max_qubits {
+        if qubits > max {
+            return Err(Error::TooManyQubits {
+                runner: runner.name().to_string(),
+                qubits,
+                max,
+            });
+        }
+    }
+    if let Some(feature) = Feature::of(sequence)
+        .into_iter()
+        .find(|feature| !capabilities.features.contains(feature))
+    {
+        return Err(Error::UnsupportedFeature {
+            runner: runner.name().to_string(),
+            feature,
+        });
+    }
+    Ok(())
+}
+
+/// The runners built into qlafoutea, with their settings from `config`.
+pub fn builtin(config: &Config) -> Vec<Arc<dyn Runner>> {
+    vec![
+        Arc::new(python::PyPulser),
+        Arc::new(studio::PulserStudio),
+        Arc::new(mps::Mps::new(config.mps.clone())),
+    ]
+}
+
+/// The runners built into qlafoutea, then the remote runners of the configuration file.
+///
+/// If the configuration file cannot be loaded, the built-in runners remain available, with
+/// their default settings, and the error is returned alongside.
+pub fn available() -> (Vec<Arc<dyn Runner>>, Option<Error>) {
+    match Config::load() {
+        Ok(config) => {
+            let mut runners = builtin(&config);
+            for (name, remote) in config.remotes {
+                runners.push(Arc::new(remote::Remote::new(&name, remote)));
+            }
+            (runners, None)
+        }
+        Err(err) => (builtin(&Config::default()), Some(err.into())),
+    }
+}
+
+/// Find a runner by name.
+///
+/// Built-in runners are found even if the configuration file cannot be loaded.
+pub fn by_name(name: &str) -> Result<Arc<dyn Runner>, Error> {
+    let (runners, err) = available();
+    match runners.iter().find(|runner| runner.name() == name) {
+        Some(runner) => {
+            if let Some(err) = err {
+                eprintln!(
+                    "...ignoring the configuration file: {:#}",
+                    anyhow::Error::from(err)
+                );
+            }
+            Ok(runner.clone())
+        }
+        None => Err(err.unwrap_or_else(|| Error::Unknown {
+            name: name.to_string(),
+            available: runners
+                .iter()
+                .map(|runner| runner.name().to_string())
+                .collect(),
+        })),
+    }
+}
+
+/// Print the available runners and their capabilities.
+pub fn list(out: &mut impl Write) -> Result<(), anyhow::Error> {
+    let (runners, err) = available();
+    for runner in runners {
+        let capabilities = runner.capabilities();
+        writeln!(out, "{}", runner.name())?;
+        writeln!(out, "    {}", runner.description())?;
+        match capabilities.max_qubits {
+            Some(max) => writeln!(out, "    qubits: up to {max}")?,
+            None => writeln!(out, "    qubits: unbounded")?,
+        }
+        writeln!(
+            out,
+            "    accepts: {}",
+            capabilities.features.iter().join(", ")
+        )?;
+        let rejected = Feature::ALL
+            .iter()
+            .filter(|feature| !capabilities.features.contains(feature))
+            .join(", ");
+        if !rejected.is_empty() {
+            writeln!(out, "    rejects: {rejected}")?;
+        }
+        let yes_no = |b: bool| if b { "yes" } else { "no" };
+        writeln!(
+            out,
+            "    shots: {}, seed: {}, asynchronous: {}",
+            yes_no(capabilities.shots),
+            yes_no(capabilities.seed),
+            yes_no(capabilities.asynchronous)
+        )?;
+    }
+    match err {
+        // The built-in runners are listed all the same.
+        Some(err) => Err(anyhow::Error::from(err).context("Cannot list the remote runners")),
+        None => Ok(()),
+    }
+}
+
+#[test]
+fn test_check() {
+    use crate::backend::pulser::sequence::fixture::{register, sequence};
+
+    struct Small;
+    impl Runner for Small {
+        fn name(&self) -> &str {
+            "small"
+        }
+        fn description(&self) -> &str {
+            "two qubits, constant waveforms"
+        }
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                max_qubits: Some(2),
+                features: [Feature::ConstantWaveforms].into_iter().collect(),
+                shots: false,
+                seed: false,
+                asynchronous: false,
+            }
+        }
+        fn run(&self, _: &Sequence, _: &Options) -> Result<Output, Error> {
+            Ok(Output::default())
+        }
+    }
+
+    check(
+        &Small,
+        &sequence(
+            register(2),
+            Waveform::constant(100., 1.),
+            Waveform::constant(100., 0.),
+        ),
+    )
+    .unwrap();
+    assert!(matches!(
+        check(
+            &Small,
+            &sequence(
+                register(3),
+                Waveform::constant(100., 1.),
+                Waveform::constant(100., 0.)
+            )
+        ),
+        Err(Error::TooManyQubits {
+            qubits: 3,
+            max: 2,
+            ..
+        })
+    ));
+    assert!(matches!(
+        check(
+            &Small,
+            &sequence(
+                register(2),
+                Waveform::interpolated(100., &[0., 1.]),
+                Waveform::constant(100., 0.)
+            )
+        ),
+        Err(Error::UnsupportedFeature {
+            feature: Feature::InterpolatedWaveforms,
+            ..
+        })
+    ));
+    assert!(matches!(
+        Small.status(&JobId("1".into())),
+        Err(Error::Unsupported { .. })
+    ));
+}
@@ -0,0 +1,587 @@
+//! A matrix-product-state (MPS) emulator, for registers too large for a state vector.
+//!
+//! The atoms are ordered along a chain, and the state is a product of one tensor per
+//! atom. The size of the tensors (the bond dimension) bounds how much entanglement the
+//! state may hold, rather than the number of atoms bounding the memory.
+//!
+//! The evolution under the Rydberg Hamiltonian is split into time steps (TEBD, with a
+//! second-order Trotter decomposition): half of the drive, the interactions, then the
+//! other half of the drive. Interactions are applied to neighbours in the chain, through
+//! a network of swaps in which every pair of atoms meets exactly once per step. After
+//! each two-atom gate, the smallest singular values are discarded, keeping at most
+//! [`MpsConfig::max_bond_dimension`] of them. The weight discarded over the evolution
+//! is reported as `truncation_error` in the metadata of the results.
+//!
+//! Each step costs O(N² χ³) for N atoms and bond dimension χ.
+
+use std::collections::BTreeMap;
+
+use nalgebra::{Complex, DMatrix, Matrix2, Matrix4};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use super::{Capabilities, Feature, Options, Output, Runner};
+use crate::{
+    backend::pulser::{
+        device::{Addressing, Basis},
+        sequence::Sequence,
+    },
+    runtime::run::Sample,
+};
+
+type C64 = Complex<f64>;
+
+/// Above this truncation error, results are likely inaccurate.
+const TRUNCATION_WARNING: f64 = 1e-2;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("channel `{0}` is not a global channel addressing the ground-rydberg basis")]
+    Channel(String),
+
+    #[error("the sequence depends on variables, bind them first")]
+    Unbound,
+
+    #[error("invalid configuration: {0}")]
+    Config(&'static str),
+}
+
+/// The settings of the emulator, as `mps` in the configuration file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MpsConfig {
+    /// The largest bond dimension. Memory grows with its square, time with its cube.
+    #[serde(default = "MpsConfig::default_max_bond_dimension")]
+    pub max_bond_dimension: usize,
+
+    /// Singular values are also discarded, smallest first, as long as the weight
+    /// discarded by a gate stays below this.
+    #[serde(default = "MpsConfig::default_truncation_cutoff")]
+    pub truncation_cutoff: f64,
+
+    /// The duration of each time step, in ns.
+    #[serde(default = "MpsConfig::default_time_step_ns")]
+    pub time_step_ns: f64,
+}
+
+impl MpsConfig {
+    fn default_max_bond_dimension() -> usize {
+        32
+    }
+    fn default_truncation_cutoff() -> f64 {
+        1e-10
+    }
+    fn default_time_step_ns() -> f64 {
+        5.
+    }
+}
+
+impl Default for MpsConfig {
+    fn default() -> Self {
+        Self {
+            max_bond_dimension: Self::default_max_bond_dimension(),
+            truncation_cutoff: Self::default_truncation_cutoff(),
+            time_step_ns: Self::default_time_step_ns(),
+        }
+    }
+}
+
+/// Emulate sequences with matrix product states.
+pub struct Mps {
+    config: MpsConfig,
+}
+
+impl Mps {
+    pub fn new(config: MpsConfig) -> Self {
+        Self { config }
+    }
+
+    fn emulate(&self, sequence: &Sequence, options: &Options) -> Result<Output, Error> {
+        if self.config.max_bond_dimension == 0 {
+            return Err(Error::Config("`max_bond_dimension` must be at least 1"));
+        }
+        if self.config.time_step_ns.is_nan() || self.config.time_step_ns <= 0. {
+            return Err(Error::Config("`time_step_ns` must be positive"));
+        }
+        let register = sequence.register();
+        let device = sequence.device();
+        let atoms = chain(register.coordinates.iter().map(|(c, _)| {
+            let (x, y) = (c.x.into_inner(), c.y.into_inner());
+            (x, y)
+        }));
+        let interactions = register
+            .coordinates
+            .iter()
+            .enumerate()
+            .map(|(i, (first, _))| {
+                register
+                    .coordinates
+                    .iter()
+                    .enumerate()
+                    .map(|(j, (second, _))| {
+                        if i == j {
+                            0.
+                        } else {
+                            device.interaction(*first, *second)
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        eprintln!(
+            "...emulating {} atoms, with bond dimension up to {}",
+            register.len(),
+            self.config.max_bond_dimension
+        );
+        let mut state = State::new(atoms, &self.config);
+        let mut steps = 0;
+        for pulse in sequence.pulses() {
+            let channel = sequence
+                .channel_id(pulse.channel())
+                .and_then(|id| device.channels().iter().find(|chan| chan.id == *id));
+            match channel {
+                Some(chan)
+                    if chan.addressing == Addressing::Global
+                        && chan.basis == Basis::GroundRydberg => {}
+                _ => return Err(Error::Channel(pulse.channel().to_string())),
+            }
+            let duration = pulse
+                .amplitude()
+                .duration()
+                .as_value()
+                .ok_or(Error::Unbound)?;
+            let phase = pulse.phase().as_value().ok_or(Error::Unbound)?;
+            let num_steps = (duration / self.config.time_step_ns).ceil().max(1.) as usize;
+            let dt_ns = duration / num_steps as f64;
+            for step in 0..num_steps {
+                let t = (step as f64 + 0.5) * dt_ns;
+                let amplitude = pulse.amplitude().value_at(t).ok_or(Error::Unbound)?;
+                let detuning = pulse.detuning().value_at(t).ok_or(Error::Unbound)?;
+                let drive = drive(amplitude, detuning, phase, dt_ns / 2_000.);
+                state.apply_drive(&drive);
+                state.apply_interactions(&interactions, dt_ns / 1_000.);
+                state.apply_drive(&drive);
+            }
+            steps += num_steps;
+        }
+
+        if state.truncation_error > TRUNCATION_WARNING {
+            eprintln!(
+                "...truncation error {:.2e}, results may be inaccurate, consider increasing `mps.max_bond_dimension`",
+                state.truncation_error
+            );
+        }
+        let mut rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let mut samples = state.sample(options.shots, &mut rng);
+        samples.sort_by(|a, b| Ord::cmp(&b.instances, &a.instances));
+
+        let metadata = BTreeMap::from([
+            (
+                "truncation_error".to_string(),
+                state.truncation_error.into(),
+            ),
+            ("max_bond_dimension".to_string(), state.max_bond.into()),
+            (
+                "bond_dimension_limit".to_string(),
+                self.config.max_bond_dimension.into(),
+            ),
+            ("time_steps".to_string(), steps.into()),
+        ]);
+        Ok(Output { samples, metadata })
+    }
+}
+
+impl Default for Mps {
+    fn default() -> Self {
+        Self::new(MpsConfig::default())
+    }
+}
+
+impl Runner for Mps {
+    fn name(&self) -> &str {
+        "mps"
+    }
+
+    fn description(&self) -> &str {
+        "Matrix-product-state emulator (TEBD), for registers too large for Pulser's emulator"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            max_qubits: None,
+            features: [
+                Feature::ConstantWaveforms,
+                Feature::InterpolatedWaveforms,
+                Feature::MultiplePulses,
+            ]
+            .into_iter()
+            .collect(),
+            shots: true,
+            seed: true,
+            asynchronous: false,
+        }
+    }
+
+    fn run(&self, sequence: &Sequence, options: &Options) -> Result<Output, super::Error> {
+        self.emulate(sequence, options)
+            .map_err(|err| super::Error::Failed {
+                runner: self.name().to_string(),
+                source: err.into(),
+            })
+    }
+}
+
+/// Order atoms along the principal axis of the register, so that atoms close to each
+/// other, which interact the most, are close in the chain.
+fn chain(coordinates: impl Iterator<Item = (f64, f64)>) -> Vec<usize> {
+    let points = coordinates.collect::<Vec<_>>();
+    let n = points.len().max(1) as f64;
+    let (mx, my) = points
+        .iter()
+        .fold((0., 0.), |(sx, sy), (x, y)| (sx + x, sy + y));
+    let (mx, my) = (mx / n, my / n);
+    let (mut sxx, mut sxy, mut syy) = (0., 0., 0.);
+    for (x, y) in &points {
+        sxx += (x - mx) * (x - mx);
+        sxy += (x - mx) * (y - my);
+        syy += (y - my) * (y - my);
+    }
+    // The direction of the largest eigenvector of the covariance matrix.
+    let angle = 0.5 * f64::atan2(2. * sxy, sxx - syy);
+    let (dx, dy) = (angle.cos(), angle.sin());
+    let mut atoms = (0..points.len()).collect::<Vec<_>>();
+    atoms.sort_by(|&a, &b| {
+        let pa = points[a].0 * dx + points[a].1 * dy;
+        let pb = points[b].0 * dx + points[b].1 * dy;
+        pa.total_cmp(&pb)
+    });
+    atoms
+}
+
+/// The evolution of one atom under the drive for `dt_us` µs, in basis (|g>, |r>):
+/// exp(-i dt (Ω/2 (cos φ X - sin φ Y) - δ n)).
+fn drive(amplitude: f64, detuning: f64, phase: f64, dt_us: f64) -> Matrix2<C64> {
+    let (bx, by, bz) = (
+        amplitude / 2. * phase.cos(),
+        -amplitude / 2. * phase.sin(),
+        detuning / 2.,
+    );
+    let norm = (bx * bx + by * by + bz * bz).sqrt();
+    // -δ n = δ/2 Z - δ/2, the latter a global phase.
+    let global = C64::from_polar(1., detuning / 2. * dt_us);
+    if norm == 0. {
+        return Matrix2::identity() * global;
+    }
+    let (cos, sin) = ((norm * dt_us).cos(), (norm * dt_us).sin());
+    let i = C64::i();
+    let axis = Matrix2::new(
+        C64::from(bz),
+        C64::new(bx, -by),
+        C64::new(bx, by),
+        C64::from(-bz),
+    ) / C64::from(norm);
+    (Matrix2::identity() * C64::from(cos) - axis * (i * sin)) * global
+}
+
+/// Which side of a two-atom gate receives the orthogonality center.
+#[derive(Clone, Copy)]
+enum Direction {
+    Right,
+    Left,
+}
+
+struct State<'a> {
+    /// For each position in the chain, the tensor of the atom in |g> and in |r>.
+    sites: Vec<[DMatrix<C64>; 2]>,
+
+    /// The index of the atom at each position in the chain.
+    atoms: Vec<usize>,
+
+    /// The position around which the state is in canonical form.
+    center: usize,
+
+    /// The weight discarded by truncations, summed over the evolution.
+    truncation_error: f64,
+
+    /// The largest bond dimension reached.
+    max_bond: usize,
+
+    config: &'a MpsConfig,
+}
+
+impl<'a> State<'a> {
+    /// All atoms in the ground state.
+    fn new(atoms: Vec<usize>, config: &'a MpsConfig) -> Self {
+        let ground = || {
+            [
+                DMatrix::from_element(1, 1, C64::from(1.)),
+                DMatrix::from_element(1, 1, C64::from(0.)),
+            ]
+        };
+        Self {
+            sites: atoms.iter().map(|_| ground()).collect(),
+            atoms,
+            center: 0,
+            truncation_error: 0.,
+            max_bond: 1,
+            config,
+        }
+    }
+
+    fn apply_drive(&mut self, gate: &Matrix2<C64>) {
+        // Unitaries on a single site preserve the canonical form.
+        for [g, r] in &mut self.sites {
+            let new_g = &*g * gate[(0, 0)] + &*r * gate[(0, 1)];
+            let new_r = &*g * gate[(1, 0)] + &*r * gate[(1, 1)];
+            *g = new_g;
+            *r = new_r;
+        }
+    }
+
+    /// Apply the interactions for `dt_us` µs, swapping atoms so that each pair is
+    /// neighbours once. This reverses the chain.
+    fn apply_interactions(&mut self, interactions: &[Vec<f64>], dt_us: f64) {
+        if self.sites.len() < 2 {
+            return;
+        }
+        let gate = |state: &mut Self, k: usize, direction| {
+            let (i, j) = (state.atoms[k], state.atoms[k + 1]);
+            let phase = C64::from_polar(1., -interactions[i][j] * dt_us);
+            let one = C64::from(1.);
+            let zero = C64::from(0.);
+            // SWAP, with the phase of the interaction on |rr>.
+            #[rustfmt::skip]
+            let swap = Matrix4::new(
+                one, zero, zero, zero,
+                zero, zero, one, zero,
+                zero, one, zero, zero,
+                zero, zero, zero, phase,
+            );
+            state.apply_pair(k, &swap, direction);
+            state.atoms.swap(k, k + 1);
+        };
+        // Bubble the first atom to the end, then the last atom to the start, etc., so
+        // that the orthogonality center follows the gates.
+        let (mut lo, mut hi) = (0, self.sites.len() - 1);
+        while lo < hi {
+            self.move_center(lo);
+            for k in lo..hi {
+                gate(self, k, Direction::Right);
+            }
+            hi -= 1;
+            if lo >= hi {
+                break;
+            }
+            self.move_center(hi);
+            for k in (lo..hi).rev() {
+                gate(self, k, Direction::Left);
+            }
+            lo += 1;
+        }
+    }
+
+    fn move_center(&mut self, to: usize) {
+        let identity = Matrix4::identity();
+        while self.center < to {
+            self.apply_pair(self.center, &identity, Direction::Right);
+        }
+        while self.center > to {
+            self.apply_pair(self.center - 1, &identity, Direction::Left);
+        }
+    }
+
+    /// Apply `gate` to positions `k` and `k + 1`, truncating the bond between them.
+    ///
+    /// The orthogonality center must be on one of these positions.
+    fn apply_pair(&mut self, k: usize, gate: &Matrix4<C64>, direction: Direction) {
+        debug_assert!(self.center == k || self.center == k + 1);
+        let (left, right) = (&self.sites[k], &self.sites[k + 1]);
+        let (rows, cols) = (left[0].nrows(), right[0].ncols());
+
+        // theta[(a, l), (b, r)] = sum over (s, t) of gate[(a, b), (s, t)] (left[s] right[t])[l, r]
+        let products = [
+            &left[0] * &right[0],
+            &left[0] * &right[1],
+            &left[1] * &right[0],
+            &left[1] * &right[1],
+        ];
+        let mut theta = DMatrix::zeros(2 * rows, 2 * cols);
+        for a in 0..2 {
+            for b in 0..2 {
+                let mut block = theta.view_mut((a * rows, b * cols), (rows, cols));
+                for (st, product) in products.iter().enumerate() {
+                    let coeff = gate[(2 * a + b, st)];
+                    if coeff != C64::from(0.) {
+                        block += product * coeff;
+                    }
+                }
+            }
+        }
+
+        let svd = theta.svd(true, true);
+        let (u, v_t) = (svd.u.unwrap(), svd.v_t.unwrap());
+        let weights = svd.singular_values.map(|s| s * s);
+        let total = weights.sum();
+        let mut keep = weights.len().min(self.config.max_bond_dimension);
+        let mut discarded = weights.rows(keep, weights.len() - keep).sum();
+        while keep > 1 && discarded + weights[keep - 1] <= self.config.truncation_cutoff * total {
+            keep -= 1;
+            discarded += weights[keep];
+        }
+        if total > 0. {
+            self.truncation_error += discarded / total;
+        }
+        self.max_bond = self.max_bond.max(keep);
+        let kept = svd.singular_values.rows(0, keep);
+        let norm = kept.norm();
+        let singular = DMatrix::from_diagonal(&kept.map(|s| C64::from(s / norm)));
+
+        let u = u.columns(0, keep);
+        let v_t = v_t.rows(0, keep);
+        let (u, v_t) = match direction {
+            Direction::Right => (u.into_owned(), singular * v_t),
+            Direction::Left => (u * singular, v_t.into_owned()),
+        };
+        self.sites[k] = [
+            u.rows(0, rows).into_owned(),
+            u.rows(rows, rows).into_owned(),
+        ];
+        self.sites[k + 1] = [
+            v_t.columns(0, cols).into_owned(),
+            v_t.columns(cols, cols).into_owned(),
+        ];
+        self.center = match direction {
+            Direction::Right => k + 1,
+            Direction::Left => k,
+        };
+    }
+
+    /// Measure every atom, `shots` times.
+    fn sample(&mut self, shots: u64, rng: &mut impl Rng) -> Vec<Sample> {
+        // With the center on the first atom, the rest of the chain is right-canonical,
+        // so each atom may be measured given the atoms before it.
+        self.move_center(0);
+        let mut counts = BTreeMap::<String, u64>::new();
+        let mut bits = vec![b'0'; self.atoms.len()];
+        for _ in 0..shots {
+            let mut env = DMatrix::from_element(1, 1, C64::from(1.));
+            for (site, &atom) in self.sites.iter().zip(&self.atoms) {
+                let ground = &env * &site[0];
+                let rydberg = &env * &site[1];
+                let (p_ground, p_rydberg) = (ground.norm_squared(), rydberg.norm_squared());
+                let (bit, next, p) = if rng.gen::<f64>() * (p_ground + p_rydberg) < p_ground {
+                    (b'0', ground, p_ground)
+                } else {
+                    (b'1', rydberg, p_rydberg)
+                };
+                bits[atom] = bit;
+                env = next / C64::from(p.sqrt());
+            }
+            let bitstring = String::from_utf8(bits.clone()).expect("bits are ASCII");
+            *counts.entry(bitstring).or_default() += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(bitstring, instances)| Sample {
+                bitstring,
+                instances,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+fn test_sequence(
+    positions: &[(f64, f64)],
+    amplitude: f64,
+    detuning: f64,
+    duration_ns: f64,
+) -> Sequence {
+    use crate::backend::pulser::{sequence::fixture, waveform::Waveform};
+
+    let names = (0..positions.len())
+        .map(|i| format!("q{i}"))
+        .collect::<Vec<_>>();
+    let atoms = names
+        .iter()
+        .zip(positions)
+        .map(|(name, &(x, y))| (name.as_str(), x, y))
+        .collect::<Vec<_>>();
+    fixture::sequence(
+        fixture::atoms(&atoms),
+        Waveform::constant(duration_ns, amplitude),
+        Waveform::constant(duration_ns, detuning),
+    )
+}
+
+#[test]
+fn test_mps() {
+    use std::f64::consts::PI;
+
+    let options = Options {
+        shots: 1_000,
+        seed: Some(0),
+    };
+    let count = |output: &Output, bitstring: &str| {
+        output
+            .samples
+            .iter()
+            .find(|sample| sample.bitstring == bitstring)
+            .map_or(0, |sample| sample.instances)
+    };
+
+    // Far apart atoms, with a π pulse: all excited.
+    let sequence = test_sequence(&[(0., 0.), (50., 0.), (0., 50.)], PI, 0., 1_000.);
+    let output = Mps::default().run(&sequence, &options).unwrap();
+    assert!(count(&output, "111") > 990, "{:?}", output.samples);
+
+    // Half a Rabi oscillation: excited half of the time.
+    let sequence = test_sequence(&[(0., 0.)], PI / 2., 0., 1_000.);
+    let output = Mps::default().run(&sequence, &options).unwrap();
+    assert!((400..600).contains(&count(&output, "1")));
+
+    // Two atoms within the blockade radius, with a π pulse at the collective Rabi
+    // frequency √2 Ω: exactly one of them is excited.
+    let sequence = test_sequence(&[(0., 0.), (6., 0.)], PI / 2f64.sqrt(), 0., 1_000.);
+    let output = Mps::default().run(&sequence, &options).unwrap();
+    assert!(count(&output, "01") + count(&output, "10") > 980);
+    assert!((400..600).contains(&count(&output, "01")));
+    assert_eq!(output.metadata["max_bond_dimension"], 2);
+    assert!(output.metadata["truncation_error"].as_f64().unwrap() < 1e-6);
+
+    // Without room for the entanglement, the truncation is reported.
+    let runner = Mps::new(MpsConfig {
+        max_bond_dimension: 1,
+        ..Default::default()
+    });
+    let output = runner.run(&sequence, &options).unwrap();
+    assert!(output.metadata["truncation_error"].as_f64().unwrap() > TRUNCATION_WARNING);
+
+    // Same seed, same samples, whichever order the atoms are in.
+    let first = test_sequence(&[(0., 0.), (7., 0.), (14., 0.)], 2., -1., 600.);
+    let second = test_sequence(&[(14., 0.), (7., 0.), (0., 0.)], 2., -1., 600.);
+    let reversed = |output: Output| {
+        output
+            .samples
+            .into_iter()
+            .map(|sample| {
+                (
+                    sample.bitstring.chars().rev().collect::<String>(),
+                    sample.instances,
+                )
+            })
+            .collect::<BTreeMap<_, _>>()
+    };
+    let first = Mps::default().run(&first, &options).unwrap();
+    let second = Mps::default().run(&second, &options).unwrap();
+    let first = first
+        .samples
+        .into_iter()
+        .map(|sample| (sample.bitstring, sample.instances))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(first, reversed(second));
+}
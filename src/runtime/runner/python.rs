@@ -0,0 +1,435 @@
+//! Pulser's emulator, in a local Python environment.
+//!
+//! The emulator runs in a separate Python process, with the first interpreter among:
+//! 1. the one given to [`set_interpreter`], e.g. with `--python`;
+//! 2. the one of the active virtual environment, i.e. `$VIRTUAL_ENV`;
+//! 3. the one given as `python` in the configuration file;
+//! 4. `python3`, then `python`, in the `PATH`.
+//!
+//! The environment is never modified: if `pulser` or `pulser_simulation` are missing or
+//! too old, we report how to install them.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
+use itertools::Itertools;
+use serde::Deserialize;
+
+use super::{Capabilities, Feature, Options, Output, Runner};
+use crate::{backend::pulser::sequence::Sequence, config::Config, runtime::run::Sample};
+
+/// The oldest supported version of `pulser` and `pulser_simulation`, which introduced
+/// `QutipEmulator`.
+pub const MIN_PULSER_VERSION: (u64, u64) = (0, 15);
+
+/// The modules we need, as imported and as installed with pip.
+const MODULES: &[(&str, &str)] = &[
+    ("pulser", "pulser-core"),
+    ("pulser_simulation", "pulser-simulation"),
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error(
+        "cannot find a Python interpreter (tried {}), please use `--python`, activate a virtual \
+         environment or set `python` in the configuration",
+        tried.join(", ")
+    )]
+    NotFound { tried: Vec<String> },
+
+    #[error("cannot launch {0}")]
+    Launch(PathBuf, #[source] std::io::Error),
+
+    #[error(
+        "{} {} not installed for {python}, please install with `{python} -m pip install {}`",
+        missing.join(" and "),
+        if missing.len() == 1 { "is" } else { "are" },
+        packages.join(" ")
+    )]
+    Missing {
+        python: String,
+        missing: Vec<String>,
+        packages: Vec<String>,
+    },
+
+    #[error(
+        "{module} {version} is too old, qlafoutea requires {}.{} or later, please upgrade with \
+         `{python} -m pip install --upgrade {package}`",
+        MIN_PULSER_VERSION.0,
+        MIN_PULSER_VERSION.1
+    )]
+    TooOld {
+        python: String,
+        module: String,
+        package: String,
+        version: String,
+    },
+
+    #[error("{python} failed:\n{stderr}")]
+    Script { python: String, stderr: String },
+
+    #[error("invalid output from {0}")]
+    InvalidOutput(String, #[source] serde_json::Error),
+}
+
+/// Where the interpreter comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Origin {
+    Explicit,
+    VirtualEnv,
+    Config,
+    Path,
+}
+
+impl Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Explicit => write!(f, "from --python"),
+            Self::VirtualEnv => write!(f, "from $VIRTUAL_ENV"),
+            Self::Config => write!(f, "from the configuration"),
+            Self::Path => write!(f, "from $PATH"),
+        }
+    }
+}
+
+/// A Python interpreter.
+#[derive(Clone, Debug)]
+pub struct Interpreter {
+    pub path: PathBuf,
+    pub origin: Origin,
+}
+
+fn explicit() -> &'static RwLock<Option<PathBuf>> {
+    static EXPLICIT: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+    EXPLICIT.get_or_init(|| RwLock::new(None))
+}
+
+/// Use this interpreter, rather than looking for one, for the rest of the process.
+pub fn set_interpreter(path: &Path) {
+    *explicit().write().unwrap_or_else(PoisonError::into_inner) = Some(path.to_path_buf());
+}
+
+impl Interpreter {
+    /// Find an interpreter, see the module documentation.
+    pub fn discover() -> Result<Self, Error> {
+        if let Some(path) = explicit()
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+        {
+            return Ok(Self {
+                path,
+                origin: Origin::Explicit,
+            });
+        }
+        if let Some(venv) = std::env::var_os("VIRTUAL_ENV").filter(|venv| !venv.is_empty()) {
+            let venv = PathBuf::from(venv);
+            let path = if cfg!(windows) {
+                venv.join("Scripts").join("python.exe")
+            } else {
+                venv.join("bin").join("python")
+            };
+            return Ok(Self {
+                path,
+                origin: Origin::VirtualEnv,
+            });
+        }
+        // An invalid configuration file is reported by whoever needs the rest of it.
+        if let Some(path) = Config::load().ok().and_then(|config| config.python) {
+            return Ok(Self {
+                path,
+                origin: Origin::Config,
+            });
+        }
+        let candidates = ["python3", "python"];
+        for candidate in candidates {
+            if let Some(path) = find_in_path(candidate) {
+                return Ok(Self {
+                    path,
+                    origin: Origin::Path,
+                });
+            }
+        }
+        Err(Error::NotFound {
+            tried: vec![
+                "`--python`".to_string(),
+                "$VIRTUAL_ENV".to_string(),
+                "the configuration".to_string(),
+                candidates.iter().map(|c| format!("`{c}`")).join(", "),
+            ],
+        })
+    }
+
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    /// Run [`SCRIPT`] with a request, returning its response.
+    fn call(&self, request: &serde_json::Value) -> Result<Response, Error> {
+        let mut child = Command::new(&self.path)
+            .args(["-c", SCRIPT])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| Error::Launch(self.path.clone(), err))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(request.to_string().as_bytes())
+                .map_err(|err| Error::Launch(self.path.clone(), err))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|err| Error::Launch(self.path.clone(), err))?;
+        if !output.status.success() {
+            return Err(Error::Script {
+                python: self.name(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        serde_json::from_slice(&output.stdout).map_err(|err| Error::InvalidOutput(self.name(), err))
+    }
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Reads a JSON request `{"sequence": ..., "shots": ..., "seed": ...}` on stdin, writes
+/// `{"versions": {...}, "missing": [...], "samples": {...}}` on stdout, or `"error"`
+/// rather than `"samples"` if the emulation fails. Without a sequence, or with missing
+/// modules, only reports the versions.
+const SCRIPT: &str = r#"
+import json
+import sys
+
+# Keep stdout for our response, whatever the emulator prints.
+out, sys.stdout = sys.stdout, sys.stderr
+
+request = json.load(sys.stdin)
+versions = {"python": sys.version.split()[0]}
+missing = []
+for module in ("pulser", "pulser_simulation"):
+    try:
+        versions[module] = getattr(__import__(module), "__version__", "unknown")
+    except ImportError:
+        missing.append(module)
+if missing or "sequence" not in request:
+    json.dump({"versions": versions, "missing": missing}, out)
+    sys.exit(0)
+
+try:
+    import numpy
+    from pulser import Sequence
+    from pulser_simulation import QutipEmulator
+
+    if request.get("seed") is not None:
+        # Pulser samples with numpy's global generator.
+        numpy.random.seed(request["seed"])
+    sequence = Sequence.from_abstract_repr(json.dumps(request["sequence"]))
+    result = QutipEmulator.from_sequence(sequence).run()
+    counts = result.sample_final_state(N_samples=request["shots"])
+    samples = {bitstring: int(count) for bitstring, count in counts.items()}
+    json.dump({"versions": versions, "missing": [], "samples": samples}, out)
+except Exception:
+    import traceback
+
+    # Reported after checking the versions, which are the likely culprit.
+    json.dump({"versions": versions, "missing": [], "error": traceback.format_exc()}, out)
+"#;
+
+#[derive(Deserialize)]
+struct Response {
+    versions: BTreeMap<String, String>,
+    missing: Vec<String>,
+    #[serde(default)]
+    samples: Option<BTreeMap<String, u64>>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl Response {
+    fn check(&self, python: &Interpreter) -> Result<(), Error> {
+        if !self.missing.is_empty() {
+            return Err(Error::Missing {
+                python: python.name(),
+                missing: self.missing.clone(),
+                packages: MODULES
+                    .iter()
+                    .filter(|(module, _)| self.missing.iter().any(|m| m == module))
+                    .map(|(_, package)| package.to_string())
+                    .collect(),
+            });
+        }
+        for (module, package) in MODULES {
+            let Some(version) = self.versions.get(*module) else {
+                continue;
+            };
+            // Versions we cannot parse, e.g. development builds, get the benefit of the doubt.
+            if parse_version(version).is_some_and(|parsed| parsed < MIN_PULSER_VERSION) {
+                return Err(Error::TooOld {
+                    python: python.name(),
+                    module: module.to_string(),
+                    package: package.to_string(),
+                    version: version.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The major and minor components of a version, e.g. `0.19.2`.
+fn parse_version(version: &str) -> Option<(u64, u64)> {
+    let mut components = version.split('.');
+    let major = components.next()?.parse().ok()?;
+    let minor = components
+        .next()?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
+
+/// Runs sequences with `pulser_simulation.QutipEmulator`.
+pub struct PyPulser;
+
+impl PyPulser {
+    fn run_value(
+        &self,
+        sequence: serde_json::Value,
+        options: &Options,
+    ) -> Result<Output, anyhow::Error> {
+        let python = Interpreter::discover()?;
+        eprintln!(
+            "...running Pulser with {} ({})",
+            python.name(),
+            python.origin
+        );
+        let response = python.call(&serde_json::json!({
+            "sequence": sequence,
+            "shots": options.shots,
+            "seed": options.seed,
+        }))?;
+        response.check(&python)?;
+        if let Some(stderr) = response.error {
+            return Err(Error::Script {
+                python: python.name(),
+                stderr: stderr.trim().to_string(),
+            }
+            .into());
+        }
+        let samples = response.samples.unwrap_or_default();
+        let sorted = samples
+            .into_iter()
+            .sorted_by(|a, b| Ord::cmp(&b.1, &a.1))
+            .map(|(bitstring, instances)| Sample {
+                bitstring,
+                instances,
+            })
+            .collect_vec();
+
+        eprintln!("simulation complete");
+        let mut metadata = BTreeMap::new();
+        metadata.insert("python".to_string(), python.name().into());
+        for (module, version) in response.versions {
+            metadata.insert(format!("{module}_version"), version.into());
+        }
+        Ok(Output {
+            samples: sorted,
+            metadata,
+        })
+    }
+}
+
+impl Runner for PyPulser {
+    fn name(&self) -> &str {
+        "py-pulser"
+    }
+
+    fn description(&self) -> &str {
+        "Pulser's QutipEmulator, in the local Python environment"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            // Bounded by memory, as the emulator stores the full state.
+            max_qubits: None,
+            features: [
+                Feature::ConstantWaveforms,
+                Feature::InterpolatedWaveforms,
+                Feature::MultiplePulses,
+                Feature::OtherOperations,
+            ]
+            .into_iter()
+            .collect(),
+            shots: true,
+            seed: true,
+            asynchronous: false,
+        }
+    }
+
+    fn run(&self, sequence: &Sequence, options: &Options) -> Result<Output, super::Error> {
+        let failed = |source| super::Error::Failed {
+            runner: self.name().to_string(),
+            source,
+        };
+        let sequence = serde_json::to_value(sequence).map_err(|err| failed(err.into()))?;
+        self.run_value(sequence, options).map_err(failed)
+    }
+}
+
+/// Run a sequence, in Pulser's JSON format, with the default options.
+///
+/// Samples are sorted by decreasing number of instances.
+pub fn run_python(source: &str) -> Result<Vec<Sample>, anyhow::Error> {
+    let sequence = serde_json::from_str(source)?;
+    Ok(PyPulser.run_value(sequence, &Options::default())?.samples)
+}
+
+#[test]
+fn test_check() {
+    assert_eq!(parse_version("0.19.2"), Some((0, 19)));
+    assert_eq!(parse_version("1.0rc1"), Some((1, 0)));
+    assert_eq!(parse_version("unknown"), None);
+
+    let python = Interpreter {
+        path: "python".into(),
+        origin: Origin::Explicit,
+    };
+    let response = |pulser: &str, missing: &[&str]| Response {
+        versions: [("pulser", pulser), ("pulser_simulation", pulser)]
+            .into_iter()
+            .filter(|(module, _)| !missing.contains(module))
+            .map(|(module, version)| (module.to_string(), version.to_string()))
+            .collect(),
+        missing: missing.iter().map(|m| m.to_string()).collect(),
+        samples: None,
+        error: None,
+    };
+    response("0.19.2", &[]).check(&python).unwrap();
+    response("unknown", &[]).check(&python).unwrap();
+    assert!(matches!(
+        response("0.12.0", &[]).check(&python),
+        Err(Error::TooOld { .. })
+    ));
+    let Err(err) = response("0.19.2", &["pulser_simulation"]).check(&python) else {
+        panic!("missing modules should be reported");
+    };
+    assert_eq!(
+        err.to_string(),
+        "pulser_simulation is not installed for python, please install with \
+         `python -m pip install pulser-simulation`"
+    );
+}
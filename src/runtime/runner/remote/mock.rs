@@ -0,0 +1,455 @@
+//! A local implementation of the job API, running jobs with another runner, typically an
+//! emulator. Used to test remote runners without a QPU.
+
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::JoinHandle,
+};
+
+use anyhow::Context;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{ErrorResponse, JobResponse, SubmitRequest};
+use crate::{
+    backend::pulser::sequence::Sequence,
+    runtime::runner::{self, JobId, JobState, Runner},
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// If specified, requests must carry `Authorization: Bearer <token>`.
+    pub token: Option<String>,
+
+    /// How many requests to answer with 503 before serving normally, to exercise retries.
+    pub failures: u32,
+
+    /// How many submissions to accept but answer with 503, as if the response had been
+    /// lost, to exercise idempotency keys.
+    pub lost_responses: u32,
+}
+
+struct Job {
+    state: JobState,
+    error: Option<String>,
+    results: Option<serde_json::Value>,
+}
+
+struct State {
+    runner: Arc<dyn Runner>,
+    options: Options,
+    failures: AtomicU32,
+    lost_responses: AtomicU32,
+    jobs: Mutex<HashMap<String, Job>>,
+
+    /// The job created for each idempotency key.
+    keys: Mutex<HashMap<String, String>>,
+}
+
+/// A running mock server, stopped when dropped.
+pub struct MockServer {
+    server: Arc<Server>,
+    addr: SocketAddr,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Start serving on `addr`, e.g. `127.0.0.1:0` for any free port.
+    pub fn start(
+        addr: &str,
+        runner: Arc<dyn Runner>,
+        options: Options,
+    ) -> Result<Self, anyhow::Error> {
+        let server = Server::http(addr)
+            .map_err(|err| anyhow::anyhow!(err))
+            .with_context(|| format!("Cannot listen on {addr}"))?;
+        let addr = server
+            .server_addr()
+            .to_ip()
+            .context("Mock server is not listening on an IP address")?;
+        let server = Arc::new(server);
+        let state = Arc::new(State {
+            runner,
+            failures: AtomicU32::new(options.failures),
+            lost_responses: AtomicU32::new(options.lost_responses),
+            options,
+            jobs: Mutex::new(HashMap::new()),
+            keys: Mutex::new(HashMap::new()),
+        });
+        let thread = {
+            let server = server.clone();
+            std::thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    state.handle(request);
+                }
+            })
+        };
+        Ok(Self {
+            server,
+            addr,
+            thread: Some(thread),
+        })
+    }
+
+    /// The base URL of the API.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Serve until the process is interrupted.
+    pub fn wait(mut self) {
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl State {
+    fn handle(self: &Arc<Self>, mut request: Request) {
+        let (status, body) = self.route(&mut request);
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes("Content-Type", "application/json").expect("valid header"),
+            );
+        if let Err(err) = request.respond(response) {
+            eprintln!("...cannot respond: {err}");
+        }
+    }
+
+    fn route(self: &Arc<Self>, request: &mut Request) -> (u16, String) {
+        if let Some(ref token) = self.options.token {
+            let expected = format!("Bearer {token}");
+            let authorized = request
+                .headers()
+                .iter()
+                .any(|header| header.field.equiv("Authorization") && header.value == *expected);
+            if !authorized {
+                return error(401, "missing or invalid token");
+            }
+        }
+        if self
+            .failures
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok()
+        {
+            return error(503, "try again later");
+        }
+
+        let url = request.url().to_string();
+        let segments = url
+            .trim_matches('/')
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<_>>();
+        match (request.method(), segments.as_slice()) {
+            (Method::Post, ["jobs"]) => {
+                let key = request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv("Idempotency-Key"))
+                    .map(|header| header.value.to_string());
+                let mut body = String::new();
+                if let Err(err) = request.as_reader().read_to_string(&mut body) {
+                    return error(400, &err.to_string());
+                }
+                let submit = match serde_json::from_str::<SubmitRequest>(&body) {
+                    Ok(submit) => submit,
+                    Err(err) => return error(400, &format!("invalid job: {err}")),
+                };
+                let response = self.submit(submit, key);
+                if self
+                    .lost_responses
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok()
+                {
+                    return error(503, "response lost");
+                }
+                json(201, &response)
+            }
+            (Method::Get, ["jobs", id]) => match self.describe(id) {
+                Some(response) => json(200, &response),
+                None => error(404, &format!("no job {id}")),
+            },
+            (Method::Get, ["jobs", id, "results"]) => {
+                let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+                match jobs.get(*id) {
+                    None => error(404, &format!("no job {id}")),
+                    Some(Job {
+                        results: Some(results),
+                        ..
+                    }) => json(200, results),
+                    Some(job) => error(409, &format!("job {id} is {}", job.state)),
+                }
+            }
+            (Method::Delete, ["jobs", id]) => {
+                {
+                    let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+                    if let Some(job) = jobs.get_mut(*id) {
+                        if matches!(job.state, JobState::Pending | JobState::Running) {
+                            job.state = JobState::Cancelled;
+                        }
+                    }
+                }
+                match self.describe(id) {
+                    Some(response) => json(200, &response),
+                    None => error(404, &format!("no job {id}")),
+                }
+            }
+            _ => error(404, &format!("no route for {} {url}", request.method())),
+        }
+    }
+
+    /// Create a job, unless one was already created for `key`.
+    fn submit(self: &Arc<Self>, submit: SubmitRequest, key: Option<String>) -> JobResponse {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(id) = key.as_ref().and_then(|key| keys.get(key)) {
+            if let Some(response) = self.describe(id) {
+                return response;
+            }
+        }
+        let id = {
+            let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+            let id = format!("job-{}", jobs.len() + 1);
+            jobs.insert(
+                id.clone(),
+                Job {
+                    state: JobState::Pending,
+                    error: None,
+                    results: None,
+                },
+            );
+            id
+        };
+        if let Some(key) = key {
+            keys.insert(key, id.clone());
+        }
+        drop(keys);
+        let state = self.clone();
+        let job = id.clone();
+        std::thread::spawn(move || state.execute(&job, submit));
+        JobResponse {
+            id: JobId(id),
+            status: JobState::Pending,
+            error: None,
+        }
+    }
+
+    fn execute(&self, id: &str, submit: SubmitRequest) {
+        if !self.transition(id, |job| job.state = JobState::Running) {
+            return;
+        }
+        let result = serde_json::from_value::<Sequence>(submit.sequence)
+            .map_err(anyhow::Error::from)
+            .and_then(|sequence| {
+                runner::check(self.runner.as_ref(), &sequence)?;
+                let options = runner::Options {
+                    shots: submit.shots,
+                    seed: submit.seed,
+                };
+                Ok(self.runner.run(&sequence, &options)?)
+            })
+            .and_then(|output| Ok(serde_json::to_value(output)?));
+        self.transition(id, |job| match result {
+            Ok(results) => {
+                job.state = JobState::Done;
+                job.results = Some(results);
+            }
+            Err(err) => {
+                job.state = JobState::Failed;
+                job.error = Some(format!("{err:#}"));
+            }
+        });
+    }
+
+    /// Update a job, unless it was cancelled. Returns `false` if it was.
+    fn transition(&self, id: &str, update: impl FnOnce(&mut Job)) -> bool {
+        let mut jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        match jobs.get_mut(id) {
+            Some(job) if job.state != JobState::Cancelled => {
+                update(job);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn describe(&self, id: &str) -> Option<JobResponse> {
+        let jobs = self.jobs.lock().unwrap_or_else(PoisonError::into_inner);
+        jobs.get(id).map(|job| JobResponse {
+            id: JobId(id.to_string()),
+            status: job.state,
+            error: job.error.clone(),
+        })
+    }
+}
+
+fn json(status: u16, body: &impl Serialize) -> (u16, String) {
+    match serde_json::to_string(body) {
+        Ok(body) => (status, body),
+        Err(err) => error(500, &err.to_string()),
+    }
+}
+
+fn error(status: u16, message: &str) -> (u16, String) {
+    let body = ErrorResponse {
+        error: message.to_string(),
+    };
+    (
+        status,
+        serde_json::to_string(&body).unwrap_or_else(|_| "{}".to_string()),
+    )
+}
+
+#[test]
+fn test_mock_server() {
+    use std::time::Duration;
+
+    use crate::{
+        backend::pulser::{
+            sequence::fixture::{register, sequence},
+            waveform::Waveform,
+        },
+        runtime::{
+            run::Sample,
+            runner::{
+                remote::{Remote, RemoteConfig},
+                Capabilities, Feature, Output, Status,
+            },
+        },
+    };
+
+    /// Returns as many `01` as there are shots, after `delay`.
+    struct Fake {
+        delay: Duration,
+    }
+    impl Runner for Fake {
+        fn name(&self) -> &str {
+            "fake"
+        }
+        fn description(&self) -> &str {
+            "fake"
+        }
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                max_qubits: Some(2),
+                features: Feature::ALL.iter().copied().collect(),
+                shots: true,
+                seed: false,
+                asynchronous: false,
+            }
+        }
+        fn run(&self, _: &Sequence, options: &runner::Options) -> Result<Output, runner::Error> {
+            std::thread::sleep(self.delay);
+            Ok(Output {
+                samples: vec![Sample {
+                    bitstring: "01".to_string(),
+                    instances: options.shots,
+                }],
+                ..Default::default()
+            })
+        }
+    }
+
+    let sequence = |len: usize| {
+        sequence(
+            register(len),
+            Waveform::constant(100., 1.),
+            Waveform::constant(100., 0.),
+        )
+    };
+    let options = runner::Options {
+        shots: 42,
+        seed: None,
+    };
+    let remote = |server: &MockServer, token: Option<&str>| {
+        Remote::new(
+            "remote",
+            RemoteConfig {
+                token: token.map(str::to_string),
+                retry_delay_ms: 1,
+                poll_interval_ms: 10,
+                ..RemoteConfig::new(&server.url())
+            },
+        )
+    };
+
+    // Submit, poll and fetch results, with retries and authentication.
+    let server = MockServer::start(
+        "127.0.0.1:0",
+        Arc::new(Fake {
+            delay: Duration::ZERO,
+        }),
+        Options {
+            token: Some("secret".to_string()),
+            failures: 2,
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    let output = remote(&server, Some("secret"))
+        .run(&sequence(2), &options)
+        .unwrap();
+    assert_eq!(output.samples[0].bitstring, "01");
+    assert_eq!(output.samples[0].instances, 42);
+    assert_eq!(output.metadata["job"], "job-1");
+
+    let err = remote(&server, Some("guess"))
+        .run(&sequence(2), &options)
+        .unwrap_err();
+    let err = format!("{:#}", anyhow::Error::from(err));
+    assert!(err.contains("401"), "{err}");
+
+    // Failures of the backing runner are reported.
+    let err = remote(&server, Some("secret"))
+        .run(&sequence(3), &options)
+        .unwrap_err();
+    let err = format!("{:#}", anyhow::Error::from(err));
+    assert!(err.contains("at most 2 qubits"), "{err}");
+
+    // Submissions are not repeated when their response is lost.
+    let server = MockServer::start(
+        "127.0.0.1:0",
+        Arc::new(Fake {
+            delay: Duration::ZERO,
+        }),
+        Options {
+            lost_responses: 1,
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    let output = remote(&server, None).run(&sequence(2), &options).unwrap();
+    assert_eq!(output.metadata["job"], "job-1");
+    assert!(remote(&server, None)
+        .status(&JobId("job-2".into()))
+        .is_err());
+
+    // Jobs may be cancelled while running.
+    let server = MockServer::start(
+        "127.0.0.1:0",
+        Arc::new(Fake {
+            delay: Duration::from_millis(500),
+        }),
+        Options::default(),
+    )
+    .unwrap();
+    let remote = remote(&server, None);
+    let job = remote.submit(&sequence(2), &options).unwrap();
+    remote.cancel(&job).unwrap();
+    assert!(matches!(remote.status(&job).unwrap(), Status::Cancelled));
+}
@@ -0,0 +1,374 @@
+//! Runners which submit jobs to an HTTP job API, e.g. a cloud QPU.
+//!
+//! The API, also implemented by the [`mock`] server:
+//! - `POST /jobs`, with a [`SubmitRequest`], creates a job and returns its [`JobResponse`];
+//! - `GET /jobs/<id>` returns the [`JobResponse`] of a job;
+//! - `GET /jobs/<id>/results` returns the [`Output`] of a job which is `done`;
+//! - `DELETE /jobs/<id>` cancels a job and returns its [`JobResponse`].
+//!
+//! Errors are reported with an HTTP status and `{"error": "..."}`. If the API requires
+//! authentication, requests carry `Authorization: Bearer <token>`.
+//!
+//! Requests are retried on network errors, 429 and 5xx. As the job may have been created
+//! even though its response was lost, `POST /jobs` carries an `Idempotency-Key`, the same
+//! for every attempt, and the API must answer a repeated key with the job it created first.
+
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{Capabilities, Feature, JobId, JobState, Options, Output, Runner, Status};
+use crate::backend::pulser::sequence::Sequence;
+
+pub mod mock;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("environment variable `{0}`, which should hold the token, is not set")]
+    MissingToken(String),
+
+    #[error("cannot encode sequence")]
+    Sequence(#[source] serde_json::Error),
+
+    #[error("cannot reach {url}")]
+    Transport {
+        url: String,
+        #[source]
+        source: Box<ureq::Transport>,
+    },
+
+    #[error("{url} answered {status}: {message}")]
+    Status {
+        url: String,
+        status: u16,
+        message: String,
+    },
+
+    #[error("invalid response from {url}")]
+    InvalidResponse {
+        url: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("job {0} failed: {1}")]
+    JobFailed(JobId, String),
+
+    #[error("job {0} was cancelled")]
+    JobCancelled(JobId),
+
+    #[error("job {job} did not complete within {timeout:?}")]
+    Timeout { job: JobId, timeout: Duration },
+}
+
+/// How to reach a job API, as written in the configuration file.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// The base URL of the API, e.g. `https://qpu.example.com/v1`.
+    pub url: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    /// The token used to authenticate.
+    #[serde(default)]
+    pub token: Option<String>,
+
+    /// The environment variable holding the token, to keep it out of the configuration file.
+    #[serde(default)]
+    pub token_env: Option<String>,
+
+    /// The largest register accepted by the QPU, if bounded.
+    #[serde(default)]
+    pub max_qubits: Option<usize>,
+
+    /// The timeout of each request, in seconds.
+    #[serde(default = "RemoteConfig::default_timeout_s")]
+    pub timeout_s: u64,
+
+    /// How many times to retry requests which fail with network errors or which the
+    /// server asks to retry (429 and 5xx).
+    #[serde(default = "RemoteConfig::default_retries")]
+    pub retries: u32,
+
+    /// The delay before the first retry, in ms, doubling with each retry.
+    #[serde(default = "RemoteConfig::default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+
+    /// How often to check the status of a job while waiting for it, in ms.
+    #[serde(default = "RemoteConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+
+    /// How long to wait for a job before cancelling it, in seconds.
+    #[serde(default = "RemoteConfig::default_job_timeout_s")]
+    pub job_timeout_s: u64,
+}
+
+impl RemoteConfig {
+    fn default_timeout_s() -> u64 {
+        30
+    }
+    fn default_retries() -> u32 {
+        3
+    }
+    fn default_retry_delay_ms() -> u64 {
+        500
+    }
+    fn default_poll_interval_ms() -> u64 {
+        1_000
+    }
+    fn default_job_timeout_s() -> u64 {
+        24 * 3_600
+    }
+
+    /// A configuration with default settings, without authentication.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            description: None,
+            token: None,
+            token_env: None,
+            max_qubits: None,
+            timeout_s: Self::default_timeout_s(),
+            retries: Self::default_retries(),
+            retry_delay_ms: Self::default_retry_delay_ms(),
+            poll_interval_ms: Self::default_poll_interval_ms(),
+            job_timeout_s: Self::default_job_timeout_s(),
+        }
+    }
+}
+
+/// The body of `POST /jobs`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubmitRequest {
+    /// The sequence, in Pulser's abstract representation.
+    pub sequence: serde_json::Value,
+    pub shots: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+/// The description of a job, as returned by the API.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JobResponse {
+    pub id: JobId,
+    pub status: JobState,
+
+    /// With `failed`, what went wrong.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A body `{"error": "..."}`.
+#[derive(Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: String,
+}
+
+/// A runner submitting jobs to an HTTP job API.
+pub struct Remote {
+    name: String,
+    description: String,
+    config: RemoteConfig,
+    agent: ureq::Agent,
+}
+
+impl Remote {
+    pub fn new(name: &str, config: RemoteConfig) -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout(Duration::from_secs(config.timeout_s))
+            .build();
+        Self {
+            name: name.to_string(),
+            description: config
+                .description
+                .clone()
+                .unwrap_or_else(|| format!("Remote job API at {}", config.url)),
+            config,
+            agent,
+        }
+    }
+
+    /// The token, read when needed so that a missing variable only affects this runner.
+    fn token(&self) -> Result<Option<String>, Error> {
+        match (&self.config.token, &self.config.token_env) {
+            (Some(token), _) => Ok(Some(token.clone())),
+            (None, Some(var)) => std::env::var(var)
+                .map(Some)
+                .map_err(|_| Error::MissingToken(var.clone())),
+            (None, None) => Ok(None),
+        }
+    }
+
+    /// Send a request, retrying on network errors and on 429 and 5xx.
+    ///
+    /// Requests which are not idempotent must carry an `idempotency_key`, so that retries
+    /// do not repeat their effect.
+    fn request<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&SubmitRequest>,
+        idempotency_key: Option<&str>,
+    ) -> Result<T, Error> {
+        let url = format!("{}{path}", self.config.url.trim_end_matches('/'));
+        let token = self.token()?;
+        let mut delay = Duration::from_millis(self.config.retry_delay_ms);
+        let mut attempt = 0;
+        loop {
+            let mut request = self.agent.request(method, &url);
+            if let Some(ref token) = token {
+                request = request.set("Authorization", &format!("Bearer {token}"));
+            }
+            if let Some(key) = idempotency_key {
+                request = request.set("Idempotency-Key", key);
+            }
+            let result = match body {
+                Some(body) => request.send_json(body),
+                None => request.call(),
+            };
+            let err = match result {
+                Ok(response) => {
+                    return response
+                        .into_json()
+                        .map_err(|source| Error::InvalidResponse { url, source })
+                }
+                Err(ureq::Error::Status(status, response)) => {
+                    let body = response.into_string().unwrap_or_default();
+                    let message = match serde_json::from_str::<ErrorResponse>(&body) {
+                        Ok(ErrorResponse { error }) => error,
+                        Err(_) => body,
+                    };
+                    let err = Error::Status {
+                        url: url.clone(),
+                        status,
+                        message,
+                    };
+                    if status != 429 && status < 500 {
+                        return Err(err);
+                    }
+                    err
+                }
+                Err(ureq::Error::Transport(source)) => Error::Transport {
+                    url: url.clone(),
+                    source: Box::new(source),
+                },
+            };
+            if attempt >= self.config.retries {
+                return Err(err);
+            }
+            attempt += 1;
+            eprintln!("...{err}, retrying in {delay:?}");
+            thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+
+    fn job(&self, job: &JobId) -> Result<JobResponse, Error> {
+        self.request("GET", &format!("/jobs/{job}"), None, None)
+    }
+
+    fn results(&self, job: &JobId) -> Result<Output, Error> {
+        let mut output: Output =
+            self.request("GET", &format!("/jobs/{job}/results"), None, None)?;
+        output
+            .samples
+            .sort_by(|a, b| Ord::cmp(&b.instances, &a.instances));
+        output
+            .metadata
+            .insert("job".to_string(), job.0.clone().into());
+        Ok(output)
+    }
+
+    fn failed(&self, source: Error) -> super::Error {
+        super::Error::Failed {
+            runner: self.name.clone(),
+            source: source.into(),
+        }
+    }
+}
+
+impl Runner for Remote {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            max_qubits: self.config.max_qubits,
+            features: [
+                Feature::ConstantWaveforms,
+                Feature::InterpolatedWaveforms,
+                Feature::MultiplePulses,
+                Feature::OtherOperations,
+            ]
+            .into_iter()
+            .collect(),
+            shots: true,
+            seed: true,
+            asynchronous: true,
+        }
+    }
+
+    fn run(&self, sequence: &Sequence, options: &Options) -> Result<Output, super::Error> {
+        let job = self.submit(sequence, options)?;
+        eprintln!("...submitted job {job} to {}", self.name);
+        let timeout = Duration::from_secs(self.config.job_timeout_s);
+        let start = Instant::now();
+        loop {
+            match self.status(&job)? {
+                Status::Done(output) => return Ok(output),
+                Status::Failed(message) => return Err(self.failed(Error::JobFailed(job, message))),
+                Status::Cancelled => return Err(self.failed(Error::JobCancelled(job))),
+                Status::Pending | Status::Running => {}
+            }
+            if start.elapsed() > timeout {
+                if let Err(err) = self.cancel(&job) {
+                    eprintln!("...could not cancel job {job}: {err:#}");
+                }
+                return Err(self.failed(Error::Timeout { job, timeout }));
+            }
+            thread::sleep(Duration::from_millis(self.config.poll_interval_ms));
+        }
+    }
+
+    fn submit(&self, sequence: &Sequence, options: &Options) -> Result<JobId, super::Error> {
+        let body = SubmitRequest {
+            sequence: serde_json::to_value(sequence)
+                .map_err(|err| self.failed(Error::Sequence(err)))?,
+            shots: options.shots,
+            seed: options.seed,
+        };
+        let key = format!("{:032x}", rand::random::<u128>());
+        let response: JobResponse = self
+            .request("POST", "/jobs", Some(&body), Some(&key))
+            .map_err(|err| self.failed(err))?;
+        Ok(response.id)
+    }
+
+    fn status(&self, job: &JobId) -> Result<Status, super::Error> {
+        let response = self.job(job).map_err(|err| self.failed(err))?;
+        Ok(match response.status {
+            JobState::Pending => Status::Pending,
+            JobState::Running => Status::Running,
+            JobState::Done => Status::Done(self.results(job).map_err(|err| self.failed(err))?),
+            JobState::Failed => Status::Failed(response.error.unwrap_or_default()),
+            JobState::Cancelled => Status::Cancelled,
+        })
+    }
+
+    fn cancel(&self, job: &JobId) -> Result<(), super::Error> {
+        let _: JobResponse = self
+            .request("DELETE", &format!("/jobs/{job}"), None, None)
+            .map_err(|err| self.failed(err))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+//! The emulator of Pulser Studio, compiled to WebAssembly and embedded in qlafoutea.
+
+use super::{Capabilities, Error, Feature, Options, Output, Runner};
+use crate::{backend::pulser::sequence::Sequence, studio};
+
+pub struct PulserStudio;
+
+impl Runner for PulserStudio {
+    fn name(&self) -> &str {
+        "pulser-studio"
+    }
+
+    fn description(&self) -> &str {
+        "Pulser Studio's emulator, embedded (experimental: results cannot be read yet)"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            max_qubits: None,
+            features: [
+                Feature::ConstantWaveforms,
+                Feature::InterpolatedWaveforms,
+                Feature::MultiplePulses,
+            ]
+            .into_iter()
+            .collect(),
+            shots: false,
+            seed: false,
+            asynchronous: false,
+        }
+    }
+
+    fn run(&self, sequence: &Sequence, _options: &Options) -> Result<Output, Error> {
+        let failed = |source| Error::Failed {
+            runner: self.name().to_string(),
+            source,
+        };
+        let studio = studio::Runner::new().map_err(failed)?;
+        let mut simulator = studio.simulator().map_err(failed)?;
+        simulator
+            .simulate_sequence(
+                sequence
+                    .bind(&Default::default())
+                    .map_err(|err| failed(err.into()))?,
+            )
+            .map_err(failed)?;
+        Err(failed(anyhow::anyhow!(
+            "reading results from Pulser Studio is not supported yet"
+        )))
+    }
+}
@@ -0,0 +1,240 @@
+//! Parameter sweeps: compile and run a problem for every combination of a set of options.
+
+use std::{io::Write, time::Instant};
+
+use itertools::Itertools;
+use rayon::prelude::*;
+
+use crate::{
+    backend::qubo::{Constraints, Normalization},
+    runtime::run::Sample,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("expected `name=values`, got `{0}`")]
+    MissingName(String),
+
+    #[error("invalid values for {name}: `{values}`, expected e.g. `1,2,5`, `0..8` or `0..1:0.25`")]
+    InvalidValues { name: String, values: String },
+
+    #[error("{0} is swept more than once")]
+    Duplicate(String),
+}
+
+/// One of the options we sweep over, with the values it takes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Axis {
+    pub name: String,
+    pub values: Vec<f64>,
+}
+
+impl Axis {
+    /// Parse `name=values`, where values are either
+    /// - a comma-separated list, e.g. `seed=1,2,5`;
+    /// - a half-open range of integers, e.g. `seed=0..8`;
+    /// - a half-open range with a step, e.g. `min-quality=0.1..0.5:0.1`.
+    pub fn parse(source: &str) -> Result<Self, Error> {
+        let Some((name, values)) = source.split_once('=') else {
+            return Err(Error::MissingName(source.to_string()));
+        };
+        let (name, values) = (name.trim(), values.trim());
+        let invalid = || Error::InvalidValues {
+            name: name.to_string(),
+            values: values.to_string(),
+        };
+        let parse = |s: &str| s.trim().parse::<f64>().map_err(|_| invalid());
+        let values = match values.split_once("..") {
+            Some((start, rest)) => {
+                let (end, step) = match rest.split_once(':') {
+                    Some((end, step)) => (parse(end)?, parse(step)?),
+                    None => (parse(rest)?, 1.),
+                };
+                let start = parse(start)?;
+                if step <= 0. || end < start {
+                    return Err(invalid());
+                }
+                // Count steps rather than accumulate them, to avoid rounding drift.
+                let count = ((end - start) / step - 1e-9).ceil().max(0.) as usize;
+                (0..count).map(|k| start + k as f64 * step).collect_vec()
+            }
+            None => values.split(',').map(parse).collect::<Result<_, _>>()?,
+        };
+        if values.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            values,
+        })
+    }
+}
+
+/// One configuration, i.e. a value for each axis, in the order of the axes.
+pub type Point = Vec<(String, f64)>;
+
+/// All the combinations of values of `axes`.
+pub fn grid(axes: &[Axis]) -> Result<Vec<Point>, Error> {
+    if let Some(duplicate) = axes.iter().map(|axis| &axis.name).duplicates().next() {
+        return Err(Error::Duplicate(duplicate.clone()));
+    }
+    Ok(axes
+        .iter()
+        .map(|axis| axis.values.iter().map(|v| (axis.name.clone(), *v)))
+        .multi_cartesian_product()
+        .collect())
+}
+
+/// The outcome of compiling and running one configuration.
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    /// The quality of the layout.
+    pub quality: Option<f64>,
+
+    /// The fraction of samples which are optimal solutions, if the problem is small enough
+    /// to enumerate them.
+    pub success_probability: Option<f64>,
+
+    /// The lowest QUBO energy among samples, in problem units.
+    pub best_energy: Option<f64>,
+
+    /// Wall-clock time spent compiling and running, in seconds.
+    pub runtime_s: f64,
+
+    /// If compiling or running failed, why.
+    pub error: Option<String>,
+}
+
+impl Summary {
+    /// Measure a set of samples against the problem.
+    ///
+    /// If `constraints` were normalized, energies are converted back to problem units.
+    pub fn from_samples(
+        constraints: &Constraints,
+        normalization: Option<&Normalization>,
+        quality: f64,
+        samples: &[Sample],
+    ) -> Result<Self, anyhow::Error> {
+        let mut best_energy = None::<f64>;
+        for sample in samples {
+            let mut energy = constraints.energy(&sample.bitstring)?;
+            if let Some(normalization) = normalization {
+                energy = normalization.to_problem_units(energy);
+            }
+            best_energy = Some(best_energy.map_or(energy, |best| best.min(energy)));
+        }
+        let success_probability = match constraints.ground_states() {
+            Err(_) => None,
+            Ok(ground_states) => {
+                let total = samples.iter().map(|s| s.instances).sum::<u64>();
+                let hits = samples
+                    .iter()
+                    .filter(|s| ground_states.contains(&s.bitstring))
+                    .map(|s| s.instances)
+                    .sum::<u64>();
+                (total > 0).then(|| hits as f64 / total as f64)
+            }
+        };
+        Ok(Self {
+            quality: Some(quality),
+            success_probability,
+            best_energy,
+            runtime_s: 0.,
+            error: None,
+        })
+    }
+}
+
+/// Evaluate `configure` on every point, in parallel.
+///
+/// Failures are recorded in the summary of their point rather than interrupting the sweep.
+pub fn run<F>(points: &[Point], configure: F) -> Vec<Summary>
+where
+    F: Fn(&Point) -> Result<Summary, anyhow::Error> + Sync,
+{
+    points
+        .par_iter()
+        .map(|point| {
+            let start = Instant::now();
+            let mut summary = configure(point).unwrap_or_else(|err| Summary {
+                error: Some(format!("{err:#}")),
+                ..Summary::default()
+            });
+            summary.runtime_s = start.elapsed().as_secs_f64();
+            summary
+        })
+        .collect()
+}
+
+/// Write one line per configuration, as CSV.
+pub fn write_csv(
+    axes: &[Axis],
+    points: &[Point],
+    summaries: &[Summary],
+    out: impl Write,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::Writer::from_writer(out);
+    let mut header = axes.iter().map(|axis| axis.name.as_str()).collect_vec();
+    header.extend([
+        "quality",
+        "success_probability",
+        "best_energy",
+        "runtime_s",
+        "error",
+    ]);
+    writer.write_record(&header)?;
+    let optional = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+    for (point, summary) in points.iter().zip(summaries) {
+        let mut record = point.iter().map(|(_, v)| v.to_string()).collect_vec();
+        record.extend([
+            optional(summary.quality),
+            optional(summary.success_probability),
+            optional(summary.best_energy),
+            format!("{:.3}", summary.runtime_s),
+            summary.error.clone().unwrap_or_default(),
+        ]);
+        writer.write_record(&record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[test]
+fn test_sweep_grid() {
+    let seed = Axis::parse("seed=0..3").unwrap();
+    assert_eq!(seed.values, vec![0., 1., 2.]);
+    let quality = Axis::parse("min-quality = 0.1..0.3:0.1").unwrap();
+    assert_eq!(quality.values.len(), 2);
+    let duration = Axis::parse("half-duration-ns=2000,4000").unwrap();
+    assert!(Axis::parse("seed").is_err());
+    assert!(Axis::parse("seed=3..0").is_err());
+
+    let axes = [seed, duration];
+    let points = grid(&axes).unwrap();
+    assert_eq!(points.len(), 6);
+    assert_eq!(
+        points[1],
+        vec![
+            ("seed".to_string(), 0.),
+            ("half-duration-ns".to_string(), 4000.)
+        ]
+    );
+    assert!(grid(&[axes[0].clone(), axes[0].clone()]).is_err());
+
+    let summaries = run(&points, |point| {
+        if point[0].1 == 2. {
+            Err(anyhow::anyhow!("no layout"))
+        } else {
+            Ok(Summary::default())
+        }
+    });
+    let mut out = vec![];
+    write_csv(&axes, &points, &summaries, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines = out.lines().collect_vec();
+    assert_eq!(
+        lines[0],
+        "seed,half-duration-ns,quality,success_probability,best_energy,runtime_s,error"
+    );
+    assert!(lines[6].ends_with(",no layout"));
+}
@@ -2,16 +2,33 @@ pub mod units;
 
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
+
 /// A quality level in [0, 1]
-#[derive(derive_more::Into, PartialEq, Clone, Copy, Debug)]
+#[derive(derive_more::Into, PartialEq, Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(try_from = "f64", into = "f64")]
 pub struct Quality(f64);
 
+#[derive(thiserror::Error, Debug)]
+#[error("quality must be in [0, 1], got {0}")]
+pub struct InvalidQuality(pub f64);
+
+impl TryFrom<f64> for Quality {
+    type Error = InvalidQuality;
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Quality::new(value)
+    }
+}
+
 impl Quality {
     pub const WORST: Quality = Quality(0.);
     pub const BEST: Quality = Quality(1.);
-    pub fn new(value: f64) -> Self {
-        assert!((0. ..=1.).contains(&value));
-        Quality(value)
+    pub fn new(value: f64) -> Result<Self, InvalidQuality> {
+        if (0. ..=1.).contains(&value) {
+            Ok(Quality(value))
+        } else {
+            Err(InvalidQuality(value))
+        }
     }
 }
 
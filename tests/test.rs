@@ -4,14 +4,15 @@ use itertools::Itertools;
 use qlafoutea::{
     backend::{
         device::Device,
+        pulser::sequence::Sequence,
         qaa,
         qubo::{self, Constraints},
     },
-    runtime::run::run_python,
+    runtime::runner::python::run_python,
     types::Quality,
 };
 
-fn qubo_compile() -> String {
+fn qubo_compile_sequence(parametrize: bool) -> Sequence {
     let half_duration_ns = 4_000;
     let constraints = Constraints::try_new(
         5,
@@ -51,15 +52,19 @@ fn qubo_compile() -> String {
 
     let device = Device::analog();
 
-    let (register, quality, _) = constraints
+    let qubo::Layout {
+        register, quality, ..
+    } = constraints
         .layout(
             &device,
             &qubo::Options {
-                min_quality: Quality::new(0.1),
+                min_quality: Quality::new(0.1).unwrap(),
                 seed: 75,
                 max_iters: 1_000,
+                max_seeds: 10_000,
                 overflow_protection_factor: 0.95,
                 overflow_protection_threshold: 1_000.,
+                acceptance: Default::default(),
             },
         )
         .expect("Failed to compile qubo");
@@ -70,15 +75,23 @@ fn qubo_compile() -> String {
     );
 
     // Step: integrate QAA.
-    let sequence = qaa::compile(
+    qaa::compile(
         &constraints,
         device,
         register,
         &qaa::Options {
-            half_duration_ns: half_duration_ns as f64,
+            half_duration_ns: Some(half_duration_ns as f64),
+            derive_sweep: false,
+            parametrize,
+            schedule: qaa::Schedule::default(),
         },
-    );
+    )
+    .unwrap()
+    .0
+}
 
+fn qubo_compile() -> String {
+    let sequence = qubo_compile_sequence(false);
     serde_json::to_string_pretty(&sequence).unwrap()
 }
 
@@ -88,6 +101,27 @@ fn test_qubo_compile() {
     println!("{json}");
 }
 
+#[test]
+fn test_qubo_compile_parametrized() {
+    let sequence = qubo_compile_sequence(true);
+    let json = serde_json::to_string(&sequence).unwrap();
+    let sequence: Sequence = serde_json::from_str(&json).unwrap();
+    assert_eq!(sequence.variables().len(), 4);
+
+    let overrides = [(qaa::VAR_DURATION.to_string(), 2_000.)]
+        .into_iter()
+        .collect();
+    let bound = sequence.bind(&overrides).unwrap();
+    assert!(bound.variables().is_empty());
+    let bound = serde_json::to_value(&bound).unwrap();
+    let amplitude = &bound["operations"][0]["amplitude"];
+    assert_eq!(amplitude["duration"], 2_000.);
+    assert_eq!(amplitude["values"][0], 0.);
+
+    let overrides = [("no_such_variable".to_string(), 0.)].into_iter().collect();
+    assert!(sequence.bind(&overrides).is_err());
+}
+
 #[test]
 fn test_qubo_compile_and_run_python() {
     let json = qubo_compile();